@@ -0,0 +1,80 @@
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+use std::env;
+
+/// Source of the current date and time.
+///
+/// All date dependent logic should get the current date from a clock instead of the system, so it
+/// can be tested with arbitrary dates.
+pub trait Clock: Send + Sync {
+    /// Get the current local date and time.
+    fn now(&self) -> DateTime<Local>;
+
+    /// Get the current local date.
+    fn today(&self) -> NaiveDate {
+        self.now().naive_local().date()
+    }
+}
+
+/// Clock reading the system time.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Local> {
+        Local::now()
+    }
+}
+
+/// Clock always returning the same time.
+pub struct FixedClock(pub DateTime<Local>);
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Local> {
+        self.0
+    }
+}
+
+/// Get the clock to use.
+///
+/// Returns a [`FixedClock`] if the `MOW_NOW` environment variable contains a valid time in the
+/// format `YYYY-MM-DDTHH:MM:SS`, a [`SystemClock`] otherwise.
+pub fn from_env() -> Box<dyn Clock> {
+    let fixed = env::var("MOW_NOW")
+        .ok()
+        .and_then(|x| NaiveDateTime::parse_from_str(&x, "%Y-%m-%dT%H:%M:%S").ok())
+        .and_then(|x| Local.from_local_datetime(&x).single());
+
+    match fixed {
+        Some(x) => {
+            log::info!("using fixed time {}", x);
+            Box::new(FixedClock(x))
+        }
+        None => Box::new(SystemClock),
+    }
+}
+
+/// Get the year and month following `month` of `year`.
+pub fn next_month(year: i32, month: u32) -> (i32, u32) {
+    if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_month_wraps_around() {
+        assert_eq!(next_month(2021, 1), (2021, 2));
+        assert_eq!(next_month(2021, 11), (2021, 12));
+        assert_eq!(next_month(2021, 12), (2022, 1));
+    }
+
+    #[test]
+    fn fixed_clock_is_local() {
+        let clock = FixedClock(Local.ymd(2021, 12, 31).and_hms(23, 59, 59));
+        assert_eq!(clock.today(), NaiveDate::from_ymd(2021, 12, 31));
+    }
+}
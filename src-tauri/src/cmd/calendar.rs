@@ -0,0 +1,242 @@
+use super::error::{CmdError, ErrorPayload};
+use crate::{
+    context::Context,
+    data::calendar::{self, Closure, DayStatus, DeliveryCalendar, Holiday, State},
+    event::{Entity, Event},
+    store::StoreError,
+};
+use chrono::{NaiveDate, Weekday};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(tag = "cmd", rename_all = "camelCase")]
+pub enum CalendarCmd {
+    /// Adds a period meals aren't delivered in.
+    ///
+    /// # Success variants
+    /// - [`CalendarCmdSuccess::AddedClosure`]
+    ///
+    /// # Error variants
+    /// - [`CalendarCmdError::InvalidRange`]: if `to` is before `from`
+    /// - [`CalendarCmdError::StoreError`]: if the calendar can't be read or written
+    AddClosure {
+        from: NaiveDate,
+        to: NaiveDate,
+        reason: String,
+    },
+    /// Deletes a closure.
+    ///
+    /// # Success variants
+    /// - [`CalendarCmdSuccess::DeletedClosure`]
+    ///
+    /// # Error variants
+    /// - [`CalendarCmdError::NotFound`]: if the closure doesn't exist
+    /// - [`CalendarCmdError::StoreError`]: if the calendar can't be read or written
+    DeleteClosure { closure: Uuid },
+    /// Returns the delivery calendar.
+    ///
+    /// # Success variants
+    /// - [`CalendarCmdSuccess::GotCalendar`]
+    ///
+    /// # Error variants
+    /// - [`CalendarCmdError::StoreError`]: if the calendar can't be read
+    GetCalendar,
+    /// Returns whether meals are delivered on each day of the active datasets month.
+    ///
+    /// # Success variants
+    /// - [`CalendarCmdSuccess::GotDeliveryDays`]
+    ///
+    /// # Error variants
+    /// - [`CalendarCmdError::DatasetNotActive`]: if there is no active dataset
+    /// - [`CalendarCmdError::StoreError`]: if the calendar can't be read
+    GetDeliveryDays,
+    /// Returns the public holidays of a year, in the state selected in the calendar.
+    ///
+    /// # Success variants
+    /// - [`CalendarCmdSuccess::GotHolidays`]
+    ///
+    /// # Error variants
+    /// - [`CalendarCmdError::InvalidYear`]: if holidays can't be calculated for `year`
+    /// - [`CalendarCmdError::StoreError`]: if the calendar can't be read
+    GetHolidays { year: i32 },
+    /// Selects the state whose public holidays meals aren't delivered on.
+    ///
+    /// # Success variants
+    /// - [`CalendarCmdSuccess::SetState`]
+    ///
+    /// # Error variants
+    /// - [`CalendarCmdError::StoreError`]: if the calendar can't be read or written
+    SetState { state: Option<State> },
+    /// Sets the weekdays meals are delivered on.
+    ///
+    /// # Success variants
+    /// - [`CalendarCmdSuccess::SetWeekdays`]
+    ///
+    /// # Error variants
+    /// - [`CalendarCmdError::StoreError`]: if the calendar can't be read or written
+    SetWeekdays { weekdays: Vec<Weekday> },
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(tag = "variant", rename_all = "camelCase")]
+pub enum CalendarCmdSuccess {
+    AddedClosure {
+        uuid: Uuid,
+    },
+    DeletedClosure,
+    GotCalendar {
+        data: DeliveryCalendar,
+    },
+    GotDeliveryDays {
+        data: BTreeMap<NaiveDate, DayStatus>,
+    },
+    GotHolidays {
+        data: BTreeMap<NaiveDate, Holiday>,
+    },
+    SetState,
+    SetWeekdays,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CalendarCmdError {
+    #[error("error.global.dataset_not_active")]
+    DatasetNotActive,
+    #[error("error.calendar.invalid_range")]
+    InvalidRange { from: NaiveDate, to: NaiveDate },
+    #[error("error.calendar.invalid_year")]
+    InvalidYear { year: i32 },
+    #[error("error.calendar.not_found")]
+    NotFound { closure: Uuid },
+    #[error(transparent)]
+    StoreError(#[from] StoreError),
+}
+
+impl CmdError for CalendarCmdError {
+    fn payload(&self) -> ErrorPayload {
+        match self {
+            Self::DatasetNotActive => {
+                ErrorPayload::new("DATASET_NOT_ACTIVE", "error.global.dataset_not_active")
+            }
+            Self::InvalidRange { from, to } => {
+                ErrorPayload::new("INVALID_RANGE", "error.calendar.invalid_range")
+                    .param("from", from)
+                    .param("to", to)
+            }
+            Self::InvalidYear { year } => {
+                ErrorPayload::new("INVALID_YEAR", "error.calendar.invalid_year")
+                    .param("year", *year)
+                    .param("min", *calendar::YEARS.start())
+                    .param("max", *calendar::YEARS.end())
+            }
+            Self::NotFound { closure } => {
+                ErrorPayload::new("CLOSURE_NOT_FOUND", "error.calendar.not_found")
+                    .param("closure", closure)
+            }
+            Self::StoreError(e) => e.payload(),
+        }
+    }
+}
+
+impl super::CmdAble for CalendarCmd {
+    type Error = CalendarCmdError;
+    type Success = CalendarCmdSuccess;
+
+    fn execute(
+        self: Self,
+        ctx: &Context,
+        events: &mut Vec<Event>,
+    ) -> Result<Self::Success, Self::Error> {
+        match self {
+            Self::AddClosure { from, to, reason } => {
+                if to < from {
+                    return Err(Self::Error::InvalidRange { from, to });
+                }
+
+                let uuid = Uuid::new_v4();
+                ctx.calendar.update(|cal| {
+                    cal.closures.insert(
+                        uuid,
+                        Closure {
+                            from,
+                            to,
+                            reason: reason.trim().to_string(),
+                        },
+                    );
+                    Ok::<_, Self::Error>(())
+                })?;
+                events.push(Event::Updated {
+                    entity: Entity::Calendar,
+                });
+                log::info!("added closure {} from {} to {}", uuid, from, to);
+
+                Ok(Self::Success::AddedClosure { uuid })
+            }
+            Self::DeleteClosure { closure } => {
+                ctx.calendar.update(|cal| {
+                    cal.closures
+                        .remove(&closure)
+                        .ok_or(Self::Error::NotFound { closure })
+                })?;
+                events.push(Event::Updated {
+                    entity: Entity::Calendar,
+                });
+                log::info!("deleted closure {}", closure);
+
+                Ok(Self::Success::DeletedClosure)
+            }
+            Self::GetCalendar => Ok(Self::Success::GotCalendar {
+                data: ctx.calendar.read()?,
+            }),
+            Self::GetDeliveryDays => {
+                let cal = ctx.calendar.read()?;
+
+                if let Some((data, _)) = &*ctx.data.read().expect("failed to get data read access")
+                {
+                    Ok(Self::Success::GotDeliveryDays {
+                        data: data.days().map(|d| (d, cal.status(d))).collect(),
+                    })
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+            Self::GetHolidays { year } => {
+                if !calendar::YEARS.contains(&year) {
+                    return Err(Self::Error::InvalidYear { year });
+                }
+
+                Ok(Self::Success::GotHolidays {
+                    data: match ctx.calendar.read()?.state {
+                        Some(state) => calendar::holidays(state, year),
+                        None => BTreeMap::new(),
+                    },
+                })
+            }
+            Self::SetState { state } => {
+                ctx.calendar.update(|cal| {
+                    cal.state = state;
+                    Ok::<_, Self::Error>(())
+                })?;
+                events.push(Event::Updated {
+                    entity: Entity::Calendar,
+                });
+
+                Ok(Self::Success::SetState)
+            }
+            Self::SetWeekdays { mut weekdays } => {
+                weekdays.sort_by_key(|d| d.num_days_from_monday());
+                weekdays.dedup();
+
+                ctx.calendar.update(|cal| {
+                    cal.weekdays = weekdays;
+                    Ok::<_, Self::Error>(())
+                })?;
+                events.push(Event::Updated {
+                    entity: Entity::Calendar,
+                });
+
+                Ok(Self::Success::SetWeekdays)
+            }
+        }
+    }
+}
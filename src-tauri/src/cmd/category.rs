@@ -0,0 +1,270 @@
+use super::error::{CmdError, ErrorPayload};
+use crate::{
+    context::Context,
+    data::category::{check_meals, meals_out_of_limits, Category, LimitError},
+    event::{Entity, Event},
+};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(tag = "cmd", rename_all = "camelCase")]
+pub enum CategoryCmd {
+    /// Adds a new category.
+    ///
+    /// # Success variants
+    /// - [`CategoryCmdSuccess::AddedCategory`]
+    ///
+    /// # Error variants
+    /// - [`CategoryCmdError::DatasetNotActive`]: if there is no active dataset
+    /// - [`CategoryCmdError::EmptyName`]: if the specified name is empty
+    /// - [`CategoryCmdError::InvalidLimits`]: if `max` is lower than `min`
+    AddCategory {
+        name: String,
+        min: u32,
+        max: Option<u32>,
+    },
+    /// Gets all categories.
+    ///
+    /// # Success variants
+    /// - [`CategoryCmdSuccess::GotCategories`]
+    ///
+    /// # Error variants
+    /// - [`CategoryCmdError::DatasetNotActive`]: if there is no active dataset
+    GetCategories,
+    /// Changes the name and limits of a category.
+    ///
+    /// Meals that kept to the old limits have to keep to the new ones as well.
+    ///
+    /// # Success variants
+    /// - [`CategoryCmdSuccess::UpdatedCategory`]
+    ///
+    /// # Error variants
+    /// - [`CategoryCmdError::DatasetNotActive`]: if there is no active dataset
+    /// - [`CategoryCmdError::EmptyName`]: if the specified name is empty
+    /// - [`CategoryCmdError::InvalidLimits`]: if `max` is lower than `min`
+    /// - [`CategoryCmdError::LimitError`]: if a meal would no longer keep to the limits
+    /// - [`CategoryCmdError::NotFound`]: if the category doesn't exist
+    UpdateCategory {
+        category: Uuid,
+        name: String,
+        min: u32,
+        max: Option<u32>,
+    },
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(tag = "variant", rename_all = "camelCase")]
+pub enum CategoryCmdSuccess {
+    AddedCategory,
+    GotCategories { data: BTreeMap<Uuid, Category> },
+    UpdatedCategory,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CategoryCmdError {
+    #[error("error.global.dataset_not_active")]
+    DatasetNotActive,
+    #[error("error.categories.name_empty")]
+    EmptyName,
+    #[error("error.categories.invalid_limits")]
+    InvalidLimits { min: u32, max: u32 },
+    #[error(transparent)]
+    LimitError(#[from] LimitError),
+    #[error("error.categories.not_found")]
+    NotFound { category: Uuid },
+}
+
+impl CmdError for CategoryCmdError {
+    fn payload(&self) -> ErrorPayload {
+        match self {
+            Self::DatasetNotActive => {
+                ErrorPayload::new("DATASET_NOT_ACTIVE", "error.global.dataset_not_active")
+            }
+            Self::EmptyName => ErrorPayload::new("NAME_EMPTY", "error.categories.name_empty"),
+            Self::InvalidLimits { min, max } => {
+                ErrorPayload::new("INVALID_LIMITS", "error.categories.invalid_limits")
+                    .param("min", *min)
+                    .param("max", *max)
+            }
+            Self::LimitError(e) => e.payload(),
+            Self::NotFound { category } => {
+                ErrorPayload::new("CATEGORY_NOT_FOUND", "error.categories.not_found")
+                    .param("category", category)
+            }
+        }
+    }
+}
+
+impl super::CmdAble for CategoryCmd {
+    type Error = CategoryCmdError;
+    type Success = CategoryCmdSuccess;
+
+    fn execute(
+        self: Self,
+        ctx: &Context,
+        events: &mut Vec<Event>,
+    ) -> Result<Self::Success, Self::Error> {
+        match self {
+            Self::AddCategory { name, min, max } => {
+                let name = name.trim();
+                if name.is_empty() {
+                    return Err(Self::Error::EmptyName);
+                }
+                check_limits(min, max)?;
+
+                if let Some((data, _)) =
+                    &mut *ctx.data.write().expect("failed to get data write access")
+                {
+                    let uuid = Uuid::new_v4();
+                    data.categories
+                        .insert(uuid, Category::new(name.to_string(), min, max));
+                    events.push(Event::Added {
+                        entity: Entity::Category { uuid },
+                    });
+                    log::debug!("added category {}", uuid);
+
+                    Ok(Self::Success::AddedCategory)
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+            Self::GetCategories => {
+                if let Some((data, _)) = &*ctx.data.read().expect("failed to get data read access")
+                {
+                    Ok(Self::Success::GotCategories {
+                        data: data.categories.clone(),
+                    })
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+            Self::UpdateCategory {
+                category,
+                name,
+                min,
+                max,
+            } => {
+                let name = name.trim();
+                if name.is_empty() {
+                    return Err(Self::Error::EmptyName);
+                }
+                check_limits(min, max)?;
+
+                if let Some((data, _)) =
+                    &mut *ctx.data.write().expect("failed to get data write access")
+                {
+                    let before = meals_out_of_limits(data, category);
+                    let cat = data
+                        .categories
+                        .get_mut(&category)
+                        .ok_or(Self::Error::NotFound { category })?;
+                    let old = cat.clone();
+                    cat.name = name.to_string();
+                    cat.min = min;
+                    cat.max = max;
+
+                    if let Err(e) = check_meals(data, category, &before) {
+                        data.categories.insert(category, old);
+                        return Err(e.into());
+                    }
+                    events.push(Event::Updated {
+                        entity: Entity::Category { uuid: category },
+                    });
+                    log::debug!("updated category {}", category);
+
+                    Ok(Self::Success::UpdatedCategory)
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+        }
+    }
+}
+
+/// Check that `max` isn't lower than `min`.
+fn check_limits(min: u32, max: Option<u32>) -> Result<(), CategoryCmdError> {
+    match max {
+        Some(max) if max < min => Err(CategoryCmdError::InvalidLimits { min, max }),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cmd::{global::GlobalCmd, run},
+        data::{component::Component, meal::Meal},
+    };
+    use chrono::{Local, TimeZone};
+    use tempfile::TempDir;
+
+    /// Create a context with an active dataset containing a category allowing one component per
+    /// meal, and a meal made of a component of that category and one without a category.
+    ///
+    /// Returns the context, the category and the component without a category.
+    fn context(dir: &TempDir) -> (Context, Uuid, Uuid) {
+        let ctx = Context::in_dir(dir.path(), Local.ymd(2021, 3, 1).and_hms(12, 0, 0));
+        run(
+            &ctx,
+            GlobalCmd::NewDataset {
+                next_month: false,
+                template: None,
+            },
+            &mut Vec::new(),
+        )
+        .unwrap();
+
+        let (category, soup, bread) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        if let Some((data, _)) = &mut *ctx.data.write().unwrap() {
+            data.categories
+                .insert(category, Category::new("soups".to_string(), 0, Some(1)));
+            for (uuid, name, category) in &[(soup, "soup", Some(category)), (bread, "bread", None)]
+            {
+                data.components.insert(
+                    *uuid,
+                    Component::new(
+                        name.to_string(),
+                        *category,
+                        BTreeMap::new(),
+                        BTreeMap::new(),
+                    ),
+                );
+            }
+            let parts = vec![(soup, None), (bread, None)].into_iter().collect();
+            data.meals.insert(
+                Uuid::new_v4(),
+                Meal::new("lunch".to_string(), String::new(), parts),
+            );
+        }
+
+        (ctx, category, bread)
+    }
+
+    fn update(ctx: &Context, category: Uuid, min: u32) -> Result<(), CategoryCmdError> {
+        let cmd = CategoryCmd::UpdateCategory {
+            category,
+            name: "soups".to_string(),
+            min,
+            max: None,
+        };
+        run(ctx, cmd, &mut Vec::new()).map(|_| ())
+    }
+
+    #[test]
+    fn updates_keep_meals_within_limits() {
+        let dir = tempfile::tempdir().expect("failed to create temporary directory");
+        let (ctx, category, _) = context(&dir);
+
+        update(&ctx, category, 1).unwrap();
+        assert!(matches!(
+            update(&ctx, category, 2),
+            Err(CategoryCmdError::LimitError(LimitError { count: 1, .. }))
+        ));
+
+        let data = ctx.data.read().unwrap();
+        let (data, _) = data.as_ref().unwrap();
+        assert_eq!(data.categories[&category].min, 1);
+    }
+}
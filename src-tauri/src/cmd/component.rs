@@ -1,8 +1,18 @@
+use super::error::{CmdError, ErrorPayload};
 use crate::{
-    data::component::{Component, Option, Variant},
-    DATA,
+    context::Context,
+    data::{
+        allergen::Allergen,
+        category::{check_meals, meals_out_of_limits, LimitError},
+        component::{Component, Option, Variant},
+        nutrition::Nutrition,
+        order::{self, Movement},
+        Data,
+    },
+    event::{Entity, Event},
+    store::StoreError,
 };
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use uuid::Uuid;
 
 #[derive(Debug, serde::Deserialize)]
@@ -14,10 +24,13 @@ pub enum ComponentCmd {
     /// - [`ComponentCmdSuccess::AddedComponent`]
     ///
     /// # Error variants
+    /// - [`ComponentCmdError::CategoryNotFound`]: if the specified category doesn't exist
     /// - [`ComponentCmdError::DatasetNotActive`]: if there is no active dataset
     /// - [`ComponentCmdError::EmptyName`]: if the specified name is empty
     AddComponent {
         name: String,
+        #[serde(default)]
+        category: std::option::Option<Uuid>,
         variants: Vec<String>,
         options: Vec<String>,
     },
@@ -47,6 +60,182 @@ pub enum ComponentCmd {
     /// # Error variants
     /// - [`ComponentCmdError::DatasetNotActive`]: if there is no active dataset
     GetComponents,
+    /// Changes the display position of a component.
+    ///
+    /// # Success variants
+    /// - [`ComponentCmdSuccess::MovedComponent`]
+    ///
+    /// # Error variants
+    /// - [`ComponentCmdError::DatasetNotActive`]: if there is no active dataset
+    /// - [`ComponentCmdError::NotFound`]: if the component doesn't exist
+    MoveComponent { component: Uuid, movement: Movement },
+    /// Changes the display position of an option within its component.
+    ///
+    /// # Success variants
+    /// - [`ComponentCmdSuccess::MovedOption`]
+    ///
+    /// # Error variants
+    /// - [`ComponentCmdError::DatasetNotActive`]: if there is no active dataset
+    /// - [`ComponentCmdError::NotFound`]: if the component doesn't exist
+    /// - [`ComponentCmdError::OptionNotFound`]: if the option doesn't exist
+    MoveOption {
+        component: Uuid,
+        option: Uuid,
+        movement: Movement,
+    },
+    /// Changes the display position of a variant within its component.
+    ///
+    /// # Success variants
+    /// - [`ComponentCmdSuccess::MovedVariant`]
+    ///
+    /// # Error variants
+    /// - [`ComponentCmdError::DatasetNotActive`]: if there is no active dataset
+    /// - [`ComponentCmdError::NotFound`]: if the component doesn't exist
+    /// - [`ComponentCmdError::VariantNotFound`]: if the variant doesn't exist
+    MoveVariant {
+        component: Uuid,
+        variant: Uuid,
+        movement: Movement,
+    },
+    /// Replaces the allergens and additives of an option.
+    ///
+    /// # Success variants
+    /// - [`ComponentCmdSuccess::SetAllergens`]
+    ///
+    /// # Error variants
+    /// - [`ComponentCmdError::DatasetNotActive`]: if there is no active dataset
+    /// - [`ComponentCmdError::NotFound`]: if the component doesn't exist
+    /// - [`ComponentCmdError::OptionNotFound`]: if the option doesn't exist
+    SetOptionAllergens {
+        component: Uuid,
+        option: Uuid,
+        allergens: BTreeSet<Allergen>,
+        additives: BTreeSet<u32>,
+    },
+    /// Replaces the allergens and additives of a variant.
+    ///
+    /// # Success variants
+    /// - [`ComponentCmdSuccess::SetAllergens`]
+    ///
+    /// # Error variants
+    /// - [`ComponentCmdError::DatasetNotActive`]: if there is no active dataset
+    /// - [`ComponentCmdError::NotFound`]: if the component doesn't exist
+    /// - [`ComponentCmdError::VariantNotFound`]: if the variant doesn't exist
+    SetVariantAllergens {
+        component: Uuid,
+        variant: Uuid,
+        allergens: BTreeSet<Allergen>,
+        additives: BTreeSet<u32>,
+    },
+    /// Replaces the diets an option is suitable for.
+    ///
+    /// # Success variants
+    /// - [`ComponentCmdSuccess::SetDiets`]
+    ///
+    /// # Error variants
+    /// - [`ComponentCmdError::DatasetNotActive`]: if there is no active dataset
+    /// - [`ComponentCmdError::DietNotFound`]: if one of the diets doesn't exist
+    /// - [`ComponentCmdError::NotFound`]: if the component doesn't exist
+    /// - [`ComponentCmdError::OptionNotFound`]: if the option doesn't exist
+    /// - [`ComponentCmdError::StoreError`]: if the diets can't be read
+    SetOptionDiets {
+        component: Uuid,
+        option: Uuid,
+        diets: BTreeSet<Uuid>,
+    },
+    /// Replaces the nutrition values of an option.
+    ///
+    /// # Success variants
+    /// - [`ComponentCmdSuccess::SetNutrition`]
+    ///
+    /// # Error variants
+    /// - [`ComponentCmdError::DatasetNotActive`]: if there is no active dataset
+    /// - [`ComponentCmdError::NotFound`]: if the component doesn't exist
+    /// - [`ComponentCmdError::OptionNotFound`]: if the option doesn't exist
+    SetOptionNutrition {
+        component: Uuid,
+        option: Uuid,
+        nutrition: Nutrition,
+    },
+    /// Replaces the diets a variant is suitable for.
+    ///
+    /// # Success variants
+    /// - [`ComponentCmdSuccess::SetDiets`]
+    ///
+    /// # Error variants
+    /// - [`ComponentCmdError::DatasetNotActive`]: if there is no active dataset
+    /// - [`ComponentCmdError::DietNotFound`]: if one of the diets doesn't exist
+    /// - [`ComponentCmdError::NotFound`]: if the component doesn't exist
+    /// - [`ComponentCmdError::StoreError`]: if the diets can't be read
+    /// - [`ComponentCmdError::VariantNotFound`]: if the variant doesn't exist
+    SetVariantDiets {
+        component: Uuid,
+        variant: Uuid,
+        diets: BTreeSet<Uuid>,
+    },
+    /// Replaces the nutrition values of a variant.
+    ///
+    /// # Success variants
+    /// - [`ComponentCmdSuccess::SetNutrition`]
+    ///
+    /// # Error variants
+    /// - [`ComponentCmdError::DatasetNotActive`]: if there is no active dataset
+    /// - [`ComponentCmdError::NotFound`]: if the component doesn't exist
+    /// - [`ComponentCmdError::VariantNotFound`]: if the variant doesn't exist
+    SetVariantNutrition {
+        component: Uuid,
+        variant: Uuid,
+        nutrition: Nutrition,
+    },
+    /// Sets the surcharge of an option.
+    ///
+    /// # Success variants
+    /// - [`ComponentCmdSuccess::SetSurcharge`]
+    ///
+    /// # Error variants
+    /// - [`ComponentCmdError::DatasetNotActive`]: if there is no active dataset
+    /// - [`ComponentCmdError::NotFound`]: if the component doesn't exist
+    /// - [`ComponentCmdError::OptionNotFound`]: if the option doesn't exist
+    SetOptionSurcharge {
+        component: Uuid,
+        option: Uuid,
+        /// The surcharge in cents. Negative for discounts.
+        surcharge: i64,
+    },
+    /// Sets the surcharge of a variant.
+    ///
+    /// # Success variants
+    /// - [`ComponentCmdSuccess::SetSurcharge`]
+    ///
+    /// # Error variants
+    /// - [`ComponentCmdError::DatasetNotActive`]: if there is no active dataset
+    /// - [`ComponentCmdError::NotFound`]: if the component doesn't exist
+    /// - [`ComponentCmdError::VariantNotFound`]: if the variant doesn't exist
+    SetVariantSurcharge {
+        component: Uuid,
+        variant: Uuid,
+        /// The surcharge in cents. Negative for discounts.
+        surcharge: i64,
+    },
+    /// Changes the category of a component.
+    ///
+    /// Meals containing the component that kept to the limits of the old and the new category
+    /// have to keep to them afterwards as well.
+    ///
+    /// # Success variants
+    /// - [`ComponentCmdSuccess::SetCategory`]
+    ///
+    /// # Error variants
+    /// - [`ComponentCmdError::CategoryNotFound`]: if the specified category doesn't exist
+    /// - [`ComponentCmdError::DatasetNotActive`]: if there is no active dataset
+    /// - [`ComponentCmdError::LimitError`]: if a meal would no longer keep to the limits of one of
+    ///   the categories
+    /// - [`ComponentCmdError::NotFound`]: if the component doesn't exist
+    SetCategory {
+        component: Uuid,
+        /// The new category. `None` removes the component from its category.
+        category: std::option::Option<Uuid>,
+    },
 }
 
 #[derive(Debug, serde::Serialize)]
@@ -55,27 +244,90 @@ pub enum ComponentCmdSuccess {
     AddedComponent,
     AddedOption,
     AddedVariant,
-    GotComponents { data: BTreeMap<Uuid, Component> },
+    GotComponents {
+        data: BTreeMap<Uuid, Component>,
+        /// The uuids of all components, in display order.
+        order: Vec<Uuid>,
+    },
+    MovedComponent,
+    MovedOption,
+    MovedVariant,
+    SetAllergens,
+    SetCategory,
+    SetDiets,
+    SetNutrition,
+    SetSurcharge,
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum ComponentCmdError {
+    #[error("error.categories.not_found")]
+    CategoryNotFound { category: Uuid },
+    #[error("error.diets.not_found")]
+    DietNotFound { diet: Uuid },
     #[error("error.components.not_found")]
-    NotFound,
+    NotFound { component: Uuid },
     #[error("error.global.dataset_not_active")]
     DatasetNotActive,
     #[error("error.components.name_empty")]
     EmptyName,
+    #[error(transparent)]
+    LimitError(#[from] LimitError),
+    #[error("error.components.option_not_found")]
+    OptionNotFound { component: Uuid, option: Uuid },
+    #[error(transparent)]
+    StoreError(#[from] StoreError),
+    #[error("error.components.variant_not_found")]
+    VariantNotFound { component: Uuid, variant: Uuid },
+}
+
+impl CmdError for ComponentCmdError {
+    fn payload(&self) -> ErrorPayload {
+        match self {
+            Self::CategoryNotFound { category } => {
+                ErrorPayload::new("CATEGORY_NOT_FOUND", "error.categories.not_found")
+                    .param("category", category)
+            }
+            Self::DietNotFound { diet } => {
+                ErrorPayload::new("DIET_NOT_FOUND", "error.diets.not_found").param("diet", diet)
+            }
+            Self::NotFound { component } => {
+                ErrorPayload::new("COMPONENT_NOT_FOUND", "error.components.not_found")
+                    .param("component", component)
+            }
+            Self::DatasetNotActive => {
+                ErrorPayload::new("DATASET_NOT_ACTIVE", "error.global.dataset_not_active")
+            }
+            Self::EmptyName => ErrorPayload::new("NAME_EMPTY", "error.components.name_empty"),
+            Self::LimitError(e) => e.payload(),
+            Self::OptionNotFound { component, option } => {
+                ErrorPayload::new("OPTION_NOT_FOUND", "error.components.option_not_found")
+                    .param("component", component)
+                    .param("option", option)
+            }
+            Self::StoreError(e) => e.payload(),
+            Self::VariantNotFound { component, variant } => {
+                ErrorPayload::new("VARIANT_NOT_FOUND", "error.components.variant_not_found")
+                    .param("component", component)
+                    .param("variant", variant)
+            }
+        }
+    }
 }
 
 impl super::CmdAble for ComponentCmd {
     type Error = ComponentCmdError;
     type Success = ComponentCmdSuccess;
 
-    fn execute(self: Self) -> Result<Self::Success, Self::Error> {
+    fn execute(
+        self: Self,
+        ctx: &Context,
+        events: &mut Vec<Event>,
+    ) -> Result<Self::Success, Self::Error> {
         match self {
             Self::AddComponent {
                 name,
+                category,
                 variants,
                 options,
             } => {
@@ -85,20 +337,41 @@ impl super::CmdAble for ComponentCmd {
                 }
 
                 let mut v = BTreeMap::new();
+                let mut variant_order = Vec::new();
                 for variant in variants {
-                    v.insert(Uuid::new_v4(), Variant::new(variant));
+                    let uuid = Uuid::new_v4();
+                    v.insert(uuid, Variant::new(variant));
+                    variant_order.push(uuid);
                 }
 
                 let mut o = BTreeMap::new();
+                let mut option_order = Vec::new();
                 for option in options {
-                    o.insert(Uuid::new_v4(), Option::new(option));
+                    let uuid = Uuid::new_v4();
+                    o.insert(uuid, Option::new(option));
+                    option_order.push(uuid);
                 }
 
                 if let Some((data, _)) =
-                    &mut *DATA.write().expect("failed to get data write access")
+                    &mut *ctx.data.write().expect("failed to get data write access")
                 {
-                    data.components
-                        .insert(Uuid::new_v4(), Component::new(name.to_string(), v, o));
+                    if let Some(category) = category {
+                        if !data.categories.contains_key(&category) {
+                            return Err(Self::Error::CategoryNotFound { category });
+                        }
+                    }
+
+                    let mut cmp = Component::new(name.to_string(), category, v, o);
+                    cmp.variant_order = variant_order;
+                    cmp.option_order = option_order;
+
+                    let uuid = Uuid::new_v4();
+                    data.components.insert(uuid, cmp);
+                    order::push(&mut data.component_order, &data.components, uuid);
+                    events.push(Event::Added {
+                        entity: Entity::Component { uuid },
+                    });
+                    log::debug!("added component {}", uuid);
 
                     Ok(Self::Success::AddedComponent)
                 } else {
@@ -112,22 +385,26 @@ impl super::CmdAble for ComponentCmd {
                 }
 
                 if let Some((data, _)) =
-                    &mut *DATA.write().expect("failed to get data write access")
+                    &mut *ctx.data.write().expect("failed to get data write access")
                 {
-                    if let Some(component) = data.components.get_mut(&component) {
-                        for (_, v) in &component.options {
+                    if let Some(cmp) = data.components.get_mut(&component) {
+                        for (_, v) in &cmp.options {
                             if v.name == name {
                                 return Ok(Self::Success::AddedOption);
                             }
                         }
 
-                        component
-                            .options
-                            .insert(Uuid::new_v4(), Option::new(name.to_string()));
+                        let uuid = Uuid::new_v4();
+                        cmp.options.insert(uuid, Option::new(name.to_string()));
+                        order::push(&mut cmp.option_order, &cmp.options, uuid);
+                        events.push(Event::Added {
+                            entity: Entity::Option { component, uuid },
+                        });
+                        log::debug!("added option {}", uuid);
 
                         Ok(Self::Success::AddedOption)
                     } else {
-                        Err(Self::Error::NotFound)
+                        Err(Self::Error::NotFound { component })
                     }
                 } else {
                     Err(Self::Error::DatasetNotActive)
@@ -140,36 +417,499 @@ impl super::CmdAble for ComponentCmd {
                 }
 
                 if let Some((data, _)) =
-                    &mut *DATA.write().expect("failed to get data write access")
+                    &mut *ctx.data.write().expect("failed to get data write access")
                 {
-                    if let Some(component) = data.components.get_mut(&component) {
-                        for (_, v) in &component.variants {
+                    if let Some(cmp) = data.components.get_mut(&component) {
+                        for (_, v) in &cmp.variants {
                             if v.name == name {
                                 return Ok(Self::Success::AddedVariant);
                             }
                         }
 
-                        component
-                            .variants
-                            .insert(Uuid::new_v4(), Variant::new(name.to_string()));
+                        let uuid = Uuid::new_v4();
+                        cmp.variants.insert(uuid, Variant::new(name.to_string()));
+                        order::push(&mut cmp.variant_order, &cmp.variants, uuid);
+                        events.push(Event::Added {
+                            entity: Entity::Variant { component, uuid },
+                        });
+                        log::debug!("added variant {}", uuid);
 
                         Ok(Self::Success::AddedVariant)
                     } else {
-                        Err(Self::Error::NotFound)
+                        Err(Self::Error::NotFound { component })
                     }
                 } else {
                     Err(Self::Error::DatasetNotActive)
                 }
             }
             Self::GetComponents => {
-                if let Some((data, _)) = &*DATA.read().expect("failed to get data read access") {
+                if let Some((data, _)) = &*ctx.data.read().expect("failed to get data read access")
+                {
+                    let mut components = data.components.clone();
+                    for cmp in components.values_mut() {
+                        cmp.variant_order = order::sorted(&cmp.variant_order, &cmp.variants);
+                        cmp.option_order = order::sorted(&cmp.option_order, &cmp.options);
+                    }
+
                     Ok(Self::Success::GotComponents {
-                        data: data.components.clone(),
+                        order: order::sorted(&data.component_order, &components),
+                        data: components,
                     })
                 } else {
                     Err(Self::Error::DatasetNotActive)
                 }
             }
+            Self::MoveComponent {
+                component,
+                movement,
+            } => {
+                if let Some((data, _)) =
+                    &mut *ctx.data.write().expect("failed to get data write access")
+                {
+                    if !order::shift(
+                        &mut data.component_order,
+                        &data.components,
+                        component,
+                        movement,
+                    ) {
+                        return Err(Self::Error::NotFound { component });
+                    }
+
+                    events.push(Event::Updated {
+                        entity: Entity::Component { uuid: component },
+                    });
+
+                    Ok(Self::Success::MovedComponent)
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+            Self::MoveOption {
+                component,
+                option,
+                movement,
+            } => {
+                if let Some((data, _)) =
+                    &mut *ctx.data.write().expect("failed to get data write access")
+                {
+                    if let Some(cmp) = data.components.get_mut(&component) {
+                        if !order::shift(&mut cmp.option_order, &cmp.options, option, movement) {
+                            return Err(Self::Error::OptionNotFound { component, option });
+                        }
+
+                        events.push(Event::Updated {
+                            entity: Entity::Option {
+                                component,
+                                uuid: option,
+                            },
+                        });
+
+                        Ok(Self::Success::MovedOption)
+                    } else {
+                        Err(Self::Error::NotFound { component })
+                    }
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+            Self::MoveVariant {
+                component,
+                variant,
+                movement,
+            } => {
+                if let Some((data, _)) =
+                    &mut *ctx.data.write().expect("failed to get data write access")
+                {
+                    if let Some(cmp) = data.components.get_mut(&component) {
+                        if !order::shift(&mut cmp.variant_order, &cmp.variants, variant, movement) {
+                            return Err(Self::Error::VariantNotFound { component, variant });
+                        }
+
+                        events.push(Event::Updated {
+                            entity: Entity::Variant {
+                                component,
+                                uuid: variant,
+                            },
+                        });
+
+                        Ok(Self::Success::MovedVariant)
+                    } else {
+                        Err(Self::Error::NotFound { component })
+                    }
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+            Self::SetOptionAllergens {
+                component,
+                option,
+                allergens,
+                additives,
+            } => {
+                if let Some((data, _)) =
+                    &mut *ctx.data.write().expect("failed to get data write access")
+                {
+                    let cmp = data
+                        .components
+                        .get_mut(&component)
+                        .ok_or(Self::Error::NotFound { component })?;
+                    let opt = cmp
+                        .options
+                        .get_mut(&option)
+                        .ok_or(Self::Error::OptionNotFound { component, option })?;
+
+                    opt.allergens = allergens;
+                    opt.additives = additives;
+                    events.push(Event::Updated {
+                        entity: Entity::Option {
+                            component,
+                            uuid: option,
+                        },
+                    });
+
+                    Ok(Self::Success::SetAllergens)
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+            Self::SetVariantAllergens {
+                component,
+                variant,
+                allergens,
+                additives,
+            } => {
+                if let Some((data, _)) =
+                    &mut *ctx.data.write().expect("failed to get data write access")
+                {
+                    let cmp = data
+                        .components
+                        .get_mut(&component)
+                        .ok_or(Self::Error::NotFound { component })?;
+                    let var = cmp
+                        .variants
+                        .get_mut(&variant)
+                        .ok_or(Self::Error::VariantNotFound { component, variant })?;
+
+                    var.allergens = allergens;
+                    var.additives = additives;
+                    events.push(Event::Updated {
+                        entity: Entity::Variant {
+                            component,
+                            uuid: variant,
+                        },
+                    });
+
+                    Ok(Self::Success::SetAllergens)
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+            Self::SetOptionDiets {
+                component,
+                option,
+                diets,
+            } => {
+                check_diets(ctx, &diets)?;
+
+                if let Some((data, _)) =
+                    &mut *ctx.data.write().expect("failed to get data write access")
+                {
+                    let cmp = data
+                        .components
+                        .get_mut(&component)
+                        .ok_or(Self::Error::NotFound { component })?;
+                    cmp.options
+                        .get_mut(&option)
+                        .ok_or(Self::Error::OptionNotFound { component, option })?
+                        .diets = diets;
+
+                    events.push(Event::Updated {
+                        entity: Entity::Option {
+                            component,
+                            uuid: option,
+                        },
+                    });
+
+                    Ok(Self::Success::SetDiets)
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+            Self::SetOptionNutrition {
+                component,
+                option,
+                nutrition,
+            } => {
+                if let Some((data, _)) =
+                    &mut *ctx.data.write().expect("failed to get data write access")
+                {
+                    let cmp = data
+                        .components
+                        .get_mut(&component)
+                        .ok_or(Self::Error::NotFound { component })?;
+                    cmp.options
+                        .get_mut(&option)
+                        .ok_or(Self::Error::OptionNotFound { component, option })?
+                        .nutrition = nutrition;
+
+                    events.push(Event::Updated {
+                        entity: Entity::Option {
+                            component,
+                            uuid: option,
+                        },
+                    });
+
+                    Ok(Self::Success::SetNutrition)
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+            Self::SetVariantDiets {
+                component,
+                variant,
+                diets,
+            } => {
+                check_diets(ctx, &diets)?;
+
+                if let Some((data, _)) =
+                    &mut *ctx.data.write().expect("failed to get data write access")
+                {
+                    let cmp = data
+                        .components
+                        .get_mut(&component)
+                        .ok_or(Self::Error::NotFound { component })?;
+                    cmp.variants
+                        .get_mut(&variant)
+                        .ok_or(Self::Error::VariantNotFound { component, variant })?
+                        .diets = diets;
+
+                    events.push(Event::Updated {
+                        entity: Entity::Variant {
+                            component,
+                            uuid: variant,
+                        },
+                    });
+
+                    Ok(Self::Success::SetDiets)
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+            Self::SetVariantNutrition {
+                component,
+                variant,
+                nutrition,
+            } => {
+                if let Some((data, _)) =
+                    &mut *ctx.data.write().expect("failed to get data write access")
+                {
+                    let cmp = data
+                        .components
+                        .get_mut(&component)
+                        .ok_or(Self::Error::NotFound { component })?;
+                    cmp.variants
+                        .get_mut(&variant)
+                        .ok_or(Self::Error::VariantNotFound { component, variant })?
+                        .nutrition = nutrition;
+
+                    events.push(Event::Updated {
+                        entity: Entity::Variant {
+                            component,
+                            uuid: variant,
+                        },
+                    });
+
+                    Ok(Self::Success::SetNutrition)
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+            Self::SetOptionSurcharge {
+                component,
+                option,
+                surcharge,
+            } => {
+                if let Some((data, _)) =
+                    &mut *ctx.data.write().expect("failed to get data write access")
+                {
+                    let cmp = data
+                        .components
+                        .get_mut(&component)
+                        .ok_or(Self::Error::NotFound { component })?;
+                    cmp.options
+                        .get_mut(&option)
+                        .ok_or(Self::Error::OptionNotFound { component, option })?
+                        .surcharge = surcharge;
+
+                    events.push(Event::Updated {
+                        entity: Entity::Option {
+                            component,
+                            uuid: option,
+                        },
+                    });
+
+                    Ok(Self::Success::SetSurcharge)
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+            Self::SetVariantSurcharge {
+                component,
+                variant,
+                surcharge,
+            } => {
+                if let Some((data, _)) =
+                    &mut *ctx.data.write().expect("failed to get data write access")
+                {
+                    let cmp = data
+                        .components
+                        .get_mut(&component)
+                        .ok_or(Self::Error::NotFound { component })?;
+                    cmp.variants
+                        .get_mut(&variant)
+                        .ok_or(Self::Error::VariantNotFound { component, variant })?
+                        .surcharge = surcharge;
+
+                    events.push(Event::Updated {
+                        entity: Entity::Variant {
+                            component,
+                            uuid: variant,
+                        },
+                    });
+
+                    Ok(Self::Success::SetSurcharge)
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+            Self::SetCategory {
+                component,
+                category,
+            } => {
+                if let Some((data, _)) =
+                    &mut *ctx.data.write().expect("failed to get data write access")
+                {
+                    if let Some(category) = category {
+                        if !data.categories.contains_key(&category) {
+                            return Err(Self::Error::CategoryNotFound { category });
+                        }
+                    }
+
+                    let old = data
+                        .components
+                        .get(&component)
+                        .ok_or(Self::Error::NotFound { component })?
+                        .category;
+                    let affected: Vec<_> = old.iter().chain(category.iter()).copied().collect();
+                    let before: Vec<_> = affected
+                        .iter()
+                        .map(|c| meals_out_of_limits(data, *c))
+                        .collect();
+
+                    let set = |data: &mut Data, category| {
+                        if let Some(cmp) = data.components.get_mut(&component) {
+                            cmp.category = category;
+                        }
+                    };
+                    set(data, category);
+                    for (c, before) in affected.iter().zip(&before) {
+                        if let Err(e) = check_meals(data, *c, before) {
+                            set(data, old);
+                            return Err(e.into());
+                        }
+                    }
+
+                    events.push(Event::Updated {
+                        entity: Entity::Component { uuid: component },
+                    });
+                    log::debug!("set category of component {}", component);
+
+                    Ok(Self::Success::SetCategory)
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
         }
     }
 }
+
+/// Check that all `diets` exist.
+fn check_diets(ctx: &Context, diets: &BTreeSet<Uuid>) -> Result<(), ComponentCmdError> {
+    let known = ctx.diets.read()?;
+    match diets.iter().find(|d| !known.contains_key(d)) {
+        Some(diet) => Err(ComponentCmdError::DietNotFound { diet: *diet }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cmd::{global::GlobalCmd, run},
+        data::{category::Category, meal::Meal},
+    };
+    use chrono::{Local, TimeZone};
+    use tempfile::TempDir;
+
+    /// Create a context with an active dataset containing a category allowing one component per
+    /// meal, and a meal made of a component of that category and one without a category.
+    ///
+    /// Returns the context, the category and the component without a category.
+    fn context(dir: &TempDir) -> (Context, Uuid, Uuid) {
+        let ctx = Context::in_dir(dir.path(), Local.ymd(2021, 3, 1).and_hms(12, 0, 0));
+        run(
+            &ctx,
+            GlobalCmd::NewDataset {
+                next_month: false,
+                template: None,
+            },
+            &mut Vec::new(),
+        )
+        .unwrap();
+
+        let (category, soup, bread) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        if let Some((data, _)) = &mut *ctx.data.write().unwrap() {
+            data.categories
+                .insert(category, Category::new("soups".to_string(), 0, Some(1)));
+            for (uuid, name, category) in &[(soup, "soup", Some(category)), (bread, "bread", None)]
+            {
+                data.components.insert(
+                    *uuid,
+                    Component::new(
+                        name.to_string(),
+                        *category,
+                        BTreeMap::new(),
+                        BTreeMap::new(),
+                    ),
+                );
+            }
+            let parts = vec![(soup, None), (bread, None)].into_iter().collect();
+            data.meals.insert(
+                Uuid::new_v4(),
+                Meal::new("lunch".to_string(), String::new(), parts),
+            );
+        }
+
+        (ctx, category, bread)
+    }
+
+    #[test]
+    fn setting_category_keeps_meals_within_limits() {
+        let dir = tempfile::tempdir().expect("failed to create temporary directory");
+        let (ctx, category, bread) = context(&dir);
+
+        let cmd = ComponentCmd::SetCategory {
+            component: bread,
+            category: Some(category),
+        };
+        assert!(matches!(
+            run(&ctx, cmd, &mut Vec::new()),
+            Err(ComponentCmdError::LimitError(LimitError { count: 2, .. }))
+        ));
+
+        let data = ctx.data.read().unwrap();
+        let (data, _) = data.as_ref().unwrap();
+        assert_eq!(data.components[&bread].category, None);
+    }
+}
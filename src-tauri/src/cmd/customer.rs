@@ -0,0 +1,376 @@
+use super::error::{CmdError, ErrorPayload};
+use crate::{
+    context::Context,
+    data::customer::{Customer, TimeWindow},
+    event::{Entity, Event},
+    store::StoreError,
+};
+use chrono::{NaiveDate, NaiveTime};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(tag = "cmd", rename_all = "camelCase")]
+pub enum CustomerCmd {
+    /// Adds a new customer.
+    ///
+    /// If the number of the customer is 0, the next free number is used.
+    ///
+    /// # Success variants
+    /// - [`CustomerCmdSuccess::AddedCustomer`]
+    ///
+    /// # Error variants
+    /// - [`CustomerCmdError::DietNotFound`]: if one of the diets doesn't exist
+    /// - [`CustomerCmdError::DuplicateNumber`]: if another customer has the same number
+    /// - [`CustomerCmdError::EmptyName`]: if the provided name is empty
+    /// - [`CustomerCmdError::InvalidCoordinates`]: if the coordinates are out of range
+    /// - [`CustomerCmdError::InvalidService`]: if the service ends before it starts
+    /// - [`CustomerCmdError::InvalidWindow`]: if the delivery time window ends before it starts
+    /// - [`CustomerCmdError::NoFreeNumber`]: if the number is 0 and the highest number is taken
+    /// - [`CustomerCmdError::StoreError`]: if the customers or diets can't be read or written
+    AddCustomer { customer: Customer },
+    /// Ends the service of a customer.
+    ///
+    /// The customer is kept, so past orders still refer to them.
+    ///
+    /// # Success variants
+    /// - [`CustomerCmdSuccess::DeactivatedCustomer`]
+    ///
+    /// # Error variants
+    /// - [`CustomerCmdError::InvalidService`]: if the service would end before it starts
+    /// - [`CustomerCmdError::NotFound`]: if the customer doesn't exist
+    /// - [`CustomerCmdError::StoreError`]: if the customers can't be read or written
+    DeactivateCustomer {
+        customer: Uuid,
+        /// The last day meals are delivered to the customer. Defaults to the planning date.
+        #[serde(default)]
+        end: Option<NaiveDate>,
+    },
+    /// Returns a single customer.
+    ///
+    /// # Success variants
+    /// - [`CustomerCmdSuccess::GotCustomer`]
+    ///
+    /// # Error variants
+    /// - [`CustomerCmdError::NotFound`]: if the customer doesn't exist
+    /// - [`CustomerCmdError::StoreError`]: if the customers can't be read
+    GetCustomer { customer: Uuid },
+    /// Returns all customers.
+    ///
+    /// # Success variants
+    /// - [`CustomerCmdSuccess::GotCustomers`]
+    ///
+    /// # Error variants
+    /// - [`CustomerCmdError::StoreError`]: if the customers can't be read
+    GetCustomers {
+        /// Whether to include customers whose service ended before the planning date.
+        #[serde(default)]
+        inactive: bool,
+    },
+    /// Returns the customers whose number, name, address or phone number match a query.
+    ///
+    /// The name, address and phone number match if they contain the query, ignoring case. The
+    /// number only matches if it is equal to the query.
+    ///
+    /// # Success variants
+    /// - [`CustomerCmdSuccess::GotCustomers`]
+    ///
+    /// # Error variants
+    /// - [`CustomerCmdError::StoreError`]: if the customers can't be read
+    SearchCustomers {
+        query: String,
+        /// Whether to include customers whose service ended before the planning date.
+        #[serde(default)]
+        inactive: bool,
+    },
+    /// Replaces the data of a customer.
+    ///
+    /// If the number is 0, the current number is kept.
+    ///
+    /// # Success variants
+    /// - [`CustomerCmdSuccess::UpdatedCustomer`]
+    ///
+    /// # Error variants
+    /// - [`CustomerCmdError::DietNotFound`]: if one of the diets doesn't exist
+    /// - [`CustomerCmdError::DuplicateNumber`]: if another customer has the same number
+    /// - [`CustomerCmdError::EmptyName`]: if the provided name is empty
+    /// - [`CustomerCmdError::InvalidCoordinates`]: if the coordinates are out of range
+    /// - [`CustomerCmdError::InvalidService`]: if the service ends before it starts
+    /// - [`CustomerCmdError::InvalidWindow`]: if the delivery time window ends before it starts
+    /// - [`CustomerCmdError::NotFound`]: if the customer doesn't exist
+    /// - [`CustomerCmdError::StoreError`]: if the customers or diets can't be read or written
+    UpdateCustomer { customer: Uuid, data: Customer },
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(tag = "variant", rename_all = "camelCase")]
+pub enum CustomerCmdSuccess {
+    AddedCustomer { uuid: Uuid, number: u32 },
+    DeactivatedCustomer,
+    GotCustomer { data: Customer },
+    GotCustomers { data: BTreeMap<Uuid, Customer> },
+    UpdatedCustomer,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CustomerCmdError {
+    #[error("error.customers.diet_not_found")]
+    DietNotFound { diet: Uuid },
+    #[error("error.customers.duplicate_number")]
+    DuplicateNumber { number: u32 },
+    #[error("error.customers.name_empty")]
+    EmptyName,
+    #[error("error.customers.invalid_coordinates")]
+    InvalidCoordinates,
+    #[error("error.customers.invalid_service")]
+    InvalidService { start: NaiveDate, end: NaiveDate },
+    #[error("error.customers.invalid_window")]
+    InvalidWindow { from: NaiveTime, to: NaiveTime },
+    #[error("error.customers.no_free_number")]
+    NoFreeNumber,
+    #[error("error.customers.not_found")]
+    NotFound { customer: Uuid },
+    #[error(transparent)]
+    StoreError(#[from] StoreError),
+}
+
+impl CmdError for CustomerCmdError {
+    fn payload(&self) -> ErrorPayload {
+        match self {
+            Self::DietNotFound { diet } => {
+                ErrorPayload::new("DIET_NOT_FOUND", "error.customers.diet_not_found")
+                    .param("diet", diet)
+            }
+            Self::DuplicateNumber { number } => {
+                ErrorPayload::new("DUPLICATE_NUMBER", "error.customers.duplicate_number")
+                    .param("number", *number)
+            }
+            Self::EmptyName => ErrorPayload::new("NAME_EMPTY", "error.customers.name_empty"),
+            Self::InvalidCoordinates => {
+                ErrorPayload::new("INVALID_COORDINATES", "error.customers.invalid_coordinates")
+            }
+            Self::InvalidService { start, end } => {
+                ErrorPayload::new("INVALID_SERVICE", "error.customers.invalid_service")
+                    .param("start", start)
+                    .param("end", end)
+            }
+            Self::InvalidWindow { from, to } => {
+                ErrorPayload::new("INVALID_WINDOW", "error.customers.invalid_window")
+                    .param("from", from.format("%H:%M").to_string())
+                    .param("to", to.format("%H:%M").to_string())
+            }
+            Self::NoFreeNumber => {
+                ErrorPayload::new("NO_FREE_NUMBER", "error.customers.no_free_number")
+            }
+            Self::NotFound { customer } => {
+                ErrorPayload::new("CUSTOMER_NOT_FOUND", "error.customers.not_found")
+                    .param("customer", customer)
+            }
+            Self::StoreError(e) => e.payload(),
+        }
+    }
+}
+
+impl super::CmdAble for CustomerCmd {
+    type Error = CustomerCmdError;
+    type Success = CustomerCmdSuccess;
+
+    fn execute(
+        self: Self,
+        ctx: &Context,
+        events: &mut Vec<Event>,
+    ) -> Result<Self::Success, Self::Error> {
+        match self {
+            Self::AddCustomer { mut customer } => {
+                check_customer(ctx, &mut customer)?;
+
+                let uuid = Uuid::new_v4();
+                let number = ctx.customers.update(|customers| {
+                    if customer.number == 0 {
+                        customer.number = customers
+                            .values()
+                            .map(|c| c.number)
+                            .max()
+                            .unwrap_or(0)
+                            .checked_add(1)
+                            .ok_or(Self::Error::NoFreeNumber)?;
+                    }
+                    check_number(customers, uuid, customer.number)?;
+
+                    let number = customer.number;
+                    customers.insert(uuid, customer);
+                    Ok::<_, Self::Error>(number)
+                })?;
+                events.push(Event::Added {
+                    entity: Entity::Customer { uuid },
+                });
+                log::info!("added customer {} with number {}", uuid, number);
+
+                Ok(Self::Success::AddedCustomer { uuid, number })
+            }
+            Self::DeactivateCustomer { customer, end } => {
+                let end = end.unwrap_or_else(|| ctx.today());
+
+                ctx.customers.update(|customers| {
+                    let c = customers
+                        .get_mut(&customer)
+                        .ok_or(Self::Error::NotFound { customer })?;
+                    match c.start {
+                        Some(start) if end < start => {
+                            return Err(Self::Error::InvalidService { start, end })
+                        }
+                        _ => c.end = Some(end),
+                    }
+
+                    Ok(())
+                })?;
+                events.push(Event::Updated {
+                    entity: Entity::Customer { uuid: customer },
+                });
+                log::info!("deactivated customer {} after {}", customer, end);
+
+                Ok(Self::Success::DeactivatedCustomer)
+            }
+            Self::GetCustomer { customer } => Ok(Self::Success::GotCustomer {
+                data: ctx
+                    .customers
+                    .read()?
+                    .remove(&customer)
+                    .ok_or(Self::Error::NotFound { customer })?,
+            }),
+            Self::GetCustomers { inactive } => {
+                let today = ctx.today();
+                let mut data = ctx.customers.read()?;
+                data.retain(|_, c| inactive || !c.has_left(today));
+
+                Ok(Self::Success::GotCustomers { data })
+            }
+            Self::SearchCustomers { query, inactive } => {
+                let today = ctx.today();
+                let query = query.trim().to_lowercase();
+                let mut data = ctx.customers.read()?;
+                data.retain(|_, c| (inactive || !c.has_left(today)) && c.matches(&query));
+
+                Ok(Self::Success::GotCustomers { data })
+            }
+            Self::UpdateCustomer { customer, mut data } => {
+                check_customer(ctx, &mut data)?;
+
+                ctx.customers.update(|customers| {
+                    let old = customers
+                        .get(&customer)
+                        .ok_or(Self::Error::NotFound { customer })?;
+                    if data.number == 0 {
+                        data.number = old.number;
+                    }
+                    check_number(customers, customer, data.number)?;
+
+                    customers.insert(customer, data);
+                    Ok::<_, Self::Error>(())
+                })?;
+                events.push(Event::Updated {
+                    entity: Entity::Customer { uuid: customer },
+                });
+
+                Ok(Self::Success::UpdatedCustomer)
+            }
+        }
+    }
+}
+
+/// Normalize the data of a customer and check that it is valid.
+fn check_customer(ctx: &Context, customer: &mut Customer) -> Result<(), CustomerCmdError> {
+    customer.name = customer.name.trim().to_string();
+    if customer.name.is_empty() {
+        return Err(CustomerCmdError::EmptyName);
+    }
+
+    if let (Some(start), Some(end)) = (customer.start, customer.end) {
+        if end < start {
+            return Err(CustomerCmdError::InvalidService { start, end });
+        }
+    }
+
+    if customer.coordinates.map_or(false, |c| !c.is_valid()) {
+        return Err(CustomerCmdError::InvalidCoordinates);
+    }
+    match customer.window {
+        Some(TimeWindow { from, to }) if to < from => {
+            return Err(CustomerCmdError::InvalidWindow { from, to })
+        }
+        _ => {}
+    }
+
+    let known = ctx.diets.read()?;
+    match customer.diets.iter().find(|d| !known.contains_key(d)) {
+        Some(diet) => Err(CustomerCmdError::DietNotFound { diet: *diet }),
+        None => Ok(()),
+    }
+}
+
+/// Check that no customer other than `uuid` has `number`.
+fn check_number(
+    customers: &BTreeMap<Uuid, Customer>,
+    uuid: Uuid,
+    number: u32,
+) -> Result<(), CustomerCmdError> {
+    if customers
+        .iter()
+        .any(|(k, c)| *k != uuid && c.number == number)
+    {
+        Err(CustomerCmdError::DuplicateNumber { number })
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cmd::run, data::customer::Address};
+    use chrono::{Local, TimeZone};
+
+    fn customer(number: u32) -> Customer {
+        Customer {
+            number,
+            name: "Jane Doe".to_string(),
+            address: Address::default(),
+            phone: String::new(),
+            notes: String::new(),
+            start: None,
+            end: None,
+            diets: Default::default(),
+            allergies: Default::default(),
+            intolerances: Default::default(),
+            coordinates: None,
+            window: None,
+        }
+    }
+
+    fn add(ctx: &Context, number: u32) -> Result<u32, CustomerCmdError> {
+        let cmd = CustomerCmd::AddCustomer {
+            customer: customer(number),
+        };
+        match run(ctx, cmd, &mut Vec::new())? {
+            CustomerCmdSuccess::AddedCustomer { number, .. } => Ok(number),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn numbers_are_assigned() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = Context::in_dir(dir.path(), Local.ymd(2021, 3, 1).and_hms(12, 0, 0));
+
+        assert_eq!(add(&ctx, 0).unwrap(), 1);
+        assert_eq!(add(&ctx, 5).unwrap(), 5);
+        assert_eq!(add(&ctx, 0).unwrap(), 6);
+        assert!(matches!(
+            add(&ctx, 5),
+            Err(CustomerCmdError::DuplicateNumber { number: 5 })
+        ));
+
+        assert_eq!(add(&ctx, u32::MAX).unwrap(), u32::MAX);
+        assert!(matches!(add(&ctx, 0), Err(CustomerCmdError::NoFreeNumber)));
+    }
+}
@@ -0,0 +1,154 @@
+use super::error::{CmdError, ErrorPayload};
+use crate::{
+    context::Context,
+    data::diet::Diet,
+    event::{Entity, Event},
+    store::StoreError,
+};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(tag = "cmd", rename_all = "camelCase")]
+pub enum DietCmd {
+    /// Adds a new diet.
+    ///
+    /// # Success variants
+    /// - [`DietCmdSuccess::AddedDiet`]
+    ///
+    /// # Error variants
+    /// - [`DietCmdError::EmptyName`]: if the provided name is empty
+    /// - [`DietCmdError::StoreError`]: if the diets can't be read or written
+    AddDiet { name: String },
+    /// Deletes a diet.
+    ///
+    /// Variants and options keep referring to the diet, but meals are no longer reported as
+    /// suitable for it.
+    ///
+    /// # Success variants
+    /// - [`DietCmdSuccess::DeletedDiet`]
+    ///
+    /// # Error variants
+    /// - [`DietCmdError::NotFound`]: if the diet doesn't exist
+    /// - [`DietCmdError::StoreError`]: if the diets can't be read or written
+    DeleteDiet { diet: Uuid },
+    /// Returns all diets.
+    ///
+    /// # Success variants
+    /// - [`DietCmdSuccess::GotDiets`]
+    ///
+    /// # Error variants
+    /// - [`DietCmdError::StoreError`]: if the diets can't be read
+    GetDiets,
+    /// Renames a diet.
+    ///
+    /// # Success variants
+    /// - [`DietCmdSuccess::RenamedDiet`]
+    ///
+    /// # Error variants
+    /// - [`DietCmdError::EmptyName`]: if the provided name is empty
+    /// - [`DietCmdError::NotFound`]: if the diet doesn't exist
+    /// - [`DietCmdError::StoreError`]: if the diets can't be read or written
+    RenameDiet { diet: Uuid, name: String },
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(tag = "variant", rename_all = "camelCase")]
+pub enum DietCmdSuccess {
+    AddedDiet { uuid: Uuid },
+    DeletedDiet,
+    GotDiets { data: BTreeMap<Uuid, Diet> },
+    RenamedDiet,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DietCmdError {
+    #[error("error.diets.name_empty")]
+    EmptyName,
+    #[error("error.diets.not_found")]
+    NotFound { diet: Uuid },
+    #[error(transparent)]
+    StoreError(#[from] StoreError),
+}
+
+impl CmdError for DietCmdError {
+    fn payload(&self) -> ErrorPayload {
+        match self {
+            Self::EmptyName => ErrorPayload::new("NAME_EMPTY", "error.diets.name_empty"),
+            Self::NotFound { diet } => {
+                ErrorPayload::new("DIET_NOT_FOUND", "error.diets.not_found").param("diet", diet)
+            }
+            Self::StoreError(e) => e.payload(),
+        }
+    }
+}
+
+impl super::CmdAble for DietCmd {
+    type Error = DietCmdError;
+    type Success = DietCmdSuccess;
+
+    fn execute(
+        self: Self,
+        ctx: &Context,
+        events: &mut Vec<Event>,
+    ) -> Result<Self::Success, Self::Error> {
+        match self {
+            Self::AddDiet { name } => {
+                let name = name.trim();
+                if name.is_empty() {
+                    return Err(Self::Error::EmptyName);
+                }
+
+                let uuid = Uuid::new_v4();
+                ctx.diets.update(|diets| {
+                    diets.insert(
+                        uuid,
+                        Diet {
+                            name: name.to_string(),
+                        },
+                    );
+                    Ok::<_, Self::Error>(())
+                })?;
+                events.push(Event::Added {
+                    entity: Entity::Diet { uuid },
+                });
+                log::info!("added diet {}", uuid);
+
+                Ok(Self::Success::AddedDiet { uuid })
+            }
+            Self::DeleteDiet { diet } => {
+                ctx.diets
+                    .update(|diets| diets.remove(&diet).ok_or(Self::Error::NotFound { diet }))?;
+                events.push(Event::Deleted {
+                    entity: Entity::Diet { uuid: diet },
+                });
+                log::info!("deleted diet {}", diet);
+
+                Ok(Self::Success::DeletedDiet)
+            }
+            Self::GetDiets => Ok(Self::Success::GotDiets {
+                data: ctx.diets.read()?,
+            }),
+            Self::RenameDiet { diet, name } => {
+                let name = name.trim();
+                if name.is_empty() {
+                    return Err(Self::Error::EmptyName);
+                }
+
+                ctx.diets.update(|diets| {
+                    match diets.get_mut(&diet) {
+                        Some(x) => x.name = name.to_string(),
+                        None => return Err(Self::Error::NotFound { diet }),
+                    }
+
+                    Ok(())
+                })?;
+                events.push(Event::Updated {
+                    entity: Entity::Diet { uuid: diet },
+                });
+
+                Ok(Self::Success::RenamedDiet)
+            }
+        }
+    }
+}
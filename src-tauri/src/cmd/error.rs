@@ -0,0 +1,133 @@
+use crate::data::allergen::Allergen;
+use chrono::NaiveDate;
+use std::{collections::BTreeMap, path::PathBuf};
+use uuid::Uuid;
+
+/// The structured representation of a command error, as it is sent to the frontend.
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorPayload {
+    /// A stable, machine readable identifier of the error.
+    pub code: &'static str,
+    /// The i18n key of the error message.
+    pub key: &'static str,
+    /// Parameters describing the error, usable for interpolation.
+    pub params: BTreeMap<&'static str, Param>,
+    /// Technical details, not meant to be translated.
+    pub detail: Option<String>,
+}
+
+impl ErrorPayload {
+    pub fn new(code: &'static str, key: &'static str) -> Self {
+        Self {
+            code,
+            key,
+            params: BTreeMap::new(),
+            detail: None,
+        }
+    }
+
+    /// Add a parameter to the payload.
+    pub fn param(mut self, name: &'static str, value: impl Into<Param>) -> Self {
+        self.params.insert(name, value.into());
+        self
+    }
+
+    /// Add technical details to the payload.
+    pub fn detail(mut self, detail: impl ToString) -> Self {
+        self.detail = Some(detail.to_string());
+        self
+    }
+}
+
+/// A single parameter of an [`ErrorPayload`].
+#[derive(Debug, serde::Serialize)]
+#[serde(untagged)]
+pub enum Param {
+    Allergen(Allergen),
+    Date(NaiveDate),
+    Number(i64),
+    Path(PathBuf),
+    Text(String),
+    Uuid(Uuid),
+}
+
+impl From<Allergen> for Param {
+    fn from(val: Allergen) -> Self {
+        Self::Allergen(val)
+    }
+}
+
+impl From<NaiveDate> for Param {
+    fn from(val: NaiveDate) -> Self {
+        Self::Date(val)
+    }
+}
+
+impl From<&NaiveDate> for Param {
+    fn from(val: &NaiveDate) -> Self {
+        Self::Date(*val)
+    }
+}
+
+impl From<i32> for Param {
+    fn from(val: i32) -> Self {
+        Self::Number(val.into())
+    }
+}
+
+impl From<u32> for Param {
+    fn from(val: u32) -> Self {
+        Self::Number(val.into())
+    }
+}
+
+impl From<usize> for Param {
+    fn from(val: usize) -> Self {
+        Self::Number(val as i64)
+    }
+}
+
+impl From<PathBuf> for Param {
+    fn from(val: PathBuf) -> Self {
+        Self::Path(val)
+    }
+}
+
+impl From<&PathBuf> for Param {
+    fn from(val: &PathBuf) -> Self {
+        Self::Path(val.clone())
+    }
+}
+
+impl From<String> for Param {
+    fn from(val: String) -> Self {
+        Self::Text(val)
+    }
+}
+
+impl From<&str> for Param {
+    fn from(val: &str) -> Self {
+        Self::Text(val.to_string())
+    }
+}
+
+impl From<Uuid> for Param {
+    fn from(val: Uuid) -> Self {
+        Self::Uuid(val)
+    }
+}
+
+impl From<&Uuid> for Param {
+    fn from(val: &Uuid) -> Self {
+        Self::Uuid(*val)
+    }
+}
+
+/// Errors that can be sent to the frontend.
+///
+/// Only their payload is sent, so implementors don't need to be serializable themselves.
+pub trait CmdError: std::error::Error {
+    /// Get the structured representation of the error.
+    fn payload(&self) -> ErrorPayload;
+}
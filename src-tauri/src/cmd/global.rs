@@ -1,18 +1,30 @@
+use super::error::{CmdError, ErrorPayload};
 use crate::{
-    data::{AvailableDatasets, Data},
-    DATA, DATADIR, PROJECT_DIRS,
+    clock,
+    context::{Context, Paths},
+    data::{
+        allergen::Allergen,
+        diff::Diff,
+        merge::{Conflict, Merge, Resolution},
+        AvailableDatasets, Data, Metadata,
+    },
+    event::{Entity, Event},
+    logger::{Entry, Logger},
+    recovery::{self, Files, RecoveryCopy},
+    settings::Settings,
+    store::{write_atomic, StoreError},
 };
-use chrono::Datelike;
+use chrono::{Datelike, NaiveDate};
 use fs2::FileExt;
-use ron::{de::from_reader, ser::to_writer};
+use ron::{de::from_reader, ser::to_string};
 use std::{
-    collections::hash_map::DefaultHasher,
+    collections::BTreeMap,
     fs::{create_dir_all, File, OpenOptions},
-    hash::{Hash, Hasher},
-    io::{BufReader, BufWriter, Seek, SeekFrom},
-    path::PathBuf,
+    io::BufReader,
+    path::{Path, PathBuf},
     sync::Mutex,
 };
+use uuid::Uuid;
 
 #[derive(Debug, serde::Deserialize)]
 #[serde(tag = "cmd", rename_all = "camelCase")]
@@ -25,16 +37,102 @@ pub enum GlobalCmd {
     /// # Error Variants
     /// - `DatasetNotActive`
     CloseDataset,
+    /// Compare the content of two datasets, as they are saved on disk.
+    ///
+    /// Neither dataset is opened or locked, so this also works for the active dataset. Unsaved
+    /// changes aren't included in that case.
+    ///
+    /// # Success variants
+    /// - `GotDiff`
+    ///
+    /// # Error variants
+    /// - `IoError`: if one of the files can't be read
+    /// - `NoDataset`: if one of the datasets doesn't exist
+    /// - `RonError`: if one of the files can't be parsed
+    #[serde(rename_all = "camelCase")]
+    DiffDatasets {
+        old_year: i32,
+        old_month: u32,
+        new_year: i32,
+        new_month: u32,
+    },
+    /// Get the footnote letters of all allergens.
+    ///
+    /// # Success variants
+    /// - `GotAllergens`
+    GetAllergens,
     /// Get a list of all available datasets.
     ///
     /// # Success variants
     /// - `GotDatasets`
     GetAvailableDatasets,
+    /// Get all recovery copies of a dataset, newest first.
+    ///
+    /// # Success variants
+    /// - `GotRecoveryCopies`
+    ///
+    /// # Error variants
+    /// - `IoError`: if the recovery directory can't be read
+    GetRecoveryCopies { year: i32, month: u32 },
+    /// Get the most recent log entries, oldest first.
+    ///
+    /// # Success variants
+    /// - `GotLogs`
+    ///
+    /// # Error variants
+    /// - `IoError`: if the log files can't be read
+    GetLogs {
+        /// The maximum number of entries to return. Defaults to 100.
+        limit: Option<usize>,
+    },
+    /// Merge two versions of a dataset that diverged from a common base, and get the conflicts
+    /// that need to be resolved.
+    ///
+    /// Nothing is written. Use `MergeDatasets` to write the result once the conflicts are resolved.
+    ///
+    /// # Success variants
+    /// - `GotMergeConflicts`
+    ///
+    /// # Error variants
+    /// - `IoError`: if one of the files can't be read
+    /// - `MergeError`: if the versions can't be merged
+    /// - `RonError`: if one of the files can't be parsed
+    GetMergeConflicts {
+        base: PathBuf,
+        ours: PathBuf,
+        theirs: PathBuf,
+    },
+    /// Get the current settings.
+    ///
+    /// # Success variants
+    /// - `GotSettings`
+    GetSettings,
     /// Get the current state of the application.
     ///
     /// # Success variants
     /// - `GotState`
     GetState,
+    /// Merge two versions of a dataset that diverged from a common base, and write the result.
+    ///
+    /// # Success variants
+    /// - `Merged`
+    ///
+    /// # Error variants
+    /// - `DatasetIsActive`: if `output` is the file of the active dataset
+    /// - `IoError`: if one of the files can't be read, or the output can't be written
+    /// - `MergeError`: if the versions can't be merged
+    /// - `RonError`: if one of the files can't be parsed or written
+    /// - `UnresolvedConflicts`: if not all conflicts are resolved
+    MergeDatasets {
+        base: PathBuf,
+        ours: PathBuf,
+        theirs: PathBuf,
+        /// The file to write the result to.
+        output: PathBuf,
+        /// The resolutions of the conflicts, as returned by `GetMergeConflicts`.
+        #[serde(default)]
+        resolutions: Vec<Resolution>,
+    },
     /// Create a new dataset
     ///
     /// # Success variants
@@ -46,6 +144,8 @@ pub enum GlobalCmd {
     /// - `DatasetIsActive`: if there is already an active dataset
     /// - `IoError`: if the corresponding files can't be opened
     /// - `LockError`: if the corresponding files can't be locked
+    /// - `StoreError`: if the templates can't be read
+    /// - `TemplateNotFound`: if the specified template doesn't exist
     #[serde(rename_all = "camelCase")]
     NewDataset {
         /// Specifies the month the dataset should be created for.
@@ -54,6 +154,10 @@ pub enum GlobalCmd {
         /// - `false`: the dataset is created for the current month
         /// - `true`: the dataset is created for the next month
         next_month: bool,
+        /// The template to fill the new dataset from. The dataset starts out empty if this is
+        /// `None`.
+        #[serde(default)]
+        template: Option<Uuid>,
     },
     /// Open an existing dataset
     ///
@@ -65,6 +169,7 @@ pub enum GlobalCmd {
     /// - `IoError`: if the corresponding files can't be opened
     /// - `LockError`: if the corresponding files can't be locked
     /// - `NoDataset`: if there is no such dataset
+    /// - `RonError`: if neither the file nor one of its recovery copies can be read
     #[serde(rename_all = "camelCase")]
     OpenDataset {
         /// The year the dataset is in.
@@ -72,6 +177,25 @@ pub enum GlobalCmd {
         /// The month the dataset if for.
         month: u32,
     },
+    /// Open an existing dataset with the content of one of its recovery copies.
+    ///
+    /// The dataset file itself is only replaced once the dataset is saved.
+    ///
+    /// # Success variants
+    /// - `OpenedDataset`
+    ///
+    /// # Error variants
+    /// - `DatasetIsActive`: if there is already an active dataset
+    /// - `IoError`: if the corresponding files can't be opened
+    /// - `LockError`: if the corresponding files can't be locked
+    /// - `NoRecoveryCopy`: if there is no such recovery copy
+    /// - `RonError`: if the recovery copy can't be read
+    RestoreRecoveryCopy {
+        year: i32,
+        month: u32,
+        /// The id of the recovery copy, as returned by `GetRecoveryCopies`.
+        id: String,
+    },
     /// Save the loaded dataset to disk
     ///
     /// # Success variants
@@ -82,24 +206,119 @@ pub enum GlobalCmd {
     /// - `IoError`
     /// - `RonError`
     Save,
+    /// Change the title and notes of the active dataset.
+    ///
+    /// # Success variants
+    /// - `SetMetadata`
+    ///
+    /// # Error variants
+    /// - `DatasetNotActive`: if there isn't an active dataset
+    SetMetadata { title: String, notes: String },
+    /// Set the date used for planning, instead of the current date.
+    ///
+    /// Affects which datasets are considered current and which can be created.
+    ///
+    /// # Success variants
+    /// - `SetPlanningDate`
+    SetPlanningDate {
+        /// The date to plan for. `None` resets to the current date.
+        date: Option<NaiveDate>,
+    },
+    /// Replace the current settings and write them to disk.
+    ///
+    /// # Success variants
+    /// - `SavedSettings`
+    ///
+    /// # Error variants
+    /// - `RonError`: if the settings can't be written
+    SetSettings { settings: Settings },
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum GlobalCmdError {
     #[error("error.global.dataset_exists")]
-    DatasetExists,
+    DatasetExists { year: i32, month: u32 },
     #[error("error.global.dataset_is_active")]
     DatasetIsActive,
     #[error("error.global.dataset_not_active")]
     DatasetNotActive,
-    #[error("error.global.io: {0}")]
-    IoError(#[from] std::io::Error),
+    #[error("error.global.io: {source}")]
+    IoError {
+        path: Option<PathBuf>,
+        source: std::io::Error,
+    },
     #[error("error.global.lock")]
-    LockError,
+    LockError { path: PathBuf },
+    #[error("error.global.merge: {0}")]
+    MergeError(#[from] serde_json::Error),
     #[error("error.global.no_dataset")]
-    NoDataset,
+    NoDataset { year: i32, month: u32 },
+    #[error("error.global.no_recovery_copy")]
+    NoRecoveryCopy { year: i32, month: u32, id: String },
     #[error("error.global.ron: {0}")]
     RonError(#[from] ron::Error),
+    #[error(transparent)]
+    StoreError(#[from] StoreError),
+    #[error("error.templates.not_found")]
+    TemplateNotFound { template: Uuid },
+    #[error("error.global.unresolved_conflicts")]
+    UnresolvedConflicts { count: usize },
+}
+
+impl CmdError for GlobalCmdError {
+    fn payload(&self) -> ErrorPayload {
+        match self {
+            Self::DatasetExists { year, month } => {
+                ErrorPayload::new("DATASET_EXISTS", "error.global.dataset_exists")
+                    .param("year", *year)
+                    .param("month", *month)
+            }
+            Self::DatasetIsActive => {
+                ErrorPayload::new("DATASET_IS_ACTIVE", "error.global.dataset_is_active")
+            }
+            Self::DatasetNotActive => {
+                ErrorPayload::new("DATASET_NOT_ACTIVE", "error.global.dataset_not_active")
+            }
+            Self::IoError { path, source } => {
+                let payload = ErrorPayload::new("IO", "error.global.io").detail(source);
+                match path {
+                    Some(path) => payload.param("path", path),
+                    None => payload,
+                }
+            }
+            Self::LockError { path } => {
+                ErrorPayload::new("LOCK", "error.global.lock").param("path", path)
+            }
+            Self::MergeError(e) => ErrorPayload::new("MERGE", "error.global.merge").detail(e),
+            Self::NoDataset { year, month } => {
+                ErrorPayload::new("NO_DATASET", "error.global.no_dataset")
+                    .param("year", *year)
+                    .param("month", *month)
+            }
+            Self::NoRecoveryCopy { year, month, id } => {
+                ErrorPayload::new("NO_RECOVERY_COPY", "error.global.no_recovery_copy")
+                    .param("year", *year)
+                    .param("month", *month)
+                    .param("id", id.as_str())
+            }
+            Self::RonError(e) => ErrorPayload::new("RON", "error.global.ron").detail(e),
+            Self::StoreError(e) => e.payload(),
+            Self::TemplateNotFound { template } => {
+                ErrorPayload::new("TEMPLATE_NOT_FOUND", "error.templates.not_found")
+                    .param("template", template)
+            }
+            Self::UnresolvedConflicts { count } => {
+                ErrorPayload::new("UNRESOLVED_CONFLICTS", "error.global.unresolved_conflicts")
+                    .param("count", *count)
+            }
+        }
+    }
+}
+
+impl From<std::io::Error> for GlobalCmdError {
+    fn from(source: std::io::Error) -> Self {
+        Self::IoError { path: None, source }
+    }
 }
 
 #[derive(Debug, serde::Serialize)]
@@ -107,99 +326,241 @@ pub enum GlobalCmdError {
 pub enum GlobalCmdSuccess {
     ClosedDataset,
     CreatedDataset,
+    GotAllergens {
+        letters: BTreeMap<Allergen, char>,
+    },
     GotDatasets(AvailableDatasets),
+    GotDiff(Diff),
+    GotLogs {
+        entries: Vec<Entry>,
+    },
+    GotMergeConflicts {
+        conflicts: Vec<Conflict>,
+    },
+    GotRecoveryCopies {
+        copies: Vec<RecoveryCopy>,
+    },
+    GotSettings {
+        settings: Settings,
+    },
+    #[serde(rename_all = "camelCase")]
     GotState {
         state: State,
+        /// The date used for planning.
+        today: NaiveDate,
+        /// true, if the planning date was set by the user
+        is_planning_date: bool,
     },
     #[serde(rename_all = "camelCase")]
     OpenedDataset {
         /// true, if the year and month within the file don't match up with its file name
         mismatch: bool,
-        /// true, if a backup file was loaded
+        /// true, if a recovery copy was loaded
         is_backup: bool,
+        /// true, if there is a recovery copy that is newer than the loaded data
+        has_newer_recovery_copy: bool,
     },
+    Merged,
     Saved,
+    SavedSettings,
+    SetMetadata,
+    SetPlanningDate,
 }
 
 impl super::CmdAble for GlobalCmd {
     type Error = GlobalCmdError;
     type Success = GlobalCmdSuccess;
 
-    fn execute(self: Self) -> Result<Self::Success, Self::Error> {
+    fn execute(
+        self: Self,
+        ctx: &Context,
+        events: &mut Vec<Event>,
+    ) -> Result<Self::Success, Self::Error> {
         match self {
             Self::CloseDataset => {
-                let mut data = DATA.write().expect("failed to get data write access");
+                let mut data = ctx.data.write().expect("failed to get data write access");
                 if data.is_none() {
                     return Err(Self::Error::DatasetNotActive);
                 }
 
                 *data = None;
+                events.push(Event::DatasetClosed);
+                log::info!("closed dataset");
 
                 Ok(Self::Success::ClosedDataset)
             }
+            Self::DiffDatasets {
+                old_year,
+                old_month,
+                new_year,
+                new_month,
+            } => Ok(Self::Success::GotDiff(Diff::new(
+                &read_dataset(&ctx.paths, old_year, old_month)?,
+                &read_dataset(&ctx.paths, new_year, new_month)?,
+            ))),
+            Self::GetAllergens => Ok(Self::Success::GotAllergens {
+                letters: Allergen::ALL.iter().map(|a| (*a, a.letter())).collect(),
+            }),
             Self::GetAvailableDatasets => Ok(GlobalCmdSuccess::GotDatasets(
-                AvailableDatasets::from_base_dir(&dataset_file_name),
+                AvailableDatasets::from_base_dir(ctx.today(), &|year, month| {
+                    ctx.paths.dataset_file(year, month)
+                }),
             )),
+            Self::GetRecoveryCopies { year, month } => Ok(Self::Success::GotRecoveryCopies {
+                copies: recovery_copies(&ctx.paths, year, month)?,
+            }),
+            Self::GetLogs { limit } => Ok(Self::Success::GotLogs {
+                entries: Logger::read(&ctx.paths.log_dir, limit.unwrap_or(100)).map_err(|e| {
+                    Self::Error::IoError {
+                        path: Some(ctx.paths.log_dir.clone()),
+                        source: e,
+                    }
+                })?,
+            }),
+            Self::GetMergeConflicts { base, ours, theirs } => {
+                Ok(Self::Success::GotMergeConflicts {
+                    conflicts: merge_files(base, ours, theirs)?.conflicts,
+                })
+            }
+            Self::GetSettings => Ok(Self::Success::GotSettings {
+                settings: ctx
+                    .settings
+                    .read()
+                    .expect("failed to get settings read access")
+                    .clone(),
+            }),
             Self::GetState => {
-                let data = DATA.read().expect("failed to get data read access");
+                let data = ctx.data.read().expect("failed to get data read access");
 
                 let ret = match &*data {
                     None => State::Select,
                     Some((val, _)) => State::Loaded {
                         year: val.year,
                         month: val.month,
+                        meta: val.meta.clone(),
                     },
                 };
 
-                Ok(Self::Success::GotState { state: ret })
+                Ok(Self::Success::GotState {
+                    state: ret,
+                    today: ctx.today(),
+                    is_planning_date: ctx
+                        .planning_date
+                        .read()
+                        .expect("failed to get planning date read access")
+                        .is_some(),
+                })
+            }
+            Self::MergeDatasets {
+                base,
+                ours,
+                theirs,
+                output,
+                resolutions,
+            } => {
+                let mut merge = merge_files(base, ours, theirs)?;
+                merge.resolve(&resolutions);
+                if !merge.conflicts.is_empty() {
+                    return Err(Self::Error::UnresolvedConflicts {
+                        count: merge.conflicts.len(),
+                    });
+                }
+
+                let mut merged = merge.into_data()?;
+                merged.meta.saved = Some(ctx.clock.now());
+                merged.meta.saved_by = ctx
+                    .settings
+                    .read()
+                    .expect("failed to get settings read access")
+                    .user_name();
+
+                // the active dataset would overwrite the result when it is saved
+                if let Some((data, _)) = &*ctx.data.read().expect("failed to get data read access")
+                {
+                    let active = ctx.paths.dataset_file(data.year, data.month);
+                    if canonical(&active) == canonical(&output) {
+                        return Err(Self::Error::DatasetIsActive);
+                    }
+                }
+
+                write_atomic(&output, to_string(&merged)?.as_bytes()).map_err(|source| {
+                    Self::Error::IoError {
+                        path: Some(output.clone()),
+                        source,
+                    }
+                })?;
+                log::info!("merged datasets into {}", output.display());
+
+                Ok(Self::Success::Merged)
             }
-            Self::NewDataset { next_month } => {
-                let now = chrono::Local::now().date();
+            Self::NewDataset {
+                next_month,
+                template,
+            } => {
+                let now = ctx.today();
 
                 // calculate month and year for new dataset
-                let month = if !next_month {
-                    now.month()
-                } else {
-                    now.month() + 1 % 12
-                };
-                let year = if !next_month {
-                    now.year()
+                let (year, month) = if !next_month {
+                    (now.year(), now.month())
                 } else {
-                    if month != 1 {
-                        now.year()
-                    } else {
-                        now.year() + 1
-                    }
+                    clock::next_month(now.year(), now.month())
                 };
 
                 // check if dataset already exists
-                if dataset_file_name(year, month).is_file() {
-                    return Err(Self::Error::DatasetExists);
+                if ctx.paths.dataset_file(year, month).is_file() {
+                    return Err(Self::Error::DatasetExists { year, month });
                 }
 
                 // open data var
-                let mut data = DATA.write().expect("failed to get data write lock");
+                let mut data = ctx.data.write().expect("failed to get data write lock");
                 if data.is_some() {
                     return Err(Self::Error::DatasetIsActive);
                 }
 
+                // get template before any files are created
+                let template = match template {
+                    Some(uuid) => Some(
+                        ctx.templates
+                            .read()?
+                            .remove(&uuid)
+                            .ok_or(Self::Error::TemplateNotFound { template: uuid })?,
+                    ),
+                    None => None,
+                };
+
                 // set data var
-                *data = Some((Data::new(year, month), Mutex::new(open_files(year, month)?)));
+                let file = open_file(ctx.paths.dataset_file(year, month))?;
+                let user = ctx
+                    .settings
+                    .read()
+                    .expect("failed to get settings read access")
+                    .user_name();
+                let mut new_data = Data::new(year, month, Metadata::new(ctx.clock.now(), user));
+                if let Some(template) = template {
+                    template.apply(&mut new_data);
+                }
+                // the dataset can be opened again, even if it is closed without saving
+                recovery::write(&file, &new_data)?;
+                *data = Some((new_data, Mutex::new(Files::new(year, month, file))));
+                events.push(Event::DatasetCreated { year, month });
+                log::info!("created dataset {}-{:02}", year, month);
 
                 Ok(Self::Success::CreatedDataset)
             }
             Self::OpenDataset { year, month } => {
                 if month < 1 || month > 12 {
-                    return Err(Self::Error::NoDataset);
+                    return Err(Self::Error::NoDataset { year, month });
                 }
 
-                let mut data = DATA.write().expect("failed to get data write lock");
+                let mut data = ctx.data.write().expect("failed to get data write lock");
                 if data.is_some() {
                     return Err(Self::Error::DatasetIsActive);
                 }
 
+                let copies = recovery_copies(&ctx.paths, year, month)?;
+
                 // establish local data
-                let (file, tmp) = open_files(year, month)?;
+                let file = open_file(ctx.paths.dataset_file(year, month))?;
                 let mut is_backup = false;
 
                 // get data from file
@@ -209,126 +570,327 @@ impl super::CmdAble for GlobalCmd {
                     new_data = from_reader(file);
                 }
 
-                // check temporary file
+                // check recovery copies, newest first
                 if let Err(e) = new_data {
-                    let tmp = BufReader::new(&tmp);
-                    new_data = from_reader(tmp)
+                    new_data = copies
+                        .iter()
+                        .find_map(|copy| read_recovery_copy(&ctx.paths, year, month, copy).ok())
                         // preserve original error
-                        .map_err(move |_| e);
+                        .ok_or(e);
 
                     is_backup = true;
                 }
 
-                match new_data {
-                    Ok(new_data) => {
-                        // check for mismatch
-                        let mismatch = new_data.year != year || new_data.month != month;
+                let new_data = new_data?;
 
-                        // set data
-                        *data = Some((new_data, Mutex::new((file, tmp))));
+                // check for mismatch
+                let mismatch = new_data.year != year || new_data.month != month;
+                let has_newer_recovery_copy = !is_backup && copies.iter().any(|c| c.is_newer);
 
-                        Ok(Self::Success::OpenedDataset {
-                            mismatch,
-                            is_backup,
-                        })
-                    }
-                    Err(e) => Err(e.into()),
+                // set data
+                *data = Some((new_data, Mutex::new(Files::new(year, month, file))));
+                events.push(Event::DatasetOpened { year, month });
+                log::info!("opened dataset {}-{:02}", year, month);
+                if mismatch {
+                    log::warn!("dataset {}-{:02} contains other date", year, month);
+                }
+                if is_backup {
+                    log::warn!("loaded backup of dataset {}-{:02}", year, month);
+                }
+
+                Ok(Self::Success::OpenedDataset {
+                    mismatch,
+                    is_backup,
+                    has_newer_recovery_copy,
+                })
+            }
+            Self::RestoreRecoveryCopy { year, month, id } => {
+                let mut data = ctx.data.write().expect("failed to get data write lock");
+                if data.is_some() {
+                    return Err(Self::Error::DatasetIsActive);
                 }
+
+                let copy = recovery_copies(&ctx.paths, year, month)?
+                    .into_iter()
+                    .find(|copy| copy.id == id)
+                    .ok_or_else(|| Self::Error::NoRecoveryCopy {
+                        year,
+                        month,
+                        id: id.clone(),
+                    })?;
+                let new_data = read_recovery_copy(&ctx.paths, year, month, &copy)?;
+
+                let file = open_file(ctx.paths.dataset_file(year, month))?;
+                let mismatch = new_data.year != year || new_data.month != month;
+
+                *data = Some((new_data, Mutex::new(Files::new(year, month, file))));
+                events.push(Event::DatasetOpened { year, month });
+                log::info!("restored dataset {}-{:02} from {}", year, month, id);
+
+                Ok(Self::Success::OpenedDataset {
+                    mismatch,
+                    is_backup: true,
+                    has_newer_recovery_copy: false,
+                })
             }
             Self::Save => {
-                if let Some((data, files)) = &*DATA.read().expect("failed to get data read lock") {
-                    let files = files.lock().expect("failet to get file lock");
-                    let (mut file, mut tmp) = (&files.0, &files.1);
-
-                    // clear tmp file
-                    tmp.set_len(0).map_err(|e| Self::Error::IoError(e))?;
-                    tmp.seek(SeekFrom::Start(0))
-                        .map_err(|e| Self::Error::IoError(e))?;
-
-                    // write data to tmp
-                    {
-                        let tmp = BufWriter::new(tmp);
-                        to_writer(tmp, &data).map_err(|e| Self::Error::RonError(e))?;
+                if let Some((data, files)) =
+                    &mut *ctx.data.write().expect("failed to get data write lock")
+                {
+                    let mut files = files.lock().expect("failet to get file lock");
+
+                    data.meta.saved = Some(ctx.clock.now());
+                    data.meta.saved_by = ctx
+                        .settings
+                        .read()
+                        .expect("failed to get settings read access")
+                        .user_name();
+
+                    // write recovery copy first, so it is complete if writing the file fails
+                    let started = files.write_copy(&ctx.paths, ctx.clock.now(), data)?;
+                    recovery::write(&files.dataset, data)?;
+                    if started {
+                        // the copy holds nothing the file doesn't
+                        files.discard_copy();
                     }
 
-                    // write data to file
-                    {
-                        // clear file before writing
-                        file.set_len(0).map_err(|e| Self::Error::IoError(e))?;
-                        file.seek(SeekFrom::Start(0))
-                            .map_err(|e| Self::Error::IoError(e))?;
-
-                        let file = BufWriter::new(file);
-                        to_writer(file, &data).map_err(|e| Self::Error::RonError(e))?;
-                    }
+                    events.push(Event::DatasetSaved {
+                        year: data.year,
+                        month: data.month,
+                    });
+                    log::info!("saved dataset {}-{:02}", data.year, data.month);
 
                     Ok(Self::Success::Saved)
                 } else {
                     Err(Self::Error::DatasetNotActive)
                 }
             }
+            Self::SetMetadata { title, notes } => {
+                if let Some((data, _)) =
+                    &mut *ctx.data.write().expect("failed to get data write lock")
+                {
+                    data.meta.title = title.trim().to_string();
+                    data.meta.notes = notes;
+                    events.push(Event::Updated {
+                        entity: Entity::Metadata,
+                    });
+
+                    Ok(Self::Success::SetMetadata)
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+            Self::SetPlanningDate { date } => {
+                *ctx.planning_date
+                    .write()
+                    .expect("failed to get planning date write access") = date;
+                log::info!("set planning date to {:?}", date);
+
+                Ok(Self::Success::SetPlanningDate)
+            }
+            Self::SetSettings { settings } => {
+                let mut current = ctx
+                    .settings
+                    .write()
+                    .expect("failed to get settings write access");
+                settings.save(&ctx.paths.settings_file())?;
+
+                log::set_max_level(settings.log_level);
+                *current = settings;
+                log::info!("changed settings: {:?}", *current);
+
+                Ok(Self::Success::SavedSettings)
+            }
         }
     }
 }
 
-/// Open the permanent and temporary file and set the DATA variable.
-fn open_files(year: i32, month: u32) -> Result<(File, File), GlobalCmdError> {
-    let file = dataset_file_name(year, month);
-    if let Some(x) = file.parent() {
-        create_dir_all(x).map_err(|e| GlobalCmdError::IoError(e))?;
-    }
-    let file = OpenOptions::new()
-        .read(true)
-        .write(true)
-        .create(true)
-        .open(file)
-        .map_err(|e| GlobalCmdError::IoError(e))?;
-    file.lock_exclusive()
-        .map_err(|_| GlobalCmdError::LockError)?;
+/// Get all recovery copies of a dataset.
+fn recovery_copies(
+    paths: &Paths,
+    year: i32,
+    month: u32,
+) -> Result<Vec<RecoveryCopy>, GlobalCmdError> {
+    recovery::list(paths, year, month).map_err(|source| GlobalCmdError::IoError {
+        path: Some(paths.recovery_dir(year, month)),
+        source,
+    })
+}
 
-    let tmp = dataset_tmp_name(year, month);
-    if let Some(x) = tmp.parent() {
-        create_dir_all(x).map_err(|e| GlobalCmdError::IoError(e))?;
+/// Read the content of a dataset file, without opening the dataset.
+fn read_dataset(paths: &Paths, year: i32, month: u32) -> Result<Data, GlobalCmdError> {
+    let path = paths.dataset_file(year, month);
+    if !path.is_file() {
+        return Err(GlobalCmdError::NoDataset { year, month });
     }
-    let tmp = OpenOptions::new()
-        .read(true)
-        .write(true)
-        .create(true)
-        .open(tmp)
-        .map_err(|e| GlobalCmdError::IoError(e))?;
-    tmp.lock_exclusive()
-        .map_err(|_| GlobalCmdError::LockError)?;
 
-    Ok((file, tmp))
+    read_file(path)
 }
 
-/// Get the name of the file for a specific date
-fn dataset_file_name(year: i32, month: u32) -> PathBuf {
-    let mut file = DATADIR.clone();
-    file.push(year.to_string());
-    file.push(month.to_string());
-    file.set_extension("ron");
+/// Read the content of a recovery copy.
+fn read_recovery_copy(
+    paths: &Paths,
+    year: i32,
+    month: u32,
+    copy: &RecoveryCopy,
+) -> Result<Data, GlobalCmdError> {
+    read_file(paths.recovery_file(year, month, &copy.id))
+}
 
-    file
+/// Merge the datasets in three files.
+fn merge_files(base: PathBuf, ours: PathBuf, theirs: PathBuf) -> Result<Merge, GlobalCmdError> {
+    Ok(Merge::new(
+        read_file(base)?,
+        read_file(ours)?,
+        read_file(theirs)?,
+    )?)
 }
 
-/// Get the name of a tmp file for a specific date
-fn dataset_tmp_name(year: i32, month: u32) -> PathBuf {
-    let mut hasher = DefaultHasher::new();
-    DATADIR.hash(&mut hasher);
+/// Get the absolute form of `path`, with all links resolved.
+///
+/// Works for files that don't exist yet, as long as their directory does. Returns `path` itself if
+/// neither can be resolved.
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize()
+        .or_else(|e| match (path.parent(), path.file_name()) {
+            (Some(dir), Some(name)) if dir.as_os_str().is_empty() => {
+                Path::new(".").canonicalize().map(|d| d.join(name))
+            }
+            (Some(dir), Some(name)) => dir.canonicalize().map(|d| d.join(name)),
+            _ => Err(e),
+        })
+        .unwrap_or_else(|_| path.to_path_buf())
+}
 
-    let mut file = PROJECT_DIRS.cache_dir().to_path_buf();
-    file.push(hasher.finish().to_string());
-    file.push(year.to_string());
-    file.push(month.to_string());
-    file.set_extension("ron");
+/// Read a dataset from any file.
+fn read_file(path: PathBuf) -> Result<Data, GlobalCmdError> {
+    let file = File::open(&path).map_err(|source| GlobalCmdError::IoError {
+        path: Some(path),
+        source,
+    })?;
 
-    file
+    Ok(from_reader(BufReader::new(file))?)
+}
+
+/// Open and lock a single file, creating it and its parent directories if necessary.
+fn open_file(path: PathBuf) -> Result<File, GlobalCmdError> {
+    let io_error = |source| GlobalCmdError::IoError {
+        path: Some(path.clone()),
+        source,
+    };
+
+    if let Some(x) = path.parent() {
+        create_dir_all(x).map_err(io_error)?;
+    }
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .open(&path)
+        .map_err(io_error)?;
+    file.lock_exclusive()
+        .map_err(|_| GlobalCmdError::LockError { path: path.clone() })?;
+
+    Ok(file)
 }
 
 #[derive(Debug, serde::Serialize)]
 #[serde(tag = "state", rename_all = "camelCase")]
 pub enum State {
     Select,
-    Loaded { year: i32, month: u32 },
+    Loaded {
+        year: i32,
+        month: u32,
+        meta: Metadata,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::run;
+    use chrono::{Local, TimeZone};
+    use tempfile::TempDir;
+
+    fn context(dir: &TempDir) -> Context {
+        Context::in_dir(dir.path(), Local.ymd(2021, 3, 15).and_hms(12, 0, 0))
+    }
+
+    fn execute(ctx: &Context, cmd: GlobalCmd) -> GlobalCmdSuccess {
+        run(ctx, cmd, &mut Vec::new()).expect("command failed")
+    }
+
+    #[test]
+    fn saved_dataset_can_be_opened() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = context(&dir);
+
+        execute(
+            &ctx,
+            GlobalCmd::NewDataset {
+                next_month: false,
+                template: None,
+            },
+        );
+        execute(
+            &ctx,
+            GlobalCmd::SetMetadata {
+                title: " March ".to_string(),
+                notes: String::new(),
+            },
+        );
+        assert_eq!(recovery::list(&ctx.paths, 2021, 3).unwrap().len(), 1);
+
+        execute(&ctx, GlobalCmd::Save);
+        execute(&ctx, GlobalCmd::CloseDataset);
+        assert!(ctx.data.read().unwrap().is_none());
+        let copies = recovery::list(&ctx.paths, 2021, 3).unwrap();
+        assert_eq!(copies.len(), 1);
+        assert!(!copies[0].is_newer);
+
+        match execute(
+            &ctx,
+            GlobalCmd::OpenDataset {
+                year: 2021,
+                month: 3,
+            },
+        ) {
+            GlobalCmdSuccess::OpenedDataset {
+                mismatch,
+                is_backup,
+                has_newer_recovery_copy,
+            } => assert!(!mismatch && !is_backup && !has_newer_recovery_copy),
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        let data = ctx.data.read().unwrap();
+        let (data, _) = data.as_ref().unwrap();
+        assert_eq!((data.year, data.month), (2021, 3));
+        assert_eq!(data.meta.title, "March");
+        assert_eq!(data.meta.saved, Some(ctx.clock.now()));
+    }
+
+    #[test]
+    fn unchanged_dataset_has_no_recovery_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = context(&dir);
+
+        execute(
+            &ctx,
+            GlobalCmd::NewDataset {
+                next_month: true,
+                template: None,
+            },
+        );
+        execute(&ctx, GlobalCmd::CloseDataset);
+        execute(
+            &ctx,
+            GlobalCmd::OpenDataset {
+                year: 2021,
+                month: 4,
+            },
+        );
+
+        assert!(recovery::list(&ctx.paths, 2021, 4).unwrap().is_empty());
+    }
 }
@@ -1,5 +1,18 @@
-use crate::{data::meal::Meal, DATA};
-use std::{collections::BTreeMap, todo};
+use super::error::{CmdError, ErrorPayload};
+use crate::{
+    context::Context,
+    data::{
+        meal::{Meal, MealEntry},
+        nutrition::NutritionTotals,
+        order::{self, Movement},
+        reference::ReferenceError,
+        selection::{Selection, SelectionError},
+        Data,
+    },
+    event::{Entity, Event},
+    store::StoreError,
+};
+use std::collections::{BTreeMap, BTreeSet};
 use uuid::Uuid;
 
 #[derive(Debug, serde::Deserialize)]
@@ -13,49 +26,210 @@ pub enum MealCmd {
     /// # Error variants
     /// - [`MealCmdError::DatasetNotActive`]: if there is no active dataset
     /// - [`MealCmdError::EmptyName`]: if the provided name is empty
-    /// - [`MealCmdError::ComponentNotFound`]: if a provided component doesn't exist
-    /// - [`MealCmdError::VariantNotFound`]: if a provided variant doesn't exist
+    /// - [`MealCmdError::ReferenceError`]: if a provided component or variant doesn't exist
+    /// - [`MealCmdError::TooFewComponents`]: if the meal has less components of a category than
+    ///   required
+    /// - [`MealCmdError::TooManyComponents`]: if the meal has more components of a category than
+    ///   allowed
     AddMeal {
         name: String,
         short: String,
         components: BTreeMap<Uuid, Option<Uuid>>,
     },
-    /// Returns a list of all meals.
+    /// Returns a list of all meals, together with their allergens, additives and the diets they
+    /// are suitable for.
     ///
     /// # Success variants
     /// - [`MealCmdSuccess::GotMeals`]
     ///
     /// # Error variants
     /// - [`MealCmdError::DatasetNotActive`]: if there is no active dataset
-    GetMeals,
+    /// - [`MealCmdError::StoreError`]: if the diets can't be read
+    GetMeals {
+        /// Only return meals that are suitable for all of these diets.
+        #[serde(default)]
+        diets: BTreeSet<Uuid>,
+    },
+    /// Returns the price of a concrete configuration of a meal, in cents.
+    ///
+    /// # Success variants
+    /// - [`MealCmdSuccess::GotPrice`]
+    ///
+    /// # Error variants
+    /// - [`MealCmdError::DatasetNotActive`]: if there is no active dataset
+    /// - [`MealCmdError::SelectionError`]: if the selection doesn't fit the meal, or the meal
+    ///   has no price
+    GetPrice { selection: Selection },
+    /// Returns the nutrition values of all meals.
+    ///
+    /// # Success variants
+    /// - [`MealCmdSuccess::GotNutrition`]
+    ///
+    /// # Error variants
+    /// - [`MealCmdError::DatasetNotActive`]: if there is no active dataset
+    GetNutrition,
+    /// Returns the nutrition values of a concrete configuration of a meal, including the chosen
+    /// options.
+    ///
+    /// # Success variants
+    /// - [`MealCmdSuccess::GotNutritionOfSelection`]
+    ///
+    /// # Error variants
+    /// - [`MealCmdError::DatasetNotActive`]: if there is no active dataset
+    /// - [`MealCmdError::SelectionError`]: if the selection doesn't fit the meal
+    GetNutritionOfSelection { selection: Selection },
+    /// Changes the display position of a meal.
+    ///
+    /// # Success variants
+    /// - [`MealCmdSuccess::MovedMeal`]
+    ///
+    /// # Error variants
+    /// - [`MealCmdError::DatasetNotActive`]: if there is no active dataset
+    /// - [`MealCmdError::NotFound`]: if the meal doesn't exist
+    MoveMeal { meal: Uuid, movement: Movement },
+    /// Sets the base price of a meal.
+    ///
+    /// # Success variants
+    /// - [`MealCmdSuccess::SetPrice`]
+    ///
+    /// # Error variants
+    /// - [`MealCmdError::DatasetNotActive`]: if there is no active dataset
+    /// - [`MealCmdError::NegativePrice`]: if the price is negative
+    /// - [`MealCmdError::NotFound`]: if the meal doesn't exist
+    SetPrice {
+        meal: Uuid,
+        /// The price in cents. `None` removes the price.
+        price: Option<i64>,
+    },
+    /// Replaces the name, short name and components of a meal.
+    ///
+    /// # Success variants
+    /// - [`MealCmdSuccess::UpdatedMeal`]
+    ///
+    /// # Error variants
+    /// - [`MealCmdError::DatasetNotActive`]: if there is no active dataset
+    /// - [`MealCmdError::EmptyName`]: if the provided name is empty
+    /// - [`MealCmdError::NotFound`]: if the meal doesn't exist
+    /// - [`MealCmdError::ReferenceError`]: if a provided component or variant doesn't exist
+    /// - [`MealCmdError::TooFewComponents`]: if the meal has less components of a category than
+    ///   required
+    /// - [`MealCmdError::TooManyComponents`]: if the meal has more components of a category than
+    ///   allowed
+    UpdateMeal {
+        meal: Uuid,
+        name: String,
+        short: String,
+        components: BTreeMap<Uuid, Option<Uuid>>,
+    },
 }
 
 #[derive(Debug, serde::Serialize)]
 #[serde(tag = "variant", rename_all = "camelCase")]
 pub enum MealCmdSuccess {
     AddedMeal,
-    GotMeals { data: BTreeMap<Uuid, Meal> },
+    GotMeals {
+        data: BTreeMap<Uuid, MealEntry>,
+        /// The uuids of all meals, in display order.
+        order: Vec<Uuid>,
+    },
+    GotNutrition {
+        data: BTreeMap<Uuid, NutritionTotals>,
+    },
+    GotNutritionOfSelection {
+        data: NutritionTotals,
+    },
+    GotPrice {
+        price: i64,
+    },
+    MovedMeal,
+    SetPrice,
+    UpdatedMeal,
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum MealCmdError {
-    #[error("error.meals.component_not_found")]
-    ComponentNotFound,
     #[error("error.global.dataset_not_active")]
     DatasetNotActive,
     #[error("error.meals.name_empty")]
     EmptyName,
     #[error("error.meals.short_empty")]
     EmptyShort,
-    #[error("error.meals.variant_not_found")]
-    VariantNotFound,
+    #[error("error.meals.negative_price")]
+    NegativePrice,
+    #[error("error.meals.not_found")]
+    NotFound { meal: Uuid },
+    #[error(transparent)]
+    ReferenceError(#[from] ReferenceError),
+    #[error(transparent)]
+    SelectionError(#[from] SelectionError),
+    #[error(transparent)]
+    StoreError(#[from] StoreError),
+    #[error("error.meals.too_few_components")]
+    TooFewComponents {
+        category: Uuid,
+        name: String,
+        min: u32,
+        count: u32,
+    },
+    #[error("error.meals.too_many_components")]
+    TooManyComponents {
+        category: Uuid,
+        name: String,
+        max: u32,
+        count: u32,
+    },
+}
+
+impl CmdError for MealCmdError {
+    fn payload(&self) -> ErrorPayload {
+        match self {
+            Self::DatasetNotActive => {
+                ErrorPayload::new("DATASET_NOT_ACTIVE", "error.global.dataset_not_active")
+            }
+            Self::EmptyName => ErrorPayload::new("NAME_EMPTY", "error.meals.name_empty"),
+            Self::EmptyShort => ErrorPayload::new("SHORT_EMPTY", "error.meals.short_empty"),
+            Self::NegativePrice => {
+                ErrorPayload::new("NEGATIVE_PRICE", "error.meals.negative_price")
+            }
+            Self::NotFound { meal } => {
+                ErrorPayload::new("MEAL_NOT_FOUND", "error.meals.not_found").param("meal", meal)
+            }
+            Self::ReferenceError(e) => e.payload(),
+            Self::SelectionError(e) => e.payload(),
+            Self::StoreError(e) => e.payload(),
+            Self::TooFewComponents {
+                category,
+                name,
+                min,
+                count,
+            } => ErrorPayload::new("TOO_FEW_COMPONENTS", "error.meals.too_few_components")
+                .param("category", category)
+                .param("name", name.as_str())
+                .param("min", *min)
+                .param("count", *count),
+            Self::TooManyComponents {
+                category,
+                name,
+                max,
+                count,
+            } => ErrorPayload::new("TOO_MANY_COMPONENTS", "error.meals.too_many_components")
+                .param("category", category)
+                .param("name", name.as_str())
+                .param("max", *max)
+                .param("count", *count),
+        }
+    }
 }
 
 impl super::CmdAble for MealCmd {
     type Error = MealCmdError;
     type Success = MealCmdSuccess;
 
-    fn execute(self: Self) -> Result<Self::Success, Self::Error> {
+    fn execute(
+        self: Self,
+        ctx: &Context,
+        events: &mut Vec<Event>,
+    ) -> Result<Self::Success, Self::Error> {
         match self {
             Self::AddMeal {
                 name,
@@ -73,41 +247,204 @@ impl super::CmdAble for MealCmd {
                 }
 
                 if let Some((data, _)) =
-                    &mut *DATA.write().expect("failed to get data write access")
+                    &mut *ctx.data.write().expect("failed to get data write access")
                 {
-                    for (k, v) in &components {
-                        if data.components.contains_key(k) {
-                            if let Some(v) = v {
-                                if !data.components[k].variants.contains_key(v) {
-                                    return Err(Self::Error::VariantNotFound);
-                                }
-                            }
-                        } else {
-                            return Err(Self::Error::ComponentNotFound);
-                        }
-                    }
+                    check_components(data, &components)?;
 
                     let name = name.to_string();
                     let short = short.to_string();
-                    data.meals
-                        .insert(Uuid::new_v4(), Meal::new(name, short, components));
-
-                    dbg!(data);
+                    let uuid = Uuid::new_v4();
+                    data.meals.insert(uuid, Meal::new(name, short, components));
+                    order::push(&mut data.meal_order, &data.meals, uuid);
+                    events.push(Event::Added {
+                        entity: Entity::Meal { uuid },
+                    });
+                    log::debug!("added meal {}", uuid);
 
                     Ok(Self::Success::AddedMeal)
                 } else {
                     Err(Self::Error::DatasetNotActive)
                 }
             }
-            Self::GetMeals => {
-                if let Some((data, _)) = &*DATA.read().expect("failed to get data read access") {
+            Self::GetMeals { diets } => {
+                let known = ctx.diets.read()?;
+
+                if let Some((data, _)) = &*ctx.data.read().expect("failed to get data read access")
+                {
+                    let meals: BTreeMap<_, _> = data
+                        .meals
+                        .iter()
+                        .map(|(k, v)| {
+                            let mut entry = MealEntry::new(v, &data.components);
+                            // variants may still refer to deleted diets
+                            entry.diets.retain(|d| known.contains_key(d));
+                            (*k, entry)
+                        })
+                        .filter(|(_, v)| v.diets.is_superset(&diets))
+                        .collect();
+
                     Ok(Self::Success::GotMeals {
-                        data: data.meals.clone(),
+                        order: order::sorted(&data.meal_order, &meals),
+                        data: meals,
+                    })
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+            Self::GetNutrition => {
+                if let Some((data, _)) = &*ctx.data.read().expect("failed to get data read access")
+                {
+                    Ok(Self::Success::GotNutrition {
+                        data: data
+                            .meals
+                            .iter()
+                            .map(|(k, v)| (*k, NutritionTotals::of_meal(v, &data.components)))
+                            .collect(),
                     })
                 } else {
                     Err(Self::Error::DatasetNotActive)
                 }
             }
+            Self::GetNutritionOfSelection { selection } => {
+                if let Some((data, _)) = &*ctx.data.read().expect("failed to get data read access")
+                {
+                    Ok(Self::Success::GotNutritionOfSelection {
+                        data: selection.nutrition(data)?,
+                    })
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+            Self::GetPrice { selection } => {
+                if let Some((data, _)) = &*ctx.data.read().expect("failed to get data read access")
+                {
+                    Ok(Self::Success::GotPrice {
+                        price: selection.price(data)?,
+                    })
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+            Self::MoveMeal { meal, movement } => {
+                if let Some((data, _)) =
+                    &mut *ctx.data.write().expect("failed to get data write access")
+                {
+                    if !order::shift(&mut data.meal_order, &data.meals, meal, movement) {
+                        return Err(Self::Error::NotFound { meal });
+                    }
+
+                    events.push(Event::Updated {
+                        entity: Entity::Meal { uuid: meal },
+                    });
+
+                    Ok(Self::Success::MovedMeal)
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+            Self::SetPrice { meal, price } => {
+                if price.map_or(false, |p| p < 0) {
+                    return Err(Self::Error::NegativePrice);
+                }
+
+                if let Some((data, _)) =
+                    &mut *ctx.data.write().expect("failed to get data write access")
+                {
+                    data.meals
+                        .get_mut(&meal)
+                        .ok_or(Self::Error::NotFound { meal })?
+                        .price = price;
+                    events.push(Event::Updated {
+                        entity: Entity::Meal { uuid: meal },
+                    });
+
+                    Ok(Self::Success::SetPrice)
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+            Self::UpdateMeal {
+                meal,
+                name,
+                short,
+                components,
+            } => {
+                let name = name.trim();
+                if name.is_empty() {
+                    return Err(Self::Error::EmptyName);
+                }
+
+                let short = short.trim();
+                if short.is_empty() {
+                    return Err(Self::Error::EmptyShort);
+                }
+
+                if let Some((data, _)) =
+                    &mut *ctx.data.write().expect("failed to get data write access")
+                {
+                    if !data.meals.contains_key(&meal) {
+                        return Err(Self::Error::NotFound { meal });
+                    }
+                    check_components(data, &components)?;
+
+                    let m = data.meals.get_mut(&meal).expect("meal vanished");
+                    let new = Meal::new(name.to_string(), short.to_string(), components);
+                    m.name = new.name;
+                    m.short = new.short;
+                    m.components = new.components;
+                    events.push(Event::Updated {
+                        entity: Entity::Meal { uuid: meal },
+                    });
+                    log::debug!("updated meal {}", meal);
+
+                    Ok(Self::Success::UpdatedMeal)
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+        }
+    }
+}
+
+/// Check that all `components` and their variants exist and aren't deleted, and that the meal they
+/// make up keeps to the limits of all categories.
+fn check_components(
+    data: &Data,
+    components: &BTreeMap<Uuid, Option<Uuid>>,
+) -> Result<(), MealCmdError> {
+    let mut counts = BTreeMap::new();
+    for (k, v) in components {
+        let cmp = data.component(*k)?;
+        if let Some(v) = v {
+            data.variant(*k, *v)?;
+        }
+
+        if let Some(category) = cmp.category {
+            *counts.entry(category).or_insert(0) += 1;
+        }
+    }
+
+    for (uuid, cat) in data.categories.iter().filter(|(_, c)| !c.delete) {
+        let count = counts.get(uuid).copied().unwrap_or(0);
+        if count < cat.min {
+            return Err(MealCmdError::TooFewComponents {
+                category: *uuid,
+                name: cat.name.clone(),
+                min: cat.min,
+                count,
+            });
+        }
+        if let Some(max) = cat.max {
+            if count > max {
+                return Err(MealCmdError::TooManyComponents {
+                    category: *uuid,
+                    name: cat.name.clone(),
+                    max,
+                    count,
+                });
+            }
         }
     }
+
+    Ok(())
 }
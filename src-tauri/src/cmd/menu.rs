@@ -0,0 +1,334 @@
+use super::error::{CmdError, ErrorPayload};
+use crate::{
+    context::Context,
+    data::{calendar::DeliveryCalendar, Data},
+    event::{Entity, Event},
+    store::StoreError,
+};
+use chrono::{Datelike, Duration, NaiveDate};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(tag = "cmd", rename_all = "camelCase")]
+pub enum MenuCmd {
+    /// Offers a meal on a day, after the meals already offered.
+    ///
+    /// Assigning a meal that is already offered on the day does nothing.
+    ///
+    /// # Success variants
+    /// - [`MenuCmdSuccess::AssignedMeal`]
+    ///
+    /// # Error variants
+    /// - [`MenuCmdError::DatasetNotActive`]: if there is no active dataset
+    /// - [`MenuCmdError::DateOutsideMonth`]: if the day isn't part of the datasets month
+    /// - [`MenuCmdError::MealNotFound`]: if the meal doesn't exist
+    /// - [`MenuCmdError::NoDelivery`]: if meals aren't delivered on the day
+    /// - [`MenuCmdError::StoreError`]: if the delivery calendar can't be read
+    AssignMeal { date: NaiveDate, meal: Uuid },
+    /// Replaces the meals offered on a day with the ones offered on another day.
+    ///
+    /// # Success variants
+    /// - [`MenuCmdSuccess::CopiedDay`]
+    ///
+    /// # Error variants
+    /// - [`MenuCmdError::DatasetNotActive`]: if there is no active dataset
+    /// - [`MenuCmdError::DateOutsideMonth`]: if one of the days isn't part of the datasets month
+    /// - [`MenuCmdError::HasOrders`]: if a meal that would be removed was ordered on the day
+    /// - [`MenuCmdError::NoDelivery`]: if meals aren't delivered on the day copied to
+    /// - [`MenuCmdError::StoreError`]: if the delivery calendar can't be read
+    CopyDay { from: NaiveDate, to: NaiveDate },
+    /// Replaces the meals offered in a week with the ones offered in another week.
+    ///
+    /// Weeks start on monday and are identified by any of their days. Days that aren't part of
+    /// the datasets month are skipped, both when reading and when writing. Days meals aren't
+    /// delivered on are skipped when writing.
+    ///
+    /// # Success variants
+    /// - [`MenuCmdSuccess::CopiedWeek`]
+    ///
+    /// # Error variants
+    /// - [`MenuCmdError::DatasetNotActive`]: if there is no active dataset
+    /// - [`MenuCmdError::DateOutsideMonth`]: if one of the days isn't part of the datasets month
+    /// - [`MenuCmdError::HasOrders`]: if a meal that would be removed was ordered on one of the
+    ///   days
+    /// - [`MenuCmdError::StoreError`]: if the delivery calendar can't be read
+    CopyWeek { from: NaiveDate, to: NaiveDate },
+    /// Returns the meals offered on every day of the month.
+    ///
+    /// Meals that were deleted are left out.
+    ///
+    /// # Success variants
+    /// - [`MenuCmdSuccess::GotMenu`]
+    ///
+    /// # Error variants
+    /// - [`MenuCmdError::DatasetNotActive`]: if there is no active dataset
+    GetMenu,
+    /// Stops offering a meal on a day.
+    ///
+    /// # Success variants
+    /// - [`MenuCmdSuccess::UnassignedMeal`]
+    ///
+    /// # Error variants
+    /// - [`MenuCmdError::DatasetNotActive`]: if there is no active dataset
+    /// - [`MenuCmdError::HasOrders`]: if the meal was ordered on the day
+    /// - [`MenuCmdError::NotAssigned`]: if the meal isn't offered on the day
+    UnassignMeal { date: NaiveDate, meal: Uuid },
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(tag = "variant", rename_all = "camelCase")]
+pub enum MenuCmdSuccess {
+    AssignedMeal,
+    CopiedDay,
+    CopiedWeek,
+    GotMenu {
+        /// The meals of every day of the month, including days without meals.
+        data: BTreeMap<NaiveDate, Vec<Uuid>>,
+    },
+    UnassignedMeal,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum MenuCmdError {
+    #[error("error.global.dataset_not_active")]
+    DatasetNotActive,
+    #[error("error.menu.date_outside_month")]
+    DateOutsideMonth { date: NaiveDate },
+    #[error("error.menu.has_orders")]
+    HasOrders { date: NaiveDate, meal: Uuid },
+    #[error("error.menu.meal_not_found")]
+    MealNotFound { meal: Uuid },
+    #[error("error.menu.no_delivery")]
+    NoDelivery { date: NaiveDate },
+    #[error("error.menu.not_assigned")]
+    NotAssigned { date: NaiveDate, meal: Uuid },
+    #[error(transparent)]
+    StoreError(#[from] StoreError),
+}
+
+impl CmdError for MenuCmdError {
+    fn payload(&self) -> ErrorPayload {
+        match self {
+            Self::DatasetNotActive => {
+                ErrorPayload::new("DATASET_NOT_ACTIVE", "error.global.dataset_not_active")
+            }
+            Self::DateOutsideMonth { date } => {
+                ErrorPayload::new("DATE_OUTSIDE_MONTH", "error.menu.date_outside_month")
+                    .param("date", date)
+            }
+            Self::HasOrders { date, meal } => {
+                ErrorPayload::new("HAS_ORDERS", "error.menu.has_orders")
+                    .param("date", date)
+                    .param("meal", meal)
+            }
+            Self::MealNotFound { meal } => {
+                ErrorPayload::new("MEAL_NOT_FOUND", "error.menu.meal_not_found").param("meal", meal)
+            }
+            Self::NoDelivery { date } => {
+                ErrorPayload::new("NO_DELIVERY", "error.menu.no_delivery").param("date", date)
+            }
+            Self::NotAssigned { date, meal } => {
+                ErrorPayload::new("MEAL_NOT_ASSIGNED", "error.menu.not_assigned")
+                    .param("date", date)
+                    .param("meal", meal)
+            }
+            Self::StoreError(e) => e.payload(),
+        }
+    }
+}
+
+impl super::CmdAble for MenuCmd {
+    type Error = MenuCmdError;
+    type Success = MenuCmdSuccess;
+
+    fn execute(
+        self: Self,
+        ctx: &Context,
+        events: &mut Vec<Event>,
+    ) -> Result<Self::Success, Self::Error> {
+        match self {
+            Self::AssignMeal { date, meal } => {
+                let cal = ctx.calendar.read()?;
+
+                if let Some((data, _)) =
+                    &mut *ctx.data.write().expect("failed to get data write access")
+                {
+                    check_date(data, date)?;
+                    check_delivery(&cal, date)?;
+                    if !data.meals.get(&meal).map_or(false, |m| !m.delete) {
+                        return Err(Self::Error::MealNotFound { meal });
+                    }
+
+                    let day = data.menu.entry(date).or_default();
+                    if !day.contains(&meal) {
+                        day.push(meal);
+                        events.push(Event::Updated {
+                            entity: Entity::Day { date },
+                        });
+                        log::debug!("assigned meal {} to {}", meal, date);
+                    }
+
+                    Ok(Self::Success::AssignedMeal)
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+            Self::CopyDay { from, to } => {
+                let cal = ctx.calendar.read()?;
+
+                if let Some((data, _)) =
+                    &mut *ctx.data.write().expect("failed to get data write access")
+                {
+                    check_date(data, from)?;
+                    check_date(data, to)?;
+                    check_delivery(&cal, to)?;
+                    check_orders(data, to, data.menu.get(&from))?;
+
+                    copy_day(data, from, to);
+                    events.push(Event::Updated {
+                        entity: Entity::Day { date: to },
+                    });
+
+                    Ok(Self::Success::CopiedDay)
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+            Self::CopyWeek { from, to } => {
+                let cal = ctx.calendar.read()?;
+
+                if let Some((data, _)) =
+                    &mut *ctx.data.write().expect("failed to get data write access")
+                {
+                    check_date(data, from)?;
+                    check_date(data, to)?;
+
+                    let (from, to) = (week_start(from), week_start(to));
+                    let days: Vec<_> = (0..7)
+                        .map(|offset| (from + Duration::days(offset), to + Duration::days(offset)))
+                        .filter(|(from, to)| {
+                            data.contains(*from) && data.contains(*to) && cal.is_delivery_day(*to)
+                        })
+                        .collect();
+                    for (from, to) in &days {
+                        check_orders(data, *to, data.menu.get(from))?;
+                    }
+
+                    for (from, to) in days {
+                        copy_day(data, from, to);
+                        events.push(Event::Updated {
+                            entity: Entity::Day { date: to },
+                        });
+                    }
+
+                    Ok(Self::Success::CopiedWeek)
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+            Self::GetMenu => {
+                if let Some((data, _)) = &*ctx.data.read().expect("failed to get data read access")
+                {
+                    let data = data
+                        .days()
+                        .map(|date| {
+                            let meals = data
+                                .menu
+                                .get(&date)
+                                .into_iter()
+                                .flatten()
+                                .filter(|m| data.meals.get(m).map_or(false, |m| !m.delete))
+                                .copied()
+                                .collect();
+                            (date, meals)
+                        })
+                        .collect();
+
+                    Ok(Self::Success::GotMenu { data })
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+            Self::UnassignMeal { date, meal } => {
+                if let Some((data, _)) =
+                    &mut *ctx.data.write().expect("failed to get data write access")
+                {
+                    let mut day = data
+                        .menu
+                        .get(&date)
+                        .filter(|d| d.contains(&meal))
+                        .cloned()
+                        .ok_or(Self::Error::NotAssigned { date, meal })?;
+                    day.retain(|m| *m != meal);
+                    check_orders(data, date, Some(&day))?;
+                    if day.is_empty() {
+                        data.menu.remove(&date);
+                    } else {
+                        data.menu.insert(date, day);
+                    }
+
+                    events.push(Event::Updated {
+                        entity: Entity::Day { date },
+                    });
+                    log::debug!("unassigned meal {} from {}", meal, date);
+
+                    Ok(Self::Success::UnassignedMeal)
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+        }
+    }
+}
+
+/// Check that `date` lies within the month of `data`.
+fn check_date(data: &Data, date: NaiveDate) -> Result<(), MenuCmdError> {
+    if data.contains(date) {
+        Ok(())
+    } else {
+        Err(MenuCmdError::DateOutsideMonth { date })
+    }
+}
+
+/// Check that meals are delivered on `date`.
+fn check_delivery(cal: &DeliveryCalendar, date: NaiveDate) -> Result<(), MenuCmdError> {
+    if cal.is_delivery_day(date) {
+        Ok(())
+    } else {
+        Err(MenuCmdError::NoDelivery { date })
+    }
+}
+
+/// Check that no orders for `date` would be left with a meal that isn't on the menu, if the menu
+/// of that day only consisted of `meals`.
+fn check_orders(
+    data: &Data,
+    date: NaiveDate,
+    meals: Option<&Vec<Uuid>>,
+) -> Result<(), MenuCmdError> {
+    let ordered = data
+        .orders
+        .values()
+        .filter(|o| !o.cancelled && o.date == date)
+        .map(|o| o.selection.meal)
+        .find(|m| !meals.map_or(false, |x| x.contains(m)));
+
+    match ordered {
+        Some(meal) => Err(MenuCmdError::HasOrders { date, meal }),
+        None => Ok(()),
+    }
+}
+
+/// Replace the meals of `to` with the ones of `from`.
+fn copy_day(data: &mut Data, from: NaiveDate, to: NaiveDate) {
+    match data.menu.get(&from).cloned() {
+        Some(meals) => data.menu.insert(to, meals),
+        None => data.menu.remove(&to),
+    };
+}
+
+/// Get the monday of the week `date` lies in.
+fn week_start(date: NaiveDate) -> NaiveDate {
+    date - Duration::days(date.weekday().num_days_from_monday().into())
+}
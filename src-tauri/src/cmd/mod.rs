@@ -1,17 +1,55 @@
+pub mod calendar;
+pub mod category;
 pub mod component;
+pub mod customer;
+pub mod diet;
+pub mod error;
 pub mod global;
 pub mod meal;
+pub mod menu;
+pub mod order;
+pub mod production;
+pub mod route;
+pub mod standing_order;
+pub mod template;
 
-use tauri::execute_promise;
+use crate::{
+    context::Context,
+    event::{self, Event},
+    recovery,
+};
+use error::CmdError;
+use std::sync::Arc;
+use tauri::api::rpc::{format_callback, format_callback_result};
 
 #[derive(Debug, serde::Deserialize)]
 #[serde(tag = "cmd", rename_all = "camelCase")]
 pub enum Cmd {
+    Calendar {
+        sub: calendar::CalendarCmd,
+        callback: String,
+        error: String,
+    },
+    Category {
+        sub: category::CategoryCmd,
+        callback: String,
+        error: String,
+    },
     Component {
         sub: component::ComponentCmd,
         callback: String,
         error: String,
     },
+    Customer {
+        sub: customer::CustomerCmd,
+        callback: String,
+        error: String,
+    },
+    Diet {
+        sub: diet::DietCmd,
+        callback: String,
+        error: String,
+    },
     Global {
         sub: global::GlobalCmd,
         callback: String,
@@ -22,50 +60,165 @@ pub enum Cmd {
         callback: String,
         error: String,
     },
+    Menu {
+        sub: menu::MenuCmd,
+        callback: String,
+        error: String,
+    },
+    Order {
+        sub: order::OrderCmd,
+        callback: String,
+        error: String,
+    },
+    Production {
+        sub: production::ProductionCmd,
+        callback: String,
+        error: String,
+    },
+    Route {
+        sub: route::RouteCmd,
+        callback: String,
+        error: String,
+    },
+    StandingOrder {
+        sub: standing_order::StandingOrderCmd,
+        callback: String,
+        error: String,
+    },
+    Template {
+        sub: template::TemplateCmd,
+        callback: String,
+        error: String,
+    },
 }
 
 impl Cmd {
-    pub fn execute(self, webview: &mut tauri::Webview) -> Result<(), String> {
+    pub fn execute(self, webview: &mut tauri::Webview, ctx: Arc<Context>) -> Result<(), String> {
         match self {
+            Self::Calendar {
+                sub,
+                callback,
+                error,
+            } => execute_sub(webview, ctx, sub, callback, error),
+            Self::Category {
+                sub,
+                callback,
+                error,
+            } => execute_sub(webview, ctx, sub, callback, error),
             Self::Component {
                 sub,
-                callback: success_callback,
-                error: error_callback,
-            } => execute_promise(
-                webview,
-                || sub.execute().map_err(|e| e.into()),
-                success_callback,
-                error_callback,
-            ),
+                callback,
+                error,
+            } => execute_sub(webview, ctx, sub, callback, error),
+            Self::Customer {
+                sub,
+                callback,
+                error,
+            } => execute_sub(webview, ctx, sub, callback, error),
+            Self::Diet {
+                sub,
+                callback,
+                error,
+            } => execute_sub(webview, ctx, sub, callback, error),
             Self::Global {
                 sub,
-                callback: success_callback,
-                error: error_callback,
-            } => execute_promise(
-                webview,
-                || sub.execute().map_err(|e| e.into()),
-                success_callback,
-                error_callback,
-            ),
+                callback,
+                error,
+            } => execute_sub(webview, ctx, sub, callback, error),
             Self::Meal {
                 sub,
-                callback: success_callback,
-                error: error_callback,
-            } => execute_promise(
-                webview,
-                || sub.execute().map_err(|e| e.into()),
-                success_callback,
-                error_callback,
-            ),
+                callback,
+                error,
+            } => execute_sub(webview, ctx, sub, callback, error),
+            Self::Menu {
+                sub,
+                callback,
+                error,
+            } => execute_sub(webview, ctx, sub, callback, error),
+            Self::Order {
+                sub,
+                callback,
+                error,
+            } => execute_sub(webview, ctx, sub, callback, error),
+            Self::Production {
+                sub,
+                callback,
+                error,
+            } => execute_sub(webview, ctx, sub, callback, error),
+            Self::Route {
+                sub,
+                callback,
+                error,
+            } => execute_sub(webview, ctx, sub, callback, error),
+            Self::StandingOrder {
+                sub,
+                callback,
+                error,
+            } => execute_sub(webview, ctx, sub, callback, error),
+            Self::Template {
+                sub,
+                callback,
+                error,
+            } => execute_sub(webview, ctx, sub, callback, error),
         };
 
         Ok(())
     }
 }
 
+/// Execute a subcommand asynchronously and emit the events it produced afterwards.
+///
+/// Works like [`tauri::execute_promise`], but passes errors to the frontend as structured
+/// [`error::ErrorPayload`]s instead of strings.
+fn execute_sub<C: CmdAble + Send + 'static>(
+    webview: &mut tauri::Webview,
+    ctx: Arc<Context>,
+    sub: C,
+    success_callback: String,
+    error_callback: String,
+) {
+    let mut handle = webview.as_mut();
+
+    tauri::spawn(move || {
+        let mut events = Vec::new();
+        let res = run(&ctx, sub, &mut events);
+        event::emit(&mut handle, events);
+
+        let res = res.map_err(|e| e.payload());
+        let callback = match format_callback_result(res, success_callback, error_callback.clone()) {
+            Ok(callback) => callback,
+            Err(e) => format_callback(error_callback, e.to_string()),
+        };
+        handle
+            .dispatch(move |webview| webview.eval(&callback))
+            .expect("failed to dispatch promise callback");
+    });
+}
+
+/// Execute a subcommand and write the changes it made to the recovery copy.
+fn run<C: CmdAble>(ctx: &Context, sub: C, events: &mut Vec<Event>) -> Result<C::Success, C::Error> {
+    let res = sub.execute(ctx, events);
+    if let Err(e) = &res {
+        log::warn!("command failed: {}", e);
+    }
+    if events.iter().any(Event::is_change) {
+        recovery::checkpoint(ctx);
+    }
+
+    res
+}
+
 pub trait CmdAble {
-    type Error: std::error::Error;
+    type Error: CmdError;
     type Success: serde::Serialize;
 
-    fn execute(self: Self) -> Result<Self::Success, Self::Error>;
+    /// Execute the command within `ctx`.
+    ///
+    /// Every change to the application data is recorded by pushing an [`Event`] to `events`.
+    /// The events are emitted to the frontend once the command finished.
+    fn execute(
+        self: Self,
+        ctx: &Context,
+        events: &mut Vec<Event>,
+    ) -> Result<Self::Success, Self::Error>;
 }
@@ -0,0 +1,394 @@
+use super::error::{CmdError, ErrorPayload};
+use crate::{
+    context::Context,
+    data::{
+        allergen::Allergen,
+        calendar::DeliveryCalendar,
+        conflict::Conflict,
+        customer::Customer,
+        customer_order::Order,
+        diet::Diet,
+        selection::{Selection, SelectionError},
+        Data,
+    },
+    event::{Entity, Event},
+    store::StoreError,
+};
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(tag = "cmd", rename_all = "camelCase")]
+pub enum OrderCmd {
+    /// Adds a new order.
+    ///
+    /// Intolerances and diets of the customer the meal doesn't keep to are returned as warnings.
+    ///
+    /// # Success variants
+    /// - [`OrderCmdSuccess::AddedOrder`]
+    ///
+    /// # Error variants
+    /// - [`OrderCmdError::Allergy`]: if the meal contains an allergen the customer is allergic to
+    /// - [`OrderCmdError::CustomerInactive`]: if meals aren't delivered to the customer on the day
+    /// - [`OrderCmdError::CustomerNotFound`]: if the customer doesn't exist
+    /// - [`OrderCmdError::DatasetNotActive`]: if there is no active dataset
+    /// - [`OrderCmdError::DateOutsideMonth`]: if the day isn't part of the datasets month
+    /// - [`OrderCmdError::InvalidQuantity`]: if the quantity is 0
+    /// - [`OrderCmdError::NoDelivery`]: if meals aren't delivered on the day
+    /// - [`OrderCmdError::NotOnMenu`]: if the meal isn't offered on the day
+    /// - [`OrderCmdError::SelectionError`]: if the meal, variants or options don't exist or don't
+    ///   fit together
+    /// - [`OrderCmdError::StoreError`]: if the customers, diets or delivery calendar can't be
+    ///   read
+    AddOrder {
+        customer: Uuid,
+        date: NaiveDate,
+        selection: Selection,
+        quantity: u32,
+    },
+    /// Cancels an order.
+    ///
+    /// # Success variants
+    /// - [`OrderCmdSuccess::CancelledOrder`]
+    ///
+    /// # Error variants
+    /// - [`OrderCmdError::DatasetNotActive`]: if there is no active dataset
+    /// - [`OrderCmdError::NotFound`]: if the order doesn't exist or is already cancelled
+    CancelOrder { order: Uuid },
+    /// Checks all orders of the active dataset that aren't cancelled for meals that don't keep to
+    /// the restrictions of the customer.
+    ///
+    /// # Success variants
+    /// - [`OrderCmdSuccess::CheckedOrders`]
+    ///
+    /// # Error variants
+    /// - [`OrderCmdError::DatasetNotActive`]: if there is no active dataset
+    /// - [`OrderCmdError::StoreError`]: if the customers or diets can't be read
+    CheckOrders,
+    /// Returns the orders of the active dataset, including cancelled ones.
+    ///
+    /// # Success variants
+    /// - [`OrderCmdSuccess::GotOrders`]
+    ///
+    /// # Error variants
+    /// - [`OrderCmdError::DatasetNotActive`]: if there is no active dataset
+    GetOrders {
+        /// Only return the orders for this day.
+        #[serde(default)]
+        date: Option<NaiveDate>,
+        /// Only return the orders of this customer.
+        #[serde(default)]
+        customer: Option<Uuid>,
+    },
+    /// Changes the ordered meal and quantity of an order.
+    ///
+    /// The order is checked again, just like a new one. Afterwards it counts as entered by hand, so
+    /// it isn't changed when orders are generated from standing orders again.
+    ///
+    /// Intolerances and diets of the customer the meal doesn't keep to are returned as warnings.
+    ///
+    /// # Success variants
+    /// - [`OrderCmdSuccess::UpdatedOrder`]
+    ///
+    /// # Error variants
+    /// - [`OrderCmdError::Allergy`]: if the meal contains an allergen the customer is allergic to
+    /// - [`OrderCmdError::CustomerInactive`]: if meals aren't delivered to the customer on the day
+    /// - [`OrderCmdError::CustomerNotFound`]: if the customer doesn't exist
+    /// - [`OrderCmdError::DatasetNotActive`]: if there is no active dataset
+    /// - [`OrderCmdError::InvalidQuantity`]: if the quantity is 0
+    /// - [`OrderCmdError::NoDelivery`]: if meals aren't delivered on the day
+    /// - [`OrderCmdError::NotFound`]: if the order doesn't exist or is cancelled
+    /// - [`OrderCmdError::NotOnMenu`]: if the meal isn't offered on the day
+    /// - [`OrderCmdError::SelectionError`]: if the meal, variants or options don't exist or don't
+    ///   fit together
+    /// - [`OrderCmdError::StoreError`]: if the customers, diets or delivery calendar can't be
+    ///   read
+    UpdateOrder {
+        order: Uuid,
+        selection: Selection,
+        quantity: u32,
+    },
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(tag = "variant", rename_all = "camelCase")]
+pub enum OrderCmdSuccess {
+    AddedOrder {
+        uuid: Uuid,
+        warnings: Vec<Conflict>,
+    },
+    CancelledOrder,
+    CheckedOrders {
+        /// The conflicts that prevent orders from being delivered, by order.
+        errors: BTreeMap<Uuid, Vec<Conflict>>,
+        /// The remaining conflicts, by order.
+        warnings: BTreeMap<Uuid, Vec<Conflict>>,
+    },
+    GotOrders {
+        data: BTreeMap<Uuid, Order>,
+    },
+    UpdatedOrder {
+        warnings: Vec<Conflict>,
+    },
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum OrderCmdError {
+    #[error("error.orders.allergy")]
+    Allergy { customer: Uuid, allergen: Allergen },
+    #[error("error.orders.customer_inactive")]
+    CustomerInactive { customer: Uuid, date: NaiveDate },
+    #[error("error.orders.customer_not_found")]
+    CustomerNotFound { customer: Uuid },
+    #[error("error.global.dataset_not_active")]
+    DatasetNotActive,
+    #[error("error.orders.date_outside_month")]
+    DateOutsideMonth { date: NaiveDate },
+    #[error("error.orders.invalid_quantity")]
+    InvalidQuantity,
+    #[error("error.orders.no_delivery")]
+    NoDelivery { date: NaiveDate },
+    #[error("error.orders.not_found")]
+    NotFound { order: Uuid },
+    #[error("error.orders.not_on_menu")]
+    NotOnMenu { date: NaiveDate, meal: Uuid },
+    #[error(transparent)]
+    SelectionError(#[from] SelectionError),
+    #[error(transparent)]
+    StoreError(#[from] StoreError),
+}
+
+impl CmdError for OrderCmdError {
+    fn payload(&self) -> ErrorPayload {
+        match self {
+            Self::Allergy { customer, allergen } => {
+                ErrorPayload::new("ALLERGY", "error.orders.allergy")
+                    .param("customer", customer)
+                    .param("allergen", *allergen)
+            }
+            Self::CustomerInactive { customer, date } => {
+                ErrorPayload::new("CUSTOMER_INACTIVE", "error.orders.customer_inactive")
+                    .param("customer", customer)
+                    .param("date", date)
+            }
+            Self::CustomerNotFound { customer } => {
+                ErrorPayload::new("CUSTOMER_NOT_FOUND", "error.orders.customer_not_found")
+                    .param("customer", customer)
+            }
+            Self::DatasetNotActive => {
+                ErrorPayload::new("DATASET_NOT_ACTIVE", "error.global.dataset_not_active")
+            }
+            Self::DateOutsideMonth { date } => {
+                ErrorPayload::new("DATE_OUTSIDE_MONTH", "error.orders.date_outside_month")
+                    .param("date", date)
+            }
+            Self::InvalidQuantity => {
+                ErrorPayload::new("INVALID_QUANTITY", "error.orders.invalid_quantity")
+            }
+            Self::NoDelivery { date } => {
+                ErrorPayload::new("NO_DELIVERY", "error.orders.no_delivery").param("date", date)
+            }
+            Self::NotFound { order } => {
+                ErrorPayload::new("ORDER_NOT_FOUND", "error.orders.not_found").param("order", order)
+            }
+            Self::NotOnMenu { date, meal } => {
+                ErrorPayload::new("NOT_ON_MENU", "error.orders.not_on_menu")
+                    .param("date", date)
+                    .param("meal", meal)
+            }
+            Self::SelectionError(e) => e.payload(),
+            Self::StoreError(e) => e.payload(),
+        }
+    }
+}
+
+impl super::CmdAble for OrderCmd {
+    type Error = OrderCmdError;
+    type Success = OrderCmdSuccess;
+
+    fn execute(
+        self: Self,
+        ctx: &Context,
+        events: &mut Vec<Event>,
+    ) -> Result<Self::Success, Self::Error> {
+        match self {
+            Self::AddOrder {
+                customer,
+                date,
+                selection,
+                quantity,
+            } => {
+                let order = Order::new(customer, date, selection, quantity);
+                let cal = ctx.calendar.read()?;
+                let customers = ctx.customers.read()?;
+                let diets = ctx.diets.read()?;
+
+                if let Some((data, _)) =
+                    &mut *ctx.data.write().expect("failed to get data write access")
+                {
+                    let warnings = check_order(data, &cal, &customers, &diets, &order)?;
+
+                    let uuid = Uuid::new_v4();
+                    data.orders.insert(uuid, order);
+                    events.push(Event::Added {
+                        entity: Entity::Order { uuid },
+                    });
+                    log::debug!("added order {} of customer {} for {}", uuid, customer, date);
+
+                    Ok(Self::Success::AddedOrder { uuid, warnings })
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+            Self::CancelOrder { order } => {
+                if let Some((data, _)) =
+                    &mut *ctx.data.write().expect("failed to get data write access")
+                {
+                    data.orders
+                        .get_mut(&order)
+                        .filter(|o| !o.cancelled)
+                        .ok_or(Self::Error::NotFound { order })?
+                        .cancelled = true;
+                    events.push(Event::Deleted {
+                        entity: Entity::Order { uuid: order },
+                    });
+                    log::debug!("cancelled order {}", order);
+
+                    Ok(Self::Success::CancelledOrder)
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+            Self::CheckOrders => {
+                let customers = ctx.customers.read()?;
+                let diets = ctx.diets.read()?;
+
+                if let Some((data, _)) = &*ctx.data.read().expect("failed to get data read access")
+                {
+                    let mut errors = BTreeMap::new();
+                    let mut warnings = BTreeMap::new();
+
+                    for (uuid, order) in data.orders.iter().filter(|(_, o)| !o.cancelled) {
+                        // orders with missing customers or meals are reported when changed
+                        let conflicts = match customers
+                            .get(&order.customer)
+                            .map(|c| Conflict::find(c, &order.selection, data, &diets))
+                        {
+                            Some(Ok(x)) => x,
+                            _ => continue,
+                        };
+
+                        let (blocking, other): (Vec<_>, Vec<_>) =
+                            conflicts.into_iter().partition(Conflict::is_blocking);
+                        if !blocking.is_empty() {
+                            errors.insert(*uuid, blocking);
+                        }
+                        if !other.is_empty() {
+                            warnings.insert(*uuid, other);
+                        }
+                    }
+
+                    Ok(Self::Success::CheckedOrders { errors, warnings })
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+            Self::GetOrders { date, customer } => {
+                if let Some((data, _)) = &*ctx.data.read().expect("failed to get data read access")
+                {
+                    Ok(Self::Success::GotOrders {
+                        data: data
+                            .orders
+                            .iter()
+                            .filter(|(_, o)| date.map_or(true, |d| o.date == d))
+                            .filter(|(_, o)| customer.map_or(true, |c| o.customer == c))
+                            .map(|(k, o)| (*k, o.clone()))
+                            .collect(),
+                    })
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+            Self::UpdateOrder {
+                order,
+                selection,
+                quantity,
+            } => {
+                let cal = ctx.calendar.read()?;
+                let customers = ctx.customers.read()?;
+                let diets = ctx.diets.read()?;
+
+                if let Some((data, _)) =
+                    &mut *ctx.data.write().expect("failed to get data write access")
+                {
+                    let old = data
+                        .orders
+                        .get(&order)
+                        .filter(|o| !o.cancelled)
+                        .ok_or(Self::Error::NotFound { order })?;
+                    let new = Order::new(old.customer, old.date, selection, quantity);
+                    let warnings = check_order(data, &cal, &customers, &diets, &new)?;
+
+                    data.orders.insert(order, new);
+                    events.push(Event::Updated {
+                        entity: Entity::Order { uuid: order },
+                    });
+
+                    Ok(Self::Success::UpdatedOrder { warnings })
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+        }
+    }
+}
+
+/// Check that `order` can be delivered.
+///
+/// The day has to be a delivery day within the month of `data`, the customer has to be active on
+/// it, the meal has to be on its menu and the selected variants and options have to fit the meal.
+/// The meal mustn't contain allergens the customer is allergic to.
+///
+/// Returns the conflicts with the restrictions of the customer that are only warnings.
+pub fn check_order(
+    data: &Data,
+    cal: &DeliveryCalendar,
+    customers: &BTreeMap<Uuid, Customer>,
+    diets: &BTreeMap<Uuid, Diet>,
+    order: &Order,
+) -> Result<Vec<Conflict>, OrderCmdError> {
+    if order.quantity == 0 {
+        return Err(OrderCmdError::InvalidQuantity);
+    }
+
+    let date = order.date;
+    if !data.contains(date) {
+        return Err(OrderCmdError::DateOutsideMonth { date });
+    }
+    if !cal.is_delivery_day(date) {
+        return Err(OrderCmdError::NoDelivery { date });
+    }
+
+    let customer = order.customer;
+    let c = match customers.get(&customer) {
+        Some(c) if c.is_active(date) => c,
+        Some(_) => return Err(OrderCmdError::CustomerInactive { customer, date }),
+        None => return Err(OrderCmdError::CustomerNotFound { customer }),
+    };
+
+    order.selection.resolve(data)?;
+    let meal = order.selection.meal;
+    if !data.menu.get(&date).map_or(false, |m| m.contains(&meal)) {
+        return Err(OrderCmdError::NotOnMenu { date, meal });
+    }
+
+    let conflicts = Conflict::find(c, &order.selection, data, diets)?;
+    match conflicts.iter().find(|x| x.is_blocking()) {
+        Some(Conflict::Allergy { allergen }) => Err(OrderCmdError::Allergy {
+            customer,
+            allergen: *allergen,
+        }),
+        _ => Ok(conflicts),
+    }
+}
@@ -0,0 +1,174 @@
+use super::error::{CmdError, ErrorPayload};
+use crate::{
+    context::Context,
+    data::{
+        production::{ProductionSheet, SheetLabels},
+        Data,
+    },
+    event::Event,
+    store::{write_atomic, StoreError},
+};
+use chrono::NaiveDate;
+use std::path::PathBuf;
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(tag = "cmd", rename_all = "camelCase")]
+pub enum ProductionCmd {
+    /// Writes the production sheet of a day or range of days to a printable HTML file.
+    ///
+    /// # Success variants
+    /// - [`ProductionCmdSuccess::ExportedProductionSheet`]
+    ///
+    /// # Error variants
+    /// - [`ProductionCmdError::DatasetNotActive`]: if there is no active dataset
+    /// - [`ProductionCmdError::DateOutsideMonth`]: if one of the days isn't part of the datasets
+    ///   month
+    /// - [`ProductionCmdError::InvalidRange`]: if `to` is before `from`
+    /// - [`ProductionCmdError::IoError`]: if the file can't be written
+    /// - [`ProductionCmdError::StoreError`]: if the customers or diets can't be read
+    ExportProductionSheet {
+        from: NaiveDate,
+        /// The last day to include. Defaults to `from`.
+        #[serde(default)]
+        to: Option<NaiveDate>,
+        labels: SheetLabels,
+        path: PathBuf,
+    },
+    /// Returns how many portions of every variant and option have to be prepared for a day or
+    /// range of days.
+    ///
+    /// # Success variants
+    /// - [`ProductionCmdSuccess::GotProductionSheet`]
+    ///
+    /// # Error variants
+    /// - [`ProductionCmdError::DatasetNotActive`]: if there is no active dataset
+    /// - [`ProductionCmdError::DateOutsideMonth`]: if one of the days isn't part of the datasets
+    ///   month
+    /// - [`ProductionCmdError::InvalidRange`]: if `to` is before `from`
+    /// - [`ProductionCmdError::StoreError`]: if the customers or diets can't be read
+    GetProductionSheet {
+        from: NaiveDate,
+        /// The last day to include. Defaults to `from`.
+        #[serde(default)]
+        to: Option<NaiveDate>,
+    },
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(tag = "variant", rename_all = "camelCase")]
+pub enum ProductionCmdSuccess {
+    ExportedProductionSheet,
+    GotProductionSheet { sheet: ProductionSheet },
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ProductionCmdError {
+    #[error("error.global.dataset_not_active")]
+    DatasetNotActive,
+    #[error("error.production.date_outside_month")]
+    DateOutsideMonth { date: NaiveDate },
+    #[error("error.production.invalid_range")]
+    InvalidRange { from: NaiveDate, to: NaiveDate },
+    #[error("error.global.io: {source}")]
+    IoError {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error(transparent)]
+    StoreError(#[from] StoreError),
+}
+
+impl CmdError for ProductionCmdError {
+    fn payload(&self) -> ErrorPayload {
+        match self {
+            Self::DatasetNotActive => {
+                ErrorPayload::new("DATASET_NOT_ACTIVE", "error.global.dataset_not_active")
+            }
+            Self::DateOutsideMonth { date } => {
+                ErrorPayload::new("DATE_OUTSIDE_MONTH", "error.production.date_outside_month")
+                    .param("date", date)
+            }
+            Self::InvalidRange { from, to } => {
+                ErrorPayload::new("INVALID_RANGE", "error.production.invalid_range")
+                    .param("from", from)
+                    .param("to", to)
+            }
+            Self::IoError { path, source } => ErrorPayload::new("IO", "error.global.io")
+                .detail(source)
+                .param("path", path),
+            Self::StoreError(e) => e.payload(),
+        }
+    }
+}
+
+impl super::CmdAble for ProductionCmd {
+    type Error = ProductionCmdError;
+    type Success = ProductionCmdSuccess;
+
+    fn execute(
+        self: Self,
+        ctx: &Context,
+        _events: &mut Vec<Event>,
+    ) -> Result<Self::Success, Self::Error> {
+        match self {
+            Self::ExportProductionSheet {
+                from,
+                to,
+                labels,
+                path,
+            } => {
+                let customers = ctx.customers.read()?;
+                let diets = ctx.diets.read()?;
+
+                if let Some((data, _)) = &*ctx.data.read().expect("failed to get data read access")
+                {
+                    let to = check_range(data, from, to)?;
+                    let sheet = ProductionSheet::new(data, &customers, &diets, from, to);
+
+                    write_atomic(&path, sheet.to_html(data, &diets, &labels).as_bytes())
+                        .map_err(|source| Self::Error::IoError { path, source })?;
+                    log::debug!("exported production sheet from {} to {}", from, to);
+
+                    Ok(Self::Success::ExportedProductionSheet)
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+            Self::GetProductionSheet { from, to } => {
+                let customers = ctx.customers.read()?;
+                let diets = ctx.diets.read()?;
+
+                if let Some((data, _)) = &*ctx.data.read().expect("failed to get data read access")
+                {
+                    let to = check_range(data, from, to)?;
+
+                    Ok(Self::Success::GotProductionSheet {
+                        sheet: ProductionSheet::new(data, &customers, &diets, from, to),
+                    })
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+        }
+    }
+}
+
+/// Check that the days from `from` to `to` are part of the month of `data`, and get the last day.
+fn check_range(
+    data: &Data,
+    from: NaiveDate,
+    to: Option<NaiveDate>,
+) -> Result<NaiveDate, ProductionCmdError> {
+    let to = to.unwrap_or(from);
+    if to < from {
+        return Err(ProductionCmdError::InvalidRange { from, to });
+    }
+
+    for date in [from, to].iter() {
+        if !data.contains(*date) {
+            return Err(ProductionCmdError::DateOutsideMonth { date: *date });
+        }
+    }
+
+    Ok(to)
+}
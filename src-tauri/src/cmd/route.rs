@@ -0,0 +1,555 @@
+use super::error::{CmdError, ErrorPayload};
+use crate::{
+    context::Context,
+    data::{
+        geo::{Coordinates, DistanceTable},
+        order::{self, Movement},
+        route::{Route, Stop},
+        tour::{DistanceSource, Summary, Tour, TourError},
+    },
+    event::{Entity, Event},
+    store::StoreError,
+};
+use chrono::{NaiveDate, NaiveTime};
+use std::{collections::BTreeMap, fs, path::PathBuf};
+use uuid::Uuid;
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(tag = "cmd", rename_all = "camelCase")]
+pub enum RouteCmd {
+    /// Adds a new route without stops.
+    ///
+    /// # Success variants
+    /// - [`RouteCmdSuccess::AddedRoute`]
+    ///
+    /// # Error variants
+    /// - [`RouteCmdError::EmptyName`]: if the provided name is empty
+    /// - [`RouteCmdError::InvalidCoordinates`]: if the coordinates of the depot are out of range
+    /// - [`RouteCmdError::StoreError`]: if the routes can't be read or written
+    AddRoute {
+        name: String,
+        #[serde(default)]
+        driver: String,
+        #[serde(default)]
+        vehicle: String,
+        #[serde(default)]
+        depot: Option<Coordinates>,
+    },
+    /// Adds a customer to a route.
+    ///
+    /// If the customer is already part of a route, they are removed from it first.
+    ///
+    /// # Success variants
+    /// - [`RouteCmdSuccess::AssignedCustomer`]
+    ///
+    /// # Error variants
+    /// - [`RouteCmdError::CustomerNotFound`]: if the customer doesn't exist
+    /// - [`RouteCmdError::NotFound`]: if the route doesn't exist
+    /// - [`RouteCmdError::StoreError`]: if the customers or routes can't be read, or the routes
+    ///   can't be written
+    AssignCustomer {
+        customer: Uuid,
+        route: Uuid,
+        /// The position of the new stop. Defaults to the end of the route, as do positions past
+        /// it.
+        #[serde(default)]
+        position: Option<usize>,
+    },
+    /// Deletes a route. Its customers aren't part of any route afterwards.
+    ///
+    /// # Success variants
+    /// - [`RouteCmdSuccess::DeletedRoute`]
+    ///
+    /// # Error variants
+    /// - [`RouteCmdError::NotFound`]: if the route doesn't exist
+    /// - [`RouteCmdError::StoreError`]: if the routes can't be read or written
+    DeleteRoute { route: Uuid },
+    /// Returns the stops of a route meals are delivered to on a day, with their orders.
+    ///
+    /// # Success variants
+    /// - [`RouteCmdSuccess::GotRouteSheet`]
+    ///
+    /// # Error variants
+    /// - [`RouteCmdError::DatasetNotActive`]: if there is no active dataset
+    /// - [`RouteCmdError::DateOutsideMonth`]: if the day isn't part of the datasets month
+    /// - [`RouteCmdError::NotFound`]: if the route doesn't exist
+    /// - [`RouteCmdError::StoreError`]: if the customers or routes can't be read
+    GetRouteSheet { route: Uuid, date: NaiveDate },
+    /// Returns all routes.
+    ///
+    /// # Success variants
+    /// - [`RouteCmdSuccess::GotRoutes`]
+    ///
+    /// # Error variants
+    /// - [`RouteCmdError::StoreError`]: if the routes can't be read
+    GetRoutes,
+    /// Replaces the imported road distances with the ones in a file.
+    ///
+    /// See [`DistanceTable::parse`] for the format of the file.
+    ///
+    /// # Success variants
+    /// - [`RouteCmdSuccess::ImportedDistances`]
+    ///
+    /// # Error variants
+    /// - [`RouteCmdError::InvalidDistanceTable`]: if a line of the file can't be parsed
+    /// - [`RouteCmdError::IoError`]: if the file can't be read
+    /// - [`RouteCmdError::StoreError`]: if the distances can't be written
+    ImportDistances { path: PathBuf },
+    /// Changes the position of a stop within its route.
+    ///
+    /// # Success variants
+    /// - [`RouteCmdSuccess::MovedStop`]
+    ///
+    /// # Error variants
+    /// - [`RouteCmdError::NotFound`]: if the route doesn't exist
+    /// - [`RouteCmdError::NotOnRoute`]: if the customer isn't part of the route
+    /// - [`RouteCmdError::StoreError`]: if the routes can't be read or written
+    MoveStop {
+        route: Uuid,
+        customer: Uuid,
+        movement: Movement,
+    },
+    /// Proposes the order of the stops of a route that takes the least time, without being late
+    /// for the time windows of the customers.
+    ///
+    /// The order is only proposed, see [`RouteCmd::ReorderStops`] for accepting it.
+    ///
+    /// # Success variants
+    /// - [`RouteCmdSuccess::ProposedOrder`]
+    ///
+    /// # Error variants
+    /// - [`RouteCmdError::NotFound`]: if the route doesn't exist
+    /// - [`RouteCmdError::StoreError`]: if the customers, distances or routes can't be read
+    /// - [`RouteCmdError::TourError`]: if the distances between the stops aren't known or the
+    ///   route takes too long or would end after midnight
+    OptimizeRoute {
+        route: Uuid,
+        #[serde(flatten)]
+        distances: DistanceSource,
+        /// The time the driver leaves the depot.
+        start: NaiveTime,
+        /// The minutes spent at every stop.
+        #[serde(default)]
+        service: u32,
+    },
+    /// Replaces the order of the stops of a route, e.g. with a proposed one.
+    ///
+    /// # Success variants
+    /// - [`RouteCmdSuccess::ReorderedStops`]
+    ///
+    /// # Error variants
+    /// - [`RouteCmdError::NotFound`]: if the route doesn't exist
+    /// - [`RouteCmdError::StopsChanged`]: if the stops aren't the ones of the route
+    /// - [`RouteCmdError::StoreError`]: if the routes can't be read or written
+    ReorderStops { route: Uuid, stops: Vec<Uuid> },
+    /// Removes a customer from their route.
+    ///
+    /// # Success variants
+    /// - [`RouteCmdSuccess::UnassignedCustomer`]
+    ///
+    /// # Error variants
+    /// - [`RouteCmdError::NotAssigned`]: if the customer isn't part of any route
+    /// - [`RouteCmdError::StoreError`]: if the routes can't be read or written
+    UnassignCustomer { customer: Uuid },
+    /// Changes the name, driver, vehicle and depot of a route.
+    ///
+    /// # Success variants
+    /// - [`RouteCmdSuccess::UpdatedRoute`]
+    ///
+    /// # Error variants
+    /// - [`RouteCmdError::EmptyName`]: if the provided name is empty
+    /// - [`RouteCmdError::InvalidCoordinates`]: if the coordinates of the depot are out of range
+    /// - [`RouteCmdError::NotFound`]: if the route doesn't exist
+    /// - [`RouteCmdError::StoreError`]: if the routes can't be read or written
+    UpdateRoute {
+        route: Uuid,
+        name: String,
+        #[serde(default)]
+        driver: String,
+        #[serde(default)]
+        vehicle: String,
+        #[serde(default)]
+        depot: Option<Coordinates>,
+    },
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(tag = "variant", rename_all = "camelCase")]
+pub enum RouteCmdSuccess {
+    AddedRoute {
+        uuid: Uuid,
+    },
+    AssignedCustomer,
+    DeletedRoute,
+    GotRouteSheet {
+        stops: Vec<Stop>,
+    },
+    GotRoutes {
+        data: BTreeMap<Uuid, Route>,
+    },
+    ImportedDistances {
+        legs: usize,
+    },
+    MovedStop,
+    #[serde(rename_all = "camelCase")]
+    ProposedOrder {
+        current: Summary,
+        proposed: Summary,
+        /// The metres saved by the proposed order.
+        saved_distance: f64,
+        /// The seconds saved by the proposed order.
+        saved_duration: i64,
+    },
+    ReorderedStops,
+    UnassignedCustomer,
+    UpdatedRoute,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RouteCmdError {
+    #[error("error.routes.customer_not_found")]
+    CustomerNotFound { customer: Uuid },
+    #[error("error.global.dataset_not_active")]
+    DatasetNotActive,
+    #[error("error.routes.date_outside_month")]
+    DateOutsideMonth { date: NaiveDate },
+    #[error("error.routes.name_empty")]
+    EmptyName,
+    #[error("error.routes.invalid_coordinates")]
+    InvalidCoordinates,
+    #[error("error.routes.invalid_distance_table")]
+    InvalidDistanceTable { path: PathBuf, line: usize },
+    #[error("error.global.io: {source}")]
+    IoError {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("error.routes.not_assigned")]
+    NotAssigned { customer: Uuid },
+    #[error("error.routes.not_found")]
+    NotFound { route: Uuid },
+    #[error("error.routes.not_on_route")]
+    NotOnRoute { route: Uuid, customer: Uuid },
+    #[error("error.routes.stops_changed")]
+    StopsChanged { route: Uuid },
+    #[error(transparent)]
+    StoreError(#[from] StoreError),
+    #[error(transparent)]
+    TourError(#[from] TourError),
+}
+
+impl CmdError for RouteCmdError {
+    fn payload(&self) -> ErrorPayload {
+        match self {
+            Self::CustomerNotFound { customer } => {
+                ErrorPayload::new("CUSTOMER_NOT_FOUND", "error.routes.customer_not_found")
+                    .param("customer", customer)
+            }
+            Self::DatasetNotActive => {
+                ErrorPayload::new("DATASET_NOT_ACTIVE", "error.global.dataset_not_active")
+            }
+            Self::DateOutsideMonth { date } => {
+                ErrorPayload::new("DATE_OUTSIDE_MONTH", "error.routes.date_outside_month")
+                    .param("date", date)
+            }
+            Self::EmptyName => ErrorPayload::new("NAME_EMPTY", "error.routes.name_empty"),
+            Self::InvalidCoordinates => {
+                ErrorPayload::new("INVALID_COORDINATES", "error.routes.invalid_coordinates")
+            }
+            Self::InvalidDistanceTable { path, line } => ErrorPayload::new(
+                "INVALID_DISTANCE_TABLE",
+                "error.routes.invalid_distance_table",
+            )
+            .param("path", path)
+            .param("line", *line),
+            Self::IoError { path, source } => ErrorPayload::new("IO", "error.global.io")
+                .detail(source)
+                .param("path", path),
+            Self::NotAssigned { customer } => {
+                ErrorPayload::new("NOT_ASSIGNED", "error.routes.not_assigned")
+                    .param("customer", customer)
+            }
+            Self::NotFound { route } => {
+                ErrorPayload::new("ROUTE_NOT_FOUND", "error.routes.not_found").param("route", route)
+            }
+            Self::NotOnRoute { route, customer } => {
+                ErrorPayload::new("NOT_ON_ROUTE", "error.routes.not_on_route")
+                    .param("route", route)
+                    .param("customer", customer)
+            }
+            Self::StopsChanged { route } => {
+                ErrorPayload::new("STOPS_CHANGED", "error.routes.stops_changed")
+                    .param("route", route)
+            }
+            Self::StoreError(e) => e.payload(),
+            Self::TourError(e) => e.payload(),
+        }
+    }
+}
+
+impl super::CmdAble for RouteCmd {
+    type Error = RouteCmdError;
+    type Success = RouteCmdSuccess;
+
+    fn execute(
+        self: Self,
+        ctx: &Context,
+        events: &mut Vec<Event>,
+    ) -> Result<Self::Success, Self::Error> {
+        match self {
+            Self::AddRoute {
+                name,
+                driver,
+                vehicle,
+                depot,
+            } => {
+                let route = Route {
+                    name: check_name(&name)?,
+                    driver: driver.trim().to_string(),
+                    vehicle: vehicle.trim().to_string(),
+                    depot: check_depot(depot)?,
+                    stops: Vec::new(),
+                };
+
+                let uuid = Uuid::new_v4();
+                ctx.routes.update(|routes| {
+                    routes.insert(uuid, route);
+                    Ok::<_, Self::Error>(())
+                })?;
+                events.push(Event::Added {
+                    entity: Entity::Route { uuid },
+                });
+                log::info!("added route {}", uuid);
+
+                Ok(Self::Success::AddedRoute { uuid })
+            }
+            Self::AssignCustomer {
+                customer,
+                route,
+                position,
+            } => {
+                if !ctx.customers.read()?.contains_key(&customer) {
+                    return Err(Self::Error::CustomerNotFound { customer });
+                }
+
+                let previous = ctx.routes.update(|routes| {
+                    if !routes.contains_key(&route) {
+                        return Err(Self::Error::NotFound { route });
+                    }
+
+                    let previous = remove_stop(routes, customer);
+                    let stops = &mut routes.get_mut(&route).expect("route vanished").stops;
+                    let position = position.unwrap_or(stops.len()).min(stops.len());
+                    stops.insert(position, customer);
+
+                    Ok(previous)
+                })?;
+                if let Some(uuid) = previous.filter(|p| *p != route) {
+                    events.push(Event::Updated {
+                        entity: Entity::Route { uuid },
+                    });
+                }
+                events.push(Event::Updated {
+                    entity: Entity::Route { uuid: route },
+                });
+                log::info!("assigned customer {} to route {}", customer, route);
+
+                Ok(Self::Success::AssignedCustomer)
+            }
+            Self::DeleteRoute { route } => {
+                ctx.routes.update(|routes| {
+                    routes
+                        .remove(&route)
+                        .map(|_| ())
+                        .ok_or(Self::Error::NotFound { route })
+                })?;
+                events.push(Event::Deleted {
+                    entity: Entity::Route { uuid: route },
+                });
+                log::info!("deleted route {}", route);
+
+                Ok(Self::Success::DeletedRoute)
+            }
+            Self::GetRouteSheet { route, date } => {
+                let customers = ctx.customers.read()?;
+                let routes = ctx.routes.read()?;
+                let r = routes.get(&route).ok_or(Self::Error::NotFound { route })?;
+
+                if let Some((data, _)) = &*ctx.data.read().expect("failed to get data read access")
+                {
+                    if !data.contains(date) {
+                        return Err(Self::Error::DateOutsideMonth { date });
+                    }
+
+                    Ok(Self::Success::GotRouteSheet {
+                        stops: r.sheet(data, &customers, date),
+                    })
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+            Self::GetRoutes => Ok(Self::Success::GotRoutes {
+                data: ctx.routes.read()?,
+            }),
+            Self::ImportDistances { path } => {
+                let text = fs::read_to_string(&path).map_err(|source| Self::Error::IoError {
+                    path: path.clone(),
+                    source,
+                })?;
+                let table = DistanceTable::parse(&text)
+                    .map_err(|line| Self::Error::InvalidDistanceTable { path, line })?;
+
+                let legs = table.legs.values().map(BTreeMap::len).sum();
+                ctx.distances.update(|distances| {
+                    *distances = table;
+                    Ok::<_, Self::Error>(())
+                })?;
+                log::info!("imported {} road distances", legs);
+
+                Ok(Self::Success::ImportedDistances { legs })
+            }
+            Self::MoveStop {
+                route,
+                customer,
+                movement,
+            } => {
+                ctx.routes.update(|routes| {
+                    let r = routes
+                        .get_mut(&route)
+                        .ok_or(Self::Error::NotFound { route })?;
+
+                    if order::relocate(&mut r.stops, customer, movement) {
+                        Ok(())
+                    } else {
+                        Err(Self::Error::NotOnRoute { route, customer })
+                    }
+                })?;
+                events.push(Event::Updated {
+                    entity: Entity::Route { uuid: route },
+                });
+                log::info!("moved customer {} within route {}", customer, route);
+
+                Ok(Self::Success::MovedStop)
+            }
+            Self::OptimizeRoute {
+                route,
+                distances,
+                start,
+                service,
+            } => {
+                let routes = ctx.routes.read()?;
+                let r = routes.get(&route).ok_or(Self::Error::NotFound { route })?;
+                let tour = Tour::new(
+                    r,
+                    &ctx.customers.read()?,
+                    distances,
+                    &ctx.distances.read()?,
+                    start,
+                    f64::from(service) * 60.0,
+                )?;
+
+                let current: Vec<_> = (0..r.stops.len()).collect();
+                let current = tour.summary(&current)?;
+                let proposed = tour.summary(&tour.optimize())?;
+
+                Ok(Self::Success::ProposedOrder {
+                    saved_distance: current.distance - proposed.distance,
+                    saved_duration: current.duration - proposed.duration,
+                    current,
+                    proposed,
+                })
+            }
+            Self::ReorderStops { route, stops } => {
+                ctx.routes.update(|routes| {
+                    let r = routes
+                        .get_mut(&route)
+                        .ok_or(Self::Error::NotFound { route })?;
+
+                    let mut old = r.stops.clone();
+                    let mut new = stops.clone();
+                    old.sort();
+                    new.sort();
+                    if old != new {
+                        return Err(Self::Error::StopsChanged { route });
+                    }
+
+                    r.stops = stops;
+                    Ok(())
+                })?;
+                events.push(Event::Updated {
+                    entity: Entity::Route { uuid: route },
+                });
+                log::info!("reordered the stops of route {}", route);
+
+                Ok(Self::Success::ReorderedStops)
+            }
+            Self::UnassignCustomer { customer } => {
+                let route = ctx.routes.update(|routes| {
+                    remove_stop(routes, customer).ok_or(Self::Error::NotAssigned { customer })
+                })?;
+                events.push(Event::Updated {
+                    entity: Entity::Route { uuid: route },
+                });
+                log::info!("removed customer {} from route {}", customer, route);
+
+                Ok(Self::Success::UnassignedCustomer)
+            }
+            Self::UpdateRoute {
+                route,
+                name,
+                driver,
+                vehicle,
+                depot,
+            } => {
+                let name = check_name(&name)?;
+                let depot = check_depot(depot)?;
+
+                ctx.routes.update(|routes| {
+                    let r = routes
+                        .get_mut(&route)
+                        .ok_or(Self::Error::NotFound { route })?;
+                    r.name = name;
+                    r.driver = driver.trim().to_string();
+                    r.vehicle = vehicle.trim().to_string();
+                    r.depot = depot;
+
+                    Ok::<_, Self::Error>(())
+                })?;
+                events.push(Event::Updated {
+                    entity: Entity::Route { uuid: route },
+                });
+
+                Ok(Self::Success::UpdatedRoute)
+            }
+        }
+    }
+}
+
+fn check_name(name: &str) -> Result<String, RouteCmdError> {
+    let name = name.trim();
+    if name.is_empty() {
+        Err(RouteCmdError::EmptyName)
+    } else {
+        Ok(name.to_string())
+    }
+}
+
+fn check_depot(depot: Option<Coordinates>) -> Result<Option<Coordinates>, RouteCmdError> {
+    match depot {
+        Some(c) if !c.is_valid() => Err(RouteCmdError::InvalidCoordinates),
+        _ => Ok(depot),
+    }
+}
+
+/// Remove `customer` from the route they are part of, and get that route.
+fn remove_stop(routes: &mut BTreeMap<Uuid, Route>, customer: Uuid) -> Option<Uuid> {
+    let mut found = None;
+    for (uuid, route) in routes.iter_mut() {
+        if route.stops.contains(&customer) {
+            route.stops.retain(|c| *c != customer);
+            found = Some(*uuid);
+        }
+    }
+
+    found
+}
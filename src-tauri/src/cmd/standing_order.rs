@@ -0,0 +1,670 @@
+use super::{
+    error::{CmdError, ErrorPayload},
+    order::check_order,
+};
+use crate::{
+    context::Context,
+    data::{
+        calendar::DeliveryCalendar,
+        customer::Customer,
+        customer_order::{Order, OrderSource},
+        diet::Diet,
+        standing_order::StandingOrder,
+        Data,
+    },
+    event::{Entity, Event},
+    store::StoreError,
+};
+use chrono::NaiveDate;
+use std::collections::{BTreeMap, BTreeSet};
+use uuid::Uuid;
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(tag = "cmd", rename_all = "camelCase")]
+pub enum StandingOrderCmd {
+    /// Adds a new standing order.
+    ///
+    /// # Success variants
+    /// - [`StandingOrderCmdSuccess::AddedStandingOrder`]
+    ///
+    /// # Error variants
+    /// - [`StandingOrderCmdError::CustomerNotFound`]: if the customer doesn't exist
+    /// - [`StandingOrderCmdError::InvalidQuantity`]: if the quantity is 0
+    /// - [`StandingOrderCmdError::InvalidRange`]: if the standing order ends before it starts
+    /// - [`StandingOrderCmdError::NoWeekdays`]: if no weekdays are provided
+    /// - [`StandingOrderCmdError::StoreError`]: if the customers or standing orders can't be read
+    ///   or written
+    AddStandingOrder { standing_order: StandingOrder },
+    /// Deletes a standing order.
+    ///
+    /// Orders generated from it are kept until orders are generated again.
+    ///
+    /// # Success variants
+    /// - [`StandingOrderCmdSuccess::DeletedStandingOrder`]
+    ///
+    /// # Error variants
+    /// - [`StandingOrderCmdError::NotFound`]: if the standing order doesn't exist
+    /// - [`StandingOrderCmdError::StoreError`]: if the standing orders can't be read or written
+    DeleteStandingOrder { standing_order: Uuid },
+    /// Generates the orders of the active dataset from the standing orders.
+    ///
+    /// For every delivery day of the month, each standing order that applies to it results in an
+    /// order, unless the customer isn't active on that day or has an order entered by hand for it.
+    /// Orders generated before are updated, or removed if their standing order no longer applies
+    /// or is skipped. Generated orders that were cancelled are kept as they are.
+    ///
+    /// # Success variants
+    /// - [`StandingOrderCmdSuccess::GeneratedOrders`]
+    ///
+    /// # Error variants
+    /// - [`StandingOrderCmdError::DatasetNotActive`]: if there is no active dataset
+    /// - [`StandingOrderCmdError::StoreError`]: if the customers, diets, standing orders or
+    ///   delivery calendar can't be read
+    GenerateOrders,
+    /// Returns all standing orders.
+    ///
+    /// # Success variants
+    /// - [`StandingOrderCmdSuccess::GotStandingOrders`]
+    ///
+    /// # Error variants
+    /// - [`StandingOrderCmdError::StoreError`]: if the standing orders can't be read
+    GetStandingOrders {
+        /// Only return the standing orders of this customer.
+        #[serde(default)]
+        customer: Option<Uuid>,
+    },
+    /// Replaces a standing order.
+    ///
+    /// # Success variants
+    /// - [`StandingOrderCmdSuccess::UpdatedStandingOrder`]
+    ///
+    /// # Error variants
+    /// - [`StandingOrderCmdError::CustomerNotFound`]: if the customer doesn't exist
+    /// - [`StandingOrderCmdError::InvalidQuantity`]: if the quantity is 0
+    /// - [`StandingOrderCmdError::InvalidRange`]: if the standing order ends before it starts
+    /// - [`StandingOrderCmdError::NoWeekdays`]: if no weekdays are provided
+    /// - [`StandingOrderCmdError::NotFound`]: if the standing order doesn't exist
+    /// - [`StandingOrderCmdError::StoreError`]: if the customers or standing orders can't be read
+    ///   or written
+    UpdateStandingOrder {
+        standing_order: Uuid,
+        data: StandingOrder,
+    },
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(tag = "variant", rename_all = "camelCase")]
+pub enum StandingOrderCmdSuccess {
+    AddedStandingOrder {
+        uuid: Uuid,
+    },
+    DeletedStandingOrder,
+    GeneratedOrders {
+        /// The number of orders that were added.
+        added: usize,
+        /// The number of generated orders that were changed.
+        updated: usize,
+        /// The number of generated orders that were removed.
+        removed: usize,
+        /// The orders that couldn't be generated.
+        skipped: Vec<SkippedOrder>,
+    },
+    GotStandingOrders {
+        data: BTreeMap<Uuid, StandingOrder>,
+    },
+    UpdatedStandingOrder,
+}
+
+/// An order that couldn't be generated from a standing order.
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedOrder {
+    pub standing_order: Uuid,
+    pub date: NaiveDate,
+    pub reason: ErrorPayload,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum StandingOrderCmdError {
+    #[error("error.standing_orders.customer_not_found")]
+    CustomerNotFound { customer: Uuid },
+    #[error("error.global.dataset_not_active")]
+    DatasetNotActive,
+    #[error("error.standing_orders.invalid_quantity")]
+    InvalidQuantity,
+    #[error("error.standing_orders.invalid_range")]
+    InvalidRange { from: NaiveDate, to: NaiveDate },
+    #[error("error.standing_orders.no_weekdays")]
+    NoWeekdays,
+    #[error("error.standing_orders.not_found")]
+    NotFound { standing_order: Uuid },
+    #[error(transparent)]
+    StoreError(#[from] StoreError),
+}
+
+impl CmdError for StandingOrderCmdError {
+    fn payload(&self) -> ErrorPayload {
+        match self {
+            Self::CustomerNotFound { customer } => ErrorPayload::new(
+                "CUSTOMER_NOT_FOUND",
+                "error.standing_orders.customer_not_found",
+            )
+            .param("customer", customer),
+            Self::DatasetNotActive => {
+                ErrorPayload::new("DATASET_NOT_ACTIVE", "error.global.dataset_not_active")
+            }
+            Self::InvalidQuantity => {
+                ErrorPayload::new("INVALID_QUANTITY", "error.standing_orders.invalid_quantity")
+            }
+            Self::InvalidRange { from, to } => {
+                ErrorPayload::new("INVALID_RANGE", "error.standing_orders.invalid_range")
+                    .param("from", from)
+                    .param("to", to)
+            }
+            Self::NoWeekdays => {
+                ErrorPayload::new("NO_WEEKDAYS", "error.standing_orders.no_weekdays")
+            }
+            Self::NotFound { standing_order } => ErrorPayload::new(
+                "STANDING_ORDER_NOT_FOUND",
+                "error.standing_orders.not_found",
+            )
+            .param("standing_order", standing_order),
+            Self::StoreError(e) => e.payload(),
+        }
+    }
+}
+
+impl super::CmdAble for StandingOrderCmd {
+    type Error = StandingOrderCmdError;
+    type Success = StandingOrderCmdSuccess;
+
+    fn execute(
+        self: Self,
+        ctx: &Context,
+        events: &mut Vec<Event>,
+    ) -> Result<Self::Success, Self::Error> {
+        match self {
+            Self::AddStandingOrder { standing_order } => {
+                check_standing_order(ctx, &standing_order)?;
+
+                let uuid = Uuid::new_v4();
+                ctx.standing_orders.update(|standing_orders| {
+                    standing_orders.insert(uuid, standing_order);
+                    Ok::<_, Self::Error>(())
+                })?;
+                events.push(Event::Added {
+                    entity: Entity::StandingOrder { uuid },
+                });
+                log::info!("added standing order {}", uuid);
+
+                Ok(Self::Success::AddedStandingOrder { uuid })
+            }
+            Self::DeleteStandingOrder { standing_order } => {
+                ctx.standing_orders.update(|standing_orders| {
+                    standing_orders
+                        .remove(&standing_order)
+                        .ok_or(Self::Error::NotFound { standing_order })
+                })?;
+                events.push(Event::Deleted {
+                    entity: Entity::StandingOrder {
+                        uuid: standing_order,
+                    },
+                });
+                log::info!("deleted standing order {}", standing_order);
+
+                Ok(Self::Success::DeletedStandingOrder)
+            }
+            Self::GenerateOrders => {
+                let cal = ctx.calendar.read()?;
+                let customers = ctx.customers.read()?;
+                let standing_orders = ctx.standing_orders.read()?;
+                let diets = ctx.diets.read()?;
+
+                if let Some((data, _)) =
+                    &mut *ctx.data.write().expect("failed to get data write access")
+                {
+                    let mut targets = BTreeSet::new();
+                    for date in data.days().filter(|d| cal.is_delivery_day(*d)) {
+                        for (k, s) in &standing_orders {
+                            let active = customers
+                                .get(&s.customer)
+                                .map_or(false, |c| c.is_active(date));
+                            if active && s.applies(date) {
+                                targets.insert((*k, date));
+                            }
+                        }
+                    }
+                    let generated: BTreeMap<(Uuid, NaiveDate), Uuid> = data
+                        .orders
+                        .iter()
+                        .filter_map(|(k, o)| match o.source {
+                            OrderSource::Standing(s) => Some(((s, o.date), *k)),
+                            OrderSource::Manual => None,
+                        })
+                        .collect();
+
+                    let (mut added, mut updated, mut removed) = (0, 0, 0);
+                    let mut skipped = Vec::new();
+
+                    for (target, k) in &generated {
+                        if !targets.contains(target) && !data.orders[k].cancelled {
+                            data.orders.remove(k);
+                            events.push(Event::Deleted {
+                                entity: Entity::Order { uuid: *k },
+                            });
+                            removed += 1;
+                        }
+                    }
+
+                    for (s, date) in targets {
+                        let existing = generated.get(&(s, date)).copied();
+                        if existing.map_or(false, |k| data.orders[&k].cancelled) {
+                            continue;
+                        }
+
+                        let standing = &standing_orders[&s];
+                        let order =
+                            match generate_order(data, &cal, &customers, &diets, s, standing, date)
+                            {
+                                Ok(x) => x,
+                                Err(reason) => {
+                                    skipped.push(SkippedOrder {
+                                        standing_order: s,
+                                        date,
+                                        reason,
+                                    });
+                                    // the customer mustn't keep an order that is no longer valid
+                                    if let Some(k) = existing {
+                                        data.orders.remove(&k);
+                                        events.push(Event::Deleted {
+                                            entity: Entity::Order { uuid: k },
+                                        });
+                                        removed += 1;
+                                    }
+                                    continue;
+                                }
+                            };
+
+                        match existing {
+                            Some(k) if data.orders[&k] == order => {}
+                            Some(k) => {
+                                data.orders.insert(k, order);
+                                events.push(Event::Updated {
+                                    entity: Entity::Order { uuid: k },
+                                });
+                                updated += 1;
+                            }
+                            None => {
+                                let uuid = Uuid::new_v4();
+                                data.orders.insert(uuid, order);
+                                events.push(Event::Added {
+                                    entity: Entity::Order { uuid },
+                                });
+                                added += 1;
+                            }
+                        }
+                    }
+
+                    log::info!(
+                        "generated orders: {} added, {} updated, {} removed, {} skipped",
+                        added,
+                        updated,
+                        removed,
+                        skipped.len()
+                    );
+
+                    Ok(Self::Success::GeneratedOrders {
+                        added,
+                        updated,
+                        removed,
+                        skipped,
+                    })
+                } else {
+                    Err(Self::Error::DatasetNotActive)
+                }
+            }
+            Self::GetStandingOrders { customer } => {
+                let mut data = ctx.standing_orders.read()?;
+                data.retain(|_, s| customer.map_or(true, |c| s.customer == c));
+
+                Ok(Self::Success::GotStandingOrders { data })
+            }
+            Self::UpdateStandingOrder {
+                standing_order,
+                data,
+            } => {
+                check_standing_order(ctx, &data)?;
+
+                ctx.standing_orders.update(|standing_orders| {
+                    match standing_orders.get_mut(&standing_order) {
+                        Some(x) => *x = data,
+                        None => return Err(Self::Error::NotFound { standing_order }),
+                    }
+
+                    Ok(())
+                })?;
+                events.push(Event::Updated {
+                    entity: Entity::StandingOrder {
+                        uuid: standing_order,
+                    },
+                });
+                log::info!("updated standing order {}", standing_order);
+
+                Ok(Self::Success::UpdatedStandingOrder)
+            }
+        }
+    }
+}
+
+/// Get the order `standing` results in on `date`.
+///
+/// Returns why no order can be generated on failure.
+fn generate_order(
+    data: &Data,
+    cal: &DeliveryCalendar,
+    customers: &BTreeMap<Uuid, Customer>,
+    diets: &BTreeMap<Uuid, Diet>,
+    uuid: Uuid,
+    standing: &StandingOrder,
+    date: NaiveDate,
+) -> Result<Order, ErrorPayload> {
+    if data.orders.values().any(|o| {
+        o.source == OrderSource::Manual
+            && !o.cancelled
+            && o.customer == standing.customer
+            && o.date == date
+    }) {
+        return Err(
+            ErrorPayload::new("MANUAL_ORDER", "error.standing_orders.manual_order")
+                .param("customer", standing.customer),
+        );
+    }
+
+    let selection = standing.selection(data, date).ok_or_else(|| {
+        ErrorPayload::new("NO_MEAL_ON_LINE", "error.standing_orders.no_meal_on_line")
+    })?;
+    let order = Order {
+        source: OrderSource::Standing(uuid),
+        ..Order::new(standing.customer, date, selection, standing.quantity)
+    };
+    check_order(data, cal, customers, diets, &order).map_err(|e| e.payload())?;
+
+    Ok(order)
+}
+
+/// Check that a standing order is valid.
+///
+/// The meal isn't checked, as it depends on the month the standing order is applied to.
+fn check_standing_order(
+    ctx: &Context,
+    standing_order: &StandingOrder,
+) -> Result<(), StandingOrderCmdError> {
+    if standing_order.weekdays.is_empty() {
+        return Err(StandingOrderCmdError::NoWeekdays);
+    }
+    if standing_order.quantity == 0 {
+        return Err(StandingOrderCmdError::InvalidQuantity);
+    }
+    if let (Some(from), Some(to)) = (standing_order.from, standing_order.to) {
+        if to < from {
+            return Err(StandingOrderCmdError::InvalidRange { from, to });
+        }
+    }
+
+    let customer = standing_order.customer;
+    if ctx.customers.read()?.contains_key(&customer) {
+        Ok(())
+    } else {
+        Err(StandingOrderCmdError::CustomerNotFound { customer })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cmd::{global::GlobalCmd, run},
+        data::{
+            component::{Component, Variant},
+            customer::Address,
+            meal::Meal,
+            standing_order::MealChoice,
+        },
+    };
+    use chrono::{Datelike, Local, TimeZone, Weekday};
+    use tempfile::TempDir;
+
+    /// The mondays of march 2021.
+    const MONDAYS: [u32; 5] = [1, 8, 15, 22, 29];
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd(2021, 3, day)
+    }
+
+    /// Create a context with an active dataset for march 2021, with the same meal on the menu
+    /// every day, and a customer with a standing order for mondays.
+    ///
+    /// Returns the context, the customer and the standing order.
+    fn context(dir: &TempDir) -> (Context, Uuid, Uuid) {
+        let ctx = Context::in_dir(dir.path(), Local.ymd(2021, 3, 1).and_hms(12, 0, 0));
+        run(
+            &ctx,
+            GlobalCmd::NewDataset {
+                next_month: false,
+                template: None,
+            },
+            &mut Vec::new(),
+        )
+        .unwrap();
+
+        let (component, variant, meal) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        if let Some((data, _)) = &mut *ctx.data.write().unwrap() {
+            let variants = vec![(variant, Variant::new("tomato".to_string()))];
+            data.components.insert(
+                component,
+                Component::new(
+                    "soup".to_string(),
+                    None,
+                    variants.into_iter().collect(),
+                    BTreeMap::new(),
+                ),
+            );
+            let parts = vec![(component, Some(variant))].into_iter().collect();
+            data.meals
+                .insert(meal, Meal::new("lunch".to_string(), String::new(), parts));
+            for day in data.days().collect::<Vec<_>>() {
+                data.menu.insert(day, vec![meal]);
+            }
+        }
+
+        let customer = Uuid::new_v4();
+        ctx.customers
+            .update(|customers| {
+                customers.insert(
+                    customer,
+                    Customer {
+                        number: 1,
+                        name: "Jane Doe".to_string(),
+                        address: Address::default(),
+                        phone: String::new(),
+                        notes: String::new(),
+                        start: None,
+                        end: None,
+                        diets: BTreeSet::new(),
+                        allergies: BTreeSet::new(),
+                        intolerances: BTreeSet::new(),
+                        coordinates: None,
+                        window: None,
+                    },
+                );
+                Ok::<_, StoreError>(())
+            })
+            .unwrap();
+
+        let standing_order = match execute(
+            &ctx,
+            StandingOrderCmd::AddStandingOrder {
+                standing_order: StandingOrder {
+                    customer,
+                    weekdays: vec![Weekday::Mon],
+                    choice: MealChoice::Line(0),
+                    variants: BTreeMap::new(),
+                    options: BTreeMap::new(),
+                    quantity: 1,
+                    from: None,
+                    to: None,
+                },
+            },
+        ) {
+            StandingOrderCmdSuccess::AddedStandingOrder { uuid } => uuid,
+            other => panic!("unexpected result {:?}", other),
+        };
+
+        (ctx, customer, standing_order)
+    }
+
+    fn execute(ctx: &Context, cmd: StandingOrderCmd) -> StandingOrderCmdSuccess {
+        run(ctx, cmd, &mut Vec::new()).expect("command failed")
+    }
+
+    /// Generate the orders and return the numbers of added, updated and removed orders, and the
+    /// codes of the reasons orders were skipped for, by day.
+    fn generate(ctx: &Context) -> (usize, usize, usize, Vec<(u32, &'static str)>) {
+        match execute(ctx, StandingOrderCmd::GenerateOrders) {
+            StandingOrderCmdSuccess::GeneratedOrders {
+                added,
+                updated,
+                removed,
+                skipped,
+            } => (
+                added,
+                updated,
+                removed,
+                skipped
+                    .iter()
+                    .map(|s| (s.date.day(), s.reason.code))
+                    .collect(),
+            ),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    /// Get the days of all orders that aren't cancelled.
+    fn ordered_days(ctx: &Context) -> Vec<u32> {
+        let data = ctx.data.read().unwrap();
+        let (data, _) = data.as_ref().unwrap();
+        let mut days: Vec<_> = data
+            .orders
+            .values()
+            .filter(|o| !o.cancelled)
+            .map(|o| o.date.day())
+            .collect();
+        days.sort_unstable();
+        days
+    }
+
+    fn with_data(ctx: &Context, f: impl FnOnce(&mut Data)) {
+        if let Some((data, _)) = &mut *ctx.data.write().unwrap() {
+            f(data);
+        }
+    }
+
+    #[test]
+    fn generates_orders_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let (ctx, _, _) = context(&dir);
+
+        assert_eq!(generate(&ctx), (5, 0, 0, vec![]));
+        assert_eq!(ordered_days(&ctx), MONDAYS);
+        assert_eq!(generate(&ctx), (0, 0, 0, vec![]));
+    }
+
+    #[test]
+    fn manual_orders_take_precedence() {
+        let dir = tempfile::tempdir().unwrap();
+        let (ctx, customer, _) = context(&dir);
+        generate(&ctx);
+
+        with_data(&ctx, |data| {
+            let selection = data.orders.values().next().unwrap().selection.clone();
+            let order = Order::new(customer, date(8), selection, 2);
+            data.orders.insert(Uuid::new_v4(), order);
+        });
+
+        assert_eq!(generate(&ctx), (0, 0, 1, vec![(8, "MANUAL_ORDER")]));
+        assert_eq!(ordered_days(&ctx), MONDAYS);
+
+        let data = ctx.data.read().unwrap();
+        let (data, _) = data.as_ref().unwrap();
+        let order = data.orders.values().find(|o| o.date == date(8)).unwrap();
+        assert_eq!(order.source, OrderSource::Manual);
+    }
+
+    #[test]
+    fn cancelled_orders_are_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let (ctx, _, standing_order) = context(&dir);
+        generate(&ctx);
+
+        with_data(&ctx, |data| {
+            let order = data
+                .orders
+                .values_mut()
+                .find(|o| o.date == date(8))
+                .unwrap();
+            order.cancelled = true;
+        });
+        assert_eq!(generate(&ctx), (0, 0, 0, vec![]));
+        assert_eq!(ordered_days(&ctx), vec![1, 15, 22, 29]);
+
+        // the cancelled order is kept, even once the standing order is gone
+        execute(
+            &ctx,
+            StandingOrderCmd::DeleteStandingOrder { standing_order },
+        );
+        assert_eq!(generate(&ctx), (0, 0, 4, vec![]));
+
+        let data = ctx.data.read().unwrap();
+        let (data, _) = data.as_ref().unwrap();
+        assert_eq!(data.orders.len(), 1);
+        assert!(data
+            .orders
+            .values()
+            .all(|o| o.cancelled && o.date == date(8)));
+    }
+
+    #[test]
+    fn orders_of_days_no_longer_applying_are_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let (ctx, _, standing_order) = context(&dir);
+        generate(&ctx);
+
+        let mut data = ctx.standing_orders.read().unwrap()[&standing_order].clone();
+        data.to = Some(date(14));
+        data.quantity = 2;
+        execute(
+            &ctx,
+            StandingOrderCmd::UpdateStandingOrder {
+                standing_order,
+                data,
+            },
+        );
+
+        assert_eq!(generate(&ctx), (0, 2, 3, vec![]));
+        assert_eq!(ordered_days(&ctx), vec![1, 8]);
+    }
+
+    #[test]
+    fn days_without_meal_on_line_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let (ctx, _, _) = context(&dir);
+        generate(&ctx);
+
+        with_data(&ctx, |data| {
+            data.menu.remove(&date(22));
+        });
+
+        assert_eq!(generate(&ctx), (0, 0, 1, vec![(22, "NO_MEAL_ON_LINE")]));
+        assert_eq!(ordered_days(&ctx), vec![1, 8, 15, 29]);
+    }
+}
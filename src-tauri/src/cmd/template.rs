@@ -0,0 +1,170 @@
+use super::error::{CmdError, ErrorPayload};
+use crate::{
+    context::Context,
+    data::template::{Template, TemplateSummary},
+    event::{Entity, Event},
+    store::StoreError,
+};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(tag = "cmd", rename_all = "camelCase")]
+pub enum TemplateCmd {
+    /// Deletes a template.
+    ///
+    /// # Success variants
+    /// - [`TemplateCmdSuccess::DeletedTemplate`]
+    ///
+    /// # Error variants
+    /// - [`TemplateCmdError::NotFound`]: if the template doesn't exist
+    /// - [`TemplateCmdError::StoreError`]: if the templates can't be read or written
+    DeleteTemplate { template: Uuid },
+    /// Returns an overview of all templates.
+    ///
+    /// # Success variants
+    /// - [`TemplateCmdSuccess::GotTemplates`]
+    ///
+    /// # Error variants
+    /// - [`TemplateCmdError::StoreError`]: if the templates can't be read
+    GetTemplates,
+    /// Renames a template.
+    ///
+    /// # Success variants
+    /// - [`TemplateCmdSuccess::RenamedTemplate`]
+    ///
+    /// # Error variants
+    /// - [`TemplateCmdError::EmptyName`]: if the provided name is empty
+    /// - [`TemplateCmdError::NotFound`]: if the template doesn't exist
+    /// - [`TemplateCmdError::StoreError`]: if the templates can't be read or written
+    RenameTemplate { template: Uuid, name: String },
+    /// Saves the categories, components and meals of the active dataset as a new template.
+    ///
+    /// # Success variants
+    /// - [`TemplateCmdSuccess::SavedTemplate`]
+    ///
+    /// # Error variants
+    /// - [`TemplateCmdError::DatasetNotActive`]: if there is no active dataset
+    /// - [`TemplateCmdError::EmptyName`]: if the provided name is empty
+    /// - [`TemplateCmdError::StoreError`]: if the templates can't be read or written
+    SaveTemplate { name: String },
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(tag = "variant", rename_all = "camelCase")]
+pub enum TemplateCmdSuccess {
+    DeletedTemplate,
+    GotTemplates {
+        data: BTreeMap<Uuid, TemplateSummary>,
+    },
+    RenamedTemplate,
+    SavedTemplate {
+        uuid: Uuid,
+    },
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TemplateCmdError {
+    #[error("error.global.dataset_not_active")]
+    DatasetNotActive,
+    #[error("error.templates.name_empty")]
+    EmptyName,
+    #[error("error.templates.not_found")]
+    NotFound { template: Uuid },
+    #[error(transparent)]
+    StoreError(#[from] StoreError),
+}
+
+impl CmdError for TemplateCmdError {
+    fn payload(&self) -> ErrorPayload {
+        match self {
+            Self::DatasetNotActive => {
+                ErrorPayload::new("DATASET_NOT_ACTIVE", "error.global.dataset_not_active")
+            }
+            Self::EmptyName => ErrorPayload::new("NAME_EMPTY", "error.templates.name_empty"),
+            Self::NotFound { template } => {
+                ErrorPayload::new("TEMPLATE_NOT_FOUND", "error.templates.not_found")
+                    .param("template", template)
+            }
+            Self::StoreError(e) => e.payload(),
+        }
+    }
+}
+
+impl super::CmdAble for TemplateCmd {
+    type Error = TemplateCmdError;
+    type Success = TemplateCmdSuccess;
+
+    fn execute(
+        self: Self,
+        ctx: &Context,
+        events: &mut Vec<Event>,
+    ) -> Result<Self::Success, Self::Error> {
+        match self {
+            Self::DeleteTemplate { template } => {
+                ctx.templates.update(|templates| {
+                    templates
+                        .remove(&template)
+                        .ok_or(Self::Error::NotFound { template })
+                })?;
+                events.push(Event::Deleted {
+                    entity: Entity::Template { uuid: template },
+                });
+                log::info!("deleted template {}", template);
+
+                Ok(Self::Success::DeletedTemplate)
+            }
+            Self::GetTemplates => Ok(Self::Success::GotTemplates {
+                data: ctx
+                    .templates
+                    .read()?
+                    .iter()
+                    .map(|(k, v)| (*k, v.into()))
+                    .collect(),
+            }),
+            Self::RenameTemplate { template, name } => {
+                let name = name.trim();
+                if name.is_empty() {
+                    return Err(Self::Error::EmptyName);
+                }
+
+                ctx.templates.update(|templates| {
+                    match templates.get_mut(&template) {
+                        Some(x) => x.name = name.to_string(),
+                        None => return Err(Self::Error::NotFound { template }),
+                    }
+
+                    Ok(())
+                })?;
+                events.push(Event::Updated {
+                    entity: Entity::Template { uuid: template },
+                });
+
+                Ok(Self::Success::RenamedTemplate)
+            }
+            Self::SaveTemplate { name } => {
+                let name = name.trim();
+                if name.is_empty() {
+                    return Err(Self::Error::EmptyName);
+                }
+
+                let template = match &*ctx.data.read().expect("failed to get data read access") {
+                    Some((data, _)) => Template::from_data(name.to_string(), ctx.clock.now(), data),
+                    None => return Err(Self::Error::DatasetNotActive),
+                };
+
+                let uuid = Uuid::new_v4();
+                ctx.templates.update(|templates| {
+                    templates.insert(uuid, template);
+                    Ok::<_, Self::Error>(())
+                })?;
+                events.push(Event::Added {
+                    entity: Entity::Template { uuid },
+                });
+                log::info!("saved template {}", uuid);
+
+                Ok(Self::Success::SavedTemplate { uuid })
+            }
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use crate::{
+    clock::Clock,
+    data::{
+        calendar::DeliveryCalendar, customer::Customer, diet::Diet, geo::DistanceTable,
+        route::Route, standing_order::StandingOrder, template::Template, Data,
+    },
+    recovery::Files,
+    settings::Settings,
+    store::Store,
+};
+use chrono::NaiveDate;
+use std::{
+    collections::BTreeMap,
+    env,
+    path::PathBuf,
+    sync::{Mutex, RwLock},
+};
+use uuid::Uuid;
+
+/// The active dataset, together with its permanent file and its recovery copy.
+pub type Dataset = (Data, Mutex<Files>);
+
+/// Holds everything commands need to access.
+///
+/// The application creates a single context on startup, which is then passed to every command.
+pub struct Context {
+    /// The active dataset, if there is one.
+    pub data: RwLock<Option<Dataset>>,
+    /// The paths the application works with.
+    pub paths: Paths,
+    /// The user settings.
+    pub settings: RwLock<Settings>,
+    /// The source of the current time.
+    pub clock: Box<dyn Clock>,
+    /// The date the user is planning for, if it differs from the current date.
+    pub planning_date: RwLock<Option<NaiveDate>>,
+    /// The saved templates.
+    pub templates: Store<BTreeMap<Uuid, Template>>,
+    /// The diets meals can be suitable for.
+    pub diets: Store<BTreeMap<Uuid, Diet>>,
+    /// The rules which days meals are delivered on.
+    pub calendar: Store<DeliveryCalendar>,
+    /// The people meals are delivered to.
+    pub customers: Store<BTreeMap<Uuid, Customer>>,
+    /// What customers get every week, unless they order something else.
+    pub standing_orders: Store<BTreeMap<Uuid, StandingOrder>>,
+    /// The tours drivers deliver meals on.
+    pub routes: Store<BTreeMap<Uuid, Route>>,
+    /// The imported road distances between customers.
+    pub distances: Store<DistanceTable>,
+}
+
+impl Context {
+    pub fn new(paths: Paths, settings: Settings, clock: Box<dyn Clock>) -> Self {
+        Self {
+            data: RwLock::new(None),
+            settings: RwLock::new(settings),
+            clock,
+            planning_date: RwLock::new(None),
+            templates: Store::new(paths.data_dir.join("templates.ron")),
+            diets: Store::new(paths.data_dir.join("diets.ron")),
+            calendar: Store::new(paths.data_dir.join("calendar.ron")),
+            customers: Store::new(paths.data_dir.join("customers.ron")),
+            standing_orders: Store::new(paths.data_dir.join("standing_orders.ron")),
+            routes: Store::new(paths.data_dir.join("routes.ron")),
+            distances: Store::new(paths.data_dir.join("distances.ron")),
+            paths,
+        }
+    }
+
+    /// Get the date used for planning.
+    ///
+    /// This is the planning date if one is set, the current date of the clock otherwise.
+    pub fn today(&self) -> NaiveDate {
+        match *self
+            .planning_date
+            .read()
+            .expect("failed to get planning date read access")
+        {
+            Some(date) => date,
+            None => self.clock.today(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl Context {
+    /// Create a context that keeps all files within `dir`, with a clock fixed at `now`.
+    pub fn in_dir(dir: &std::path::Path, now: chrono::DateTime<chrono::Local>) -> Self {
+        let paths = Paths {
+            data_dir: dir.join("data"),
+            cache_dir: dir.join("cache"),
+            config_dir: dir.join("config"),
+            log_dir: dir.join("logs"),
+        };
+
+        Self::new(
+            paths,
+            Settings::default(),
+            Box::new(crate::clock::FixedClock(now)),
+        )
+    }
+}
+
+/// The directories the application stores its files in.
+#[derive(Clone, Debug)]
+pub struct Paths {
+    /// Holds the datasets and their recovery copies.
+    pub data_dir: PathBuf,
+    /// Held the recovery copies of older versions.
+    ///
+    /// See [`crate::recovery::import_legacy`] for details.
+    pub cache_dir: PathBuf,
+    /// Holds the settings.
+    pub config_dir: PathBuf,
+    /// Holds the log files. A subdirectory of the data directory.
+    pub log_dir: PathBuf,
+}
+
+impl Paths {
+    /// Get the default directories of the current platform.
+    ///
+    /// The data directory can be overridden by setting the `MOW_DATADIR` environment variable. The
+    /// log files are kept within it.
+    pub fn from_env() -> Self {
+        let dirs = directories::ProjectDirs::from("dev", "tfld", "Meals on Wheels")
+            .expect("unable to find default directories");
+
+        let data_dir = match env::var("MOW_DATADIR") {
+            Ok(x) => PathBuf::from(x),
+            Err(_) => dirs.data_dir().to_path_buf(),
+        };
+
+        Self {
+            cache_dir: dirs.cache_dir().to_path_buf(),
+            config_dir: dirs.config_dir().to_path_buf(),
+            log_dir: data_dir.join("logs"),
+            data_dir,
+        }
+    }
+
+    /// Get the name of the file for a specific date
+    pub fn dataset_file(&self, year: i32, month: u32) -> PathBuf {
+        let mut file = self.data_dir.clone();
+        file.push(year.to_string());
+        file.push(month.to_string());
+        file.set_extension("ron");
+
+        file
+    }
+
+    /// Get the directory holding the recovery copies of a dataset.
+    ///
+    /// See [`crate::recovery`] for details.
+    pub fn recovery_dir(&self, year: i32, month: u32) -> PathBuf {
+        let mut dir = self.data_dir.clone();
+        dir.push("recovery");
+        dir.push(year.to_string());
+        dir.push(month.to_string());
+
+        dir
+    }
+
+    /// Get the name of a recovery copy of a dataset.
+    pub fn recovery_file(&self, year: i32, month: u32, id: &str) -> PathBuf {
+        let mut file = self.recovery_dir(year, month);
+        file.push(id);
+        file.set_extension("ron");
+
+        file
+    }
+
+    /// Get the name of the settings file.
+    pub fn settings_file(&self) -> PathBuf {
+        self.config_dir.join("settings.ron")
+    }
+}
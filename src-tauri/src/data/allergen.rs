@@ -0,0 +1,90 @@
+use std::collections::BTreeSet;
+
+/// One of the 14 allergens that have to be declared in the EU, as listed in annex II of
+/// regulation (EU) No 1169/2011.
+#[derive(
+    Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, serde::Deserialize, serde::Serialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum Allergen {
+    Gluten,
+    Crustaceans,
+    Eggs,
+    Fish,
+    Peanuts,
+    Soy,
+    Milk,
+    Nuts,
+    Celery,
+    Mustard,
+    Sesame,
+    Sulphites,
+    Lupin,
+    Molluscs,
+}
+
+impl Allergen {
+    /// All allergens, in the order of their letters.
+    pub const ALL: [Self; 14] = [
+        Self::Gluten,
+        Self::Crustaceans,
+        Self::Eggs,
+        Self::Fish,
+        Self::Peanuts,
+        Self::Soy,
+        Self::Milk,
+        Self::Nuts,
+        Self::Celery,
+        Self::Mustard,
+        Self::Sesame,
+        Self::Sulphites,
+        Self::Lupin,
+        Self::Molluscs,
+    ];
+
+    /// Get the letter commonly used for the allergen in footnotes on german menus.
+    pub fn letter(self) -> char {
+        match self {
+            Self::Gluten => 'A',
+            Self::Crustaceans => 'B',
+            Self::Eggs => 'C',
+            Self::Fish => 'D',
+            Self::Peanuts => 'E',
+            Self::Soy => 'F',
+            Self::Milk => 'G',
+            Self::Nuts => 'H',
+            Self::Celery => 'L',
+            Self::Mustard => 'M',
+            Self::Sesame => 'N',
+            Self::Sulphites => 'O',
+            Self::Lupin => 'P',
+            Self::Molluscs => 'R',
+        }
+    }
+}
+
+/// Struct to hold everything that has to be declared for a dish.
+#[derive(Clone, Debug, Default, serde::Serialize)]
+pub struct Declaration {
+    pub allergens: BTreeSet<Allergen>,
+    /// The numbers of the contained additives.
+    pub additives: BTreeSet<u32>,
+}
+
+impl Declaration {
+    /// Add the allergens and additives of a part of the dish.
+    pub fn add(&mut self, allergens: &BTreeSet<Allergen>, additives: &BTreeSet<u32>) {
+        self.allergens.extend(allergens);
+        self.additives.extend(additives);
+    }
+
+    /// Get the footnote for the dish, e.g. `1, 3, A, G`.
+    pub fn footnote(&self) -> String {
+        self.additives
+            .iter()
+            .map(|x| x.to_string())
+            .chain(self.allergens.iter().map(|x| x.letter().to_string()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
@@ -1,4 +1,6 @@
-use chrono::{Datelike, Local};
+use super::Metadata;
+use crate::clock::next_month;
+use chrono::{Datelike, NaiveDate};
 use std::{
     collections::{BTreeMap, HashSet},
     path::PathBuf,
@@ -8,6 +10,7 @@ use std::{
 #[serde(rename_all = "camelCase")]
 pub struct AvailableDatasets {
     data: BTreeMap<i32, HashSet<u32>>,
+    metadata: BTreeMap<i32, BTreeMap<u32, Metadata>>,
     current_year: Option<i32>,
     current_month: Option<u32>,
     can_create_now: bool,
@@ -18,14 +21,14 @@ impl AvailableDatasets {
     /// Get all months for which a data file exists within `base_dir`.
     ///
     /// # Params
-    /// - `base_dir`: searches for data files in this directory. Should be absolute.
+    /// - `now`: the date that is considered the current date.
+    /// - `file_name_format`: gets the data file name of a specific month. Should be absolute.
     ///
     /// # Search limits
-    /// The first month checked is January 1970, the last month is the month after the current local
-    /// month.
-    pub fn from_base_dir(file_name_format: &dyn Fn(i32, u32) -> PathBuf) -> Self {
+    /// The first month checked is January 1970, the last month is the month after the month of
+    /// `now`.
+    pub fn from_base_dir(now: NaiveDate, file_name_format: &dyn Fn(i32, u32) -> PathBuf) -> Self {
         let mut datasets = BTreeMap::new();
-        let now = Local::now().date();
 
         // check past years and current year
         for y in 1970..(now.year() + 1) {
@@ -82,22 +85,29 @@ impl AvailableDatasets {
             None => true,
             Some(x) => !x.contains(&now.month()),
         };
-        let can_create_next = if now.month() != 12 {
-            match datasets.get(&now.year()) {
-                None => true,
-                Some(x) => !x.contains(&(now.month() + 1)),
-            }
-        } else {
-            match datasets.get(&(now.year() + 1)) {
-                None => true,
-                Some(x) => !x.contains(&1),
-            }
+        let (next_year, next_month) = next_month(now.year(), now.month());
+        let can_create_next = match datasets.get(&next_year) {
+            None => true,
+            Some(x) => !x.contains(&next_month),
         };
 
+        // read metadata of all datasets
+        let mut metadata = BTreeMap::new();
+        for (year, months) in &datasets {
+            let mut metadata_year = BTreeMap::new();
+            for month in months {
+                if let Some(x) = Metadata::read(&file_name_format(*year, *month)) {
+                    metadata_year.insert(*month, x);
+                }
+            }
+            metadata.insert(*year, metadata_year);
+        }
+
         Self {
             current_year,
             current_month,
             data: datasets,
+            metadata,
             can_create_now,
             can_create_next,
         }
@@ -125,3 +135,65 @@ impl AvailableDatasets {
         data
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{Clock, FixedClock};
+    use chrono::{Local, TimeZone};
+    use std::fs::{create_dir_all, File};
+    use tempfile::TempDir;
+
+    /// Get the file name of a dataset within `dir`, creating the file if `create` is set.
+    fn file(dir: &TempDir, year: i32, month: u32, create: bool) -> PathBuf {
+        let path = dir
+            .path()
+            .join(year.to_string())
+            .join(format!("{}.ron", month));
+        if create {
+            create_dir_all(path.parent().unwrap()).unwrap();
+            File::create(&path).unwrap();
+        }
+        path
+    }
+
+    #[test]
+    fn next_month_is_in_next_year_in_december() {
+        let dir = tempfile::tempdir().unwrap();
+        let today = FixedClock(Local.ymd(2021, 12, 31).and_hms(23, 59, 59)).today();
+        let format = |year, month| file(&dir, year, month, false);
+
+        let datasets = AvailableDatasets::from_base_dir(today, &format);
+        assert!(datasets.data.is_empty());
+        assert!(datasets.can_create_now);
+        assert!(datasets.can_create_next);
+
+        file(&dir, 2021, 12, true);
+        let datasets = AvailableDatasets::from_base_dir(today, &format);
+        assert_eq!(datasets.current_year, Some(2021));
+        assert_eq!(datasets.current_month, Some(12));
+        assert!(!datasets.can_create_now);
+        assert!(datasets.can_create_next);
+
+        file(&dir, 2022, 1, true);
+        let datasets = AvailableDatasets::from_base_dir(today, &format);
+        assert!(datasets.data[&2022].contains(&1));
+        assert!(!datasets.can_create_now);
+        assert!(!datasets.can_create_next);
+    }
+
+    #[test]
+    fn later_months_are_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let today = FixedClock(Local.ymd(2021, 6, 30).and_hms(12, 0, 0)).today();
+        let format = |year, month| file(&dir, year, month, false);
+
+        file(&dir, 2021, 7, true);
+        file(&dir, 2021, 8, true);
+        let datasets = AvailableDatasets::from_base_dir(today, &format);
+        assert_eq!(datasets.data[&2021], vec![7].into_iter().collect());
+        assert_eq!(datasets.current_month, None);
+        assert!(datasets.can_create_now);
+        assert!(!datasets.can_create_next);
+    }
+}
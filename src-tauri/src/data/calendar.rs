@@ -0,0 +1,353 @@
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use std::{collections::BTreeMap, ops::RangeInclusive};
+use uuid::Uuid;
+
+/// The years public holidays can be calculated for.
+///
+/// The date of easter is only defined by the gregorian calendar, which was introduced in 1582.
+pub const YEARS: RangeInclusive<i32> = 1583..=9999;
+
+/// Struct to hold the rules which days meals are delivered on.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct DeliveryCalendar {
+    /// The weekdays meals are delivered on.
+    pub weekdays: Vec<Weekday>,
+    /// The federal state whose public holidays meals aren't delivered on.
+    pub state: Option<State>,
+    /// Periods meals aren't delivered in, e.g. because the kitchen is closed.
+    pub closures: BTreeMap<Uuid, Closure>,
+}
+
+impl Default for DeliveryCalendar {
+    fn default() -> Self {
+        Self {
+            weekdays: vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+                Weekday::Sat,
+            ],
+            state: None,
+            closures: BTreeMap::new(),
+        }
+    }
+}
+
+impl DeliveryCalendar {
+    /// Get whether meals are delivered on `date`, and why not if they aren't.
+    ///
+    /// If there are several reasons, closures take precedence over holidays, which take
+    /// precedence over weekdays.
+    pub fn status(&self, date: NaiveDate) -> DayStatus {
+        if let Some((uuid, closure)) = self.closures.iter().find(|(_, c)| c.contains(date)) {
+            return DayStatus::Closed {
+                closure: *uuid,
+                reason: closure.reason.clone(),
+            };
+        }
+
+        if let Some(holiday) = self
+            .state
+            .filter(|_| YEARS.contains(&date.year()))
+            .and_then(|s| holidays(s, date.year()).remove(&date))
+        {
+            return DayStatus::Holiday { holiday };
+        }
+
+        if self.weekdays.contains(&date.weekday()) {
+            DayStatus::Delivery
+        } else {
+            DayStatus::Weekday
+        }
+    }
+
+    /// Check whether meals are delivered on `date`.
+    pub fn is_delivery_day(&self, date: NaiveDate) -> bool {
+        matches!(self.status(date), DayStatus::Delivery)
+    }
+}
+
+/// Struct to hold a period meals aren't delivered in.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct Closure {
+    /// The first day of the closure.
+    pub from: NaiveDate,
+    /// The last day of the closure.
+    pub to: NaiveDate,
+    pub reason: String,
+}
+
+impl Closure {
+    /// Check whether `date` lies within the closure.
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.from <= date && date <= self.to
+    }
+}
+
+/// Whether meals are delivered on a day.
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum DayStatus {
+    Delivery,
+    /// Meals aren't delivered on the weekday of the day.
+    Weekday,
+    Holiday {
+        holiday: Holiday,
+    },
+    Closed {
+        closure: Uuid,
+        reason: String,
+    },
+}
+
+/// One of the federal states of germany.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum State {
+    BadenWuerttemberg,
+    Bavaria,
+    Berlin,
+    Brandenburg,
+    Bremen,
+    Hamburg,
+    Hesse,
+    LowerSaxony,
+    MecklenburgWesternPomerania,
+    NorthRhineWestphalia,
+    RhinelandPalatinate,
+    Saarland,
+    Saxony,
+    SaxonyAnhalt,
+    SchleswigHolstein,
+    Thuringia,
+}
+
+/// A public holiday in germany.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Holiday {
+    NewYear,
+    Epiphany,
+    WomensDay,
+    GoodFriday,
+    EasterSunday,
+    EasterMonday,
+    LabourDay,
+    LiberationDay,
+    Ascension,
+    WhitSunday,
+    WhitMonday,
+    CorpusChristi,
+    Assumption,
+    ChildrensDay,
+    GermanUnity,
+    Reformation,
+    AllSaints,
+    Repentance,
+    Christmas,
+    SecondChristmas,
+}
+
+/// Get the public holidays of `state` in `year`.
+///
+/// Holidays that only apply to parts of a state, like the assumption in catholic municipalities
+/// of bavaria, aren't included and have to be entered as closures.
+///
+/// # Panics
+/// Panics if `year` is outside of [`YEARS`].
+pub fn holidays(state: State, year: i32) -> BTreeMap<NaiveDate, Holiday> {
+    use State::*;
+    assert!(YEARS.contains(&year), "no holidays for year {}", year);
+
+    let date = |month, day| NaiveDate::from_ymd(year, month, day);
+    let easter = easter(year);
+    let mut days = BTreeMap::new();
+    let mut add = |date, holiday, applies| {
+        if applies {
+            days.insert(date, holiday);
+        }
+    };
+
+    add(date(1, 1), Holiday::NewYear, true);
+    add(
+        date(1, 6),
+        Holiday::Epiphany,
+        matches!(state, BadenWuerttemberg | Bavaria | SaxonyAnhalt),
+    );
+    add(
+        date(3, 8),
+        Holiday::WomensDay,
+        match state {
+            Berlin => year >= 2019,
+            MecklenburgWesternPomerania => year >= 2023,
+            _ => false,
+        },
+    );
+    add(easter - Duration::days(2), Holiday::GoodFriday, true);
+    add(easter, Holiday::EasterSunday, state == Brandenburg);
+    add(easter + Duration::days(1), Holiday::EasterMonday, true);
+    add(date(5, 1), Holiday::LabourDay, true);
+    add(
+        date(5, 8),
+        Holiday::LiberationDay,
+        state == Berlin && (year == 2020 || year == 2025),
+    );
+    add(easter + Duration::days(39), Holiday::Ascension, true);
+    add(
+        easter + Duration::days(49),
+        Holiday::WhitSunday,
+        state == Brandenburg,
+    );
+    add(easter + Duration::days(50), Holiday::WhitMonday, true);
+    add(
+        easter + Duration::days(60),
+        Holiday::CorpusChristi,
+        matches!(
+            state,
+            BadenWuerttemberg
+                | Bavaria
+                | Hesse
+                | NorthRhineWestphalia
+                | RhinelandPalatinate
+                | Saarland
+        ),
+    );
+    add(date(8, 15), Holiday::Assumption, state == Saarland);
+    add(
+        date(9, 20),
+        Holiday::ChildrensDay,
+        state == Thuringia && year >= 2019,
+    );
+    add(date(10, 3), Holiday::GermanUnity, true);
+    add(
+        date(10, 31),
+        Holiday::Reformation,
+        match state {
+            Brandenburg | MecklenburgWesternPomerania | Saxony | SaxonyAnhalt | Thuringia => true,
+            Bremen | Hamburg | LowerSaxony | SchleswigHolstein => year >= 2017,
+            _ => year == 2017,
+        },
+    );
+    add(
+        date(11, 1),
+        Holiday::AllSaints,
+        matches!(
+            state,
+            BadenWuerttemberg | Bavaria | NorthRhineWestphalia | RhinelandPalatinate | Saarland
+        ),
+    );
+    add(repentance(year), Holiday::Repentance, state == Saxony);
+    add(date(12, 25), Holiday::Christmas, true);
+    add(date(12, 26), Holiday::SecondChristmas, true);
+
+    days
+}
+
+/// Get the date of easter sunday in `year`, using the anonymous gregorian algorithm.
+fn easter(year: i32) -> NaiveDate {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+
+    NaiveDate::from_ymd(year, month as u32, day as u32)
+}
+
+/// Get the date of the day of repentance and prayer in `year`, the last wednesday before the 23rd
+/// of november.
+fn repentance(year: i32) -> NaiveDate {
+    let last = NaiveDate::from_ymd(year, 11, 22);
+    let offset =
+        (last.weekday().num_days_from_monday() + 7 - Weekday::Wed.num_days_from_monday()) % 7;
+
+    last - Duration::days(offset.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd(year, month, day)
+    }
+
+    #[test]
+    fn easter_dates() {
+        assert_eq!(easter(2000), date(2000, 4, 23));
+        assert_eq!(easter(2008), date(2008, 3, 23));
+        assert_eq!(easter(2019), date(2019, 4, 21));
+        assert_eq!(easter(2024), date(2024, 3, 31));
+        assert_eq!(easter(2038), date(2038, 4, 25));
+    }
+
+    #[test]
+    fn state_holidays() {
+        let bavaria = holidays(State::Bavaria, 2024);
+        assert_eq!(bavaria.get(&date(2024, 1, 6)), Some(&Holiday::Epiphany));
+        assert_eq!(bavaria.get(&date(2024, 3, 29)), Some(&Holiday::GoodFriday));
+        assert_eq!(
+            bavaria.get(&date(2024, 5, 30)),
+            Some(&Holiday::CorpusChristi)
+        );
+        assert_eq!(bavaria.get(&date(2024, 3, 31)), None);
+
+        let brandenburg = holidays(State::Brandenburg, 2024);
+        assert_eq!(
+            brandenburg.get(&date(2024, 3, 31)),
+            Some(&Holiday::EasterSunday)
+        );
+        assert_eq!(brandenburg.get(&date(2024, 1, 6)), None);
+
+        let saxony = holidays(State::Saxony, 2024);
+        assert_eq!(saxony.get(&date(2024, 11, 20)), Some(&Holiday::Repentance));
+    }
+
+    #[test]
+    fn holidays_depend_on_year() {
+        assert!(!holidays(State::Berlin, 2018).contains_key(&date(2018, 3, 8)));
+        assert!(holidays(State::Berlin, 2019).contains_key(&date(2019, 3, 8)));
+
+        assert!(holidays(State::Hesse, 2017).contains_key(&date(2017, 10, 31)));
+        assert!(!holidays(State::Hesse, 2018).contains_key(&date(2018, 10, 31)));
+    }
+
+    #[test]
+    fn closures_take_precedence() {
+        let mut cal = DeliveryCalendar {
+            state: Some(State::Bavaria),
+            ..DeliveryCalendar::default()
+        };
+        assert!(matches!(
+            cal.status(date(2024, 1, 6)),
+            DayStatus::Holiday {
+                holiday: Holiday::Epiphany
+            }
+        ));
+
+        cal.closures.insert(
+            Uuid::new_v4(),
+            Closure {
+                from: date(2024, 1, 1),
+                to: date(2024, 1, 7),
+                reason: "vacation".to_string(),
+            },
+        );
+        assert!(matches!(
+            cal.status(date(2024, 1, 6)),
+            DayStatus::Closed { .. }
+        ));
+    }
+}
@@ -0,0 +1,104 @@
+use super::Data;
+use crate::cmd::error::{CmdError, ErrorPayload};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// Error describing a meal that no longer keeps to the limits of a category after a change.
+#[derive(Debug, thiserror::Error)]
+#[error("error.categories.meal_out_of_limits")]
+pub struct LimitError {
+    pub category: Uuid,
+    pub meal: Uuid,
+    /// The name of the meal.
+    pub name: String,
+    /// The number of components of the category in the meal.
+    pub count: u32,
+}
+
+impl CmdError for LimitError {
+    fn payload(&self) -> ErrorPayload {
+        ErrorPayload::new("MEAL_OUT_OF_LIMITS", "error.categories.meal_out_of_limits")
+            .param("category", self.category)
+            .param("meal", self.meal)
+            .param("name", self.name.as_str())
+            .param("count", self.count)
+    }
+}
+
+/// Struct to hold a category of components, e.g. soups or desserts.
+///
+/// The category limits how many of its components a single meal may contain.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct Category {
+    pub name: String,
+    pub delete: bool,
+    /// The minimum number of components of this category in a meal.
+    pub min: u32,
+    /// The maximum number of components of this category in a meal. `None` means there is no
+    /// limit.
+    pub max: Option<u32>,
+}
+
+impl Category {
+    pub fn new(name: String, min: u32, max: Option<u32>) -> Self {
+        Self {
+            name,
+            delete: false,
+            min,
+            max,
+        }
+    }
+}
+
+/// Get the meals that don't keep to the limits of `category`, with their number of components of
+/// it.
+///
+/// Deleted meals are skipped, as are all meals if the category is deleted.
+pub fn meals_out_of_limits(data: &Data, category: Uuid) -> BTreeMap<Uuid, u32> {
+    let cat = match data.categories.get(&category) {
+        Some(x) if !x.delete => x,
+        _ => return BTreeMap::new(),
+    };
+
+    let mut meals = BTreeMap::new();
+    for (uuid, meal) in data.meals.iter().filter(|(_, m)| !m.delete) {
+        let count = meal
+            .components
+            .iter()
+            .filter(|(k, mc)| {
+                !mc.delete
+                    && data
+                        .components
+                        .get(k)
+                        .map_or(false, |c| c.category == Some(category))
+            })
+            .count() as u32;
+
+        if count < cat.min || cat.max.map_or(false, |max| count > max) {
+            meals.insert(*uuid, count);
+        }
+    }
+
+    meals
+}
+
+/// Check that all meals keep to the limits of `category`, except those in `before`.
+///
+/// `before` are the meals that didn't keep to them before a change, see
+/// [`meals_out_of_limits`], so only meals broken by the change are reported.
+pub fn check_meals(
+    data: &Data,
+    category: Uuid,
+    before: &BTreeMap<Uuid, u32>,
+) -> Result<(), LimitError> {
+    let after = meals_out_of_limits(data, category);
+    match after.into_iter().find(|(k, _)| !before.contains_key(k)) {
+        Some((meal, count)) => Err(LimitError {
+            category,
+            meal,
+            name: data.meals[&meal].name.clone(),
+            count,
+        }),
+        None => Ok(()),
+    }
+}
@@ -1,4 +1,5 @@
-use std::collections::BTreeMap;
+use super::{allergen::Allergen, nutrition::Nutrition};
+use std::collections::{BTreeMap, BTreeSet};
 use uuid::Uuid;
 
 /// Struct to hold a single component of a menu.
@@ -6,21 +7,34 @@ use uuid::Uuid;
 pub struct Component {
     pub name: String,
     pub delete: bool,
+    /// The category the component belongs to, if any.
+    #[serde(default)]
+    pub category: std::option::Option<Uuid>,
     pub variants: BTreeMap<Uuid, Variant>,
+    /// The display order of the variants, see [`super::order`].
+    #[serde(default)]
+    pub variant_order: Vec<Uuid>,
     pub options: BTreeMap<Uuid, Option>,
+    /// The display order of the options, see [`super::order`].
+    #[serde(default)]
+    pub option_order: Vec<Uuid>,
 }
 
 impl Component {
     pub fn new(
         name: String,
+        category: std::option::Option<Uuid>,
         variants: BTreeMap<Uuid, Variant>,
         options: BTreeMap<Uuid, Option>,
     ) -> Self {
         Self {
             name,
             delete: false,
+            category,
             variants,
+            variant_order: Vec::new(),
             options,
+            option_order: Vec::new(),
         }
     }
 }
@@ -30,6 +44,22 @@ impl Component {
 pub struct Variant {
     pub name: String,
     pub delete: bool,
+    /// The allergens the variant contains.
+    #[serde(default)]
+    pub allergens: BTreeSet<Allergen>,
+    /// The numbers of the additives the variant contains.
+    #[serde(default)]
+    pub additives: BTreeSet<u32>,
+    /// The diets the variant is suitable for.
+    #[serde(default)]
+    pub diets: BTreeSet<Uuid>,
+    /// The amount added to the price of a meal with this variant, in cents. Negative for
+    /// discounts.
+    #[serde(default)]
+    pub surcharge: i64,
+    /// The nutrition values of a single portion.
+    #[serde(default)]
+    pub nutrition: Nutrition,
 }
 
 impl Variant {
@@ -37,6 +67,11 @@ impl Variant {
         Self {
             name,
             delete: false,
+            allergens: BTreeSet::new(),
+            additives: BTreeSet::new(),
+            diets: BTreeSet::new(),
+            surcharge: 0,
+            nutrition: Nutrition::default(),
         }
     }
 }
@@ -46,6 +81,22 @@ impl Variant {
 pub struct Option {
     pub name: String,
     pub delete: bool,
+    /// The allergens the option contains.
+    #[serde(default)]
+    pub allergens: BTreeSet<Allergen>,
+    /// The numbers of the additives the option contains.
+    #[serde(default)]
+    pub additives: BTreeSet<u32>,
+    /// The diets the option is suitable for.
+    #[serde(default)]
+    pub diets: BTreeSet<Uuid>,
+    /// The amount added to the price of a meal with this option, in cents. Negative for
+    /// discounts.
+    #[serde(default)]
+    pub surcharge: i64,
+    /// The nutrition values of a single portion.
+    #[serde(default)]
+    pub nutrition: Nutrition,
 }
 
 impl Option {
@@ -53,6 +104,11 @@ impl Option {
         Self {
             name,
             delete: false,
+            allergens: BTreeSet::new(),
+            additives: BTreeSet::new(),
+            diets: BTreeSet::new(),
+            surcharge: 0,
+            nutrition: Nutrition::default(),
         }
     }
 }
@@ -0,0 +1,60 @@
+use super::{
+    allergen::Allergen,
+    customer::Customer,
+    diet::Diet,
+    selection::{Selection, SelectionError},
+    Data,
+};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// A restriction of a customer that an ordered meal doesn't keep to.
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Conflict {
+    /// The meal contains an allergen the customer is allergic to.
+    Allergy { allergen: Allergen },
+    /// The meal contains an allergen the customer is intolerant to.
+    Intolerance { allergen: Allergen },
+    /// The meal isn't suitable for a diet the customer needs.
+    Diet { diet: Uuid },
+}
+
+impl Conflict {
+    /// Check whether the conflict prevents the meal from being delivered to the customer.
+    ///
+    /// Other conflicts are only warnings.
+    pub fn is_blocking(&self) -> bool {
+        matches!(self, Self::Allergy { .. })
+    }
+
+    /// Get all conflicts between the restrictions of `customer` and `selection`.
+    ///
+    /// Diets that were deleted from `diets` are ignored.
+    pub fn find(
+        customer: &Customer,
+        selection: &Selection,
+        data: &Data,
+        diets: &BTreeMap<Uuid, Diet>,
+    ) -> Result<Vec<Self>, SelectionError> {
+        let declaration = selection.declaration(data)?;
+        let fitting = selection.diets(data)?;
+
+        let allergies = customer
+            .allergies
+            .intersection(&declaration.allergens)
+            .map(|a| Self::Allergy { allergen: *a });
+        let intolerances = customer
+            .intolerances
+            .intersection(&declaration.allergens)
+            .filter(|a| !customer.allergies.contains(a))
+            .map(|a| Self::Intolerance { allergen: *a });
+        let diets = customer
+            .diets
+            .iter()
+            .filter(|d| diets.contains_key(d) && !fitting.contains(d))
+            .map(|d| Self::Diet { diet: *d });
+
+        Ok(allergies.chain(intolerances).chain(diets).collect())
+    }
+}
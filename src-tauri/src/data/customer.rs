@@ -0,0 +1,86 @@
+use super::{allergen::Allergen, geo::Coordinates};
+use chrono::{NaiveDate, NaiveTime};
+use std::collections::BTreeSet;
+use uuid::Uuid;
+
+/// Struct to hold a person meals are delivered to.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct Customer {
+    /// The customer number, unique among all customers.
+    #[serde(default)]
+    pub number: u32,
+    pub name: String,
+    pub address: Address,
+    #[serde(default)]
+    pub phone: String,
+    /// Notes for the driver, e.g. where to leave the meal.
+    #[serde(default)]
+    pub notes: String,
+    /// The first day meals are delivered to the customer.
+    #[serde(default)]
+    pub start: Option<NaiveDate>,
+    /// The last day meals are delivered to the customer.
+    #[serde(default)]
+    pub end: Option<NaiveDate>,
+    /// The diets the customer needs.
+    #[serde(default)]
+    pub diets: BTreeSet<Uuid>,
+    /// The allergens the customer mustn't get.
+    #[serde(default)]
+    pub allergies: BTreeSet<Allergen>,
+    /// The allergens the customer should avoid, but may get if necessary.
+    #[serde(default)]
+    pub intolerances: BTreeSet<Allergen>,
+    /// Where meals are delivered to, used to plan the order of route stops.
+    #[serde(default)]
+    pub coordinates: Option<Coordinates>,
+    /// The time of day meals have to be delivered in.
+    #[serde(default)]
+    pub window: Option<TimeWindow>,
+}
+
+impl Customer {
+    /// Check whether meals are delivered to the customer on `date`.
+    pub fn is_active(&self, date: NaiveDate) -> bool {
+        self.start.map_or(true, |s| s <= date) && !self.has_left(date)
+    }
+
+    /// Check whether the service of the customer ended before `date`.
+    pub fn has_left(&self, date: NaiveDate) -> bool {
+        self.end.map_or(false, |e| e < date)
+    }
+
+    /// Check whether the customer matches a search query.
+    ///
+    /// `query` has to be lowercase. It is searched for in the number, name, address and phone
+    /// number.
+    pub fn matches(&self, query: &str) -> bool {
+        self.number.to_string() == query
+            || [
+                &self.name,
+                &self.address.street,
+                &self.address.postcode,
+                &self.address.city,
+                &self.phone,
+            ]
+            .iter()
+            .any(|x| x.to_lowercase().contains(query))
+    }
+}
+
+/// A span of time within a day, both ends included.
+#[derive(Clone, Copy, Debug, serde::Deserialize, serde::Serialize)]
+pub struct TimeWindow {
+    pub from: NaiveTime,
+    pub to: NaiveTime,
+}
+
+/// Struct to hold a postal address.
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Address {
+    /// The street and house number.
+    pub street: String,
+    pub postcode: String,
+    pub city: String,
+}
@@ -0,0 +1,47 @@
+use super::selection::Selection;
+use chrono::NaiveDate;
+use uuid::Uuid;
+
+/// Struct to hold the meals a customer ordered for a single day.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Order {
+    pub customer: Uuid,
+    /// The day the meals are delivered on.
+    pub date: NaiveDate,
+    /// The ordered meal, with its variants and options.
+    pub selection: Selection,
+    /// The number of portions.
+    pub quantity: u32,
+    pub cancelled: bool,
+    #[serde(default)]
+    pub source: OrderSource,
+}
+
+impl Order {
+    pub fn new(customer: Uuid, date: NaiveDate, selection: Selection, quantity: u32) -> Self {
+        Self {
+            customer,
+            date,
+            selection,
+            quantity,
+            cancelled: false,
+            source: OrderSource::Manual,
+        }
+    }
+}
+
+/// Where an order came from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OrderSource {
+    /// The order was entered or changed by hand.
+    Manual,
+    /// The order was generated from the standing order with the uuid.
+    Standing(Uuid),
+}
+
+impl Default for OrderSource {
+    fn default() -> Self {
+        Self::Manual
+    }
+}
@@ -0,0 +1,5 @@
+/// Struct to hold a diet meals can be suitable for, e.g. vegetarian or pureed.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct Diet {
+    pub name: String,
+}
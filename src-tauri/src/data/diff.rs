@@ -0,0 +1,332 @@
+use super::{meal::Meal, Data};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// Struct to hold the differences between two datasets.
+///
+/// Entities marked as deleted are treated as if they didn't exist. Entities are matched by their
+/// uuid first. Entities that can't be matched that way are matched by their name instead.
+#[derive(Debug, Default, serde::Serialize)]
+pub struct Diff {
+    /// Components that were added, removed, renamed or had their variants or options changed.
+    pub components: Vec<ComponentDiff>,
+    /// Meals that were added, removed, renamed or had their components changed.
+    pub meals: Vec<MealDiff>,
+}
+
+impl Diff {
+    /// Compare the content of `old` with the content of `new`.
+    pub fn new(old: &Data, new: &Data) -> Self {
+        let mut diff = Self::default();
+        // maps the uuids of matched components and their variants from old to new
+        let mut ids = BTreeMap::new();
+
+        for (o, n) in pair(&old.components, &new.components, |c| c.delete, |c| &c.name) {
+            let mut variants = Vec::new();
+            let mut options = Vec::new();
+
+            if let (Some((o_id, o_cmp)), Some((n_id, n_cmp))) = (o, n) {
+                let mut variant_ids = BTreeMap::new();
+                for (o, n) in pair(&o_cmp.variants, &n_cmp.variants, |v| v.delete, |v| &v.name) {
+                    if let (Some((o_id, _)), Some((n_id, _))) = (o, n) {
+                        variant_ids.insert(o_id, n_id);
+                    }
+                    variants.push(Change::new(o, n, |v| &v.name));
+                }
+                for (o, n) in pair(&o_cmp.options, &n_cmp.options, |o| o.delete, |o| &o.name) {
+                    options.push(Change::new(o, n, |o| &o.name));
+                }
+                variants.retain(|c| !c.is_matched());
+                options.retain(|c| !c.is_matched());
+
+                ids.insert(o_id, (n_id, variant_ids));
+            }
+
+            let change = Change::new(o, n, |c| &c.name);
+            if change.is_matched() && variants.is_empty() && options.is_empty() {
+                continue;
+            }
+
+            diff.components.push(ComponentDiff {
+                change,
+                variants,
+                options,
+            });
+        }
+
+        for (o, n) in pair(&old.meals, &new.meals, |m| m.delete, |m| &m.name) {
+            let components = match (o, n) {
+                (Some((_, o_meal)), Some((_, n_meal))) => {
+                    assignment_diffs(old, o_meal, new, n_meal, &ids)
+                }
+                _ => Vec::new(),
+            };
+
+            let change = Change::new(o, n, |m| &m.name);
+            if change.is_matched() && components.is_empty() {
+                continue;
+            }
+
+            diff.meals.push(MealDiff { change, components });
+        }
+
+        diff
+    }
+}
+
+/// Struct to hold the differences of a single component.
+#[derive(Debug, serde::Serialize)]
+pub struct ComponentDiff {
+    #[serde(flatten)]
+    pub change: Change,
+    pub variants: Vec<Change>,
+    pub options: Vec<Change>,
+}
+
+/// Struct to hold the differences of a single meal.
+#[derive(Debug, serde::Serialize)]
+pub struct MealDiff {
+    #[serde(flatten)]
+    pub change: Change,
+    /// The changed component assignments.
+    pub components: Vec<AssignmentDiff>,
+}
+
+/// How a single entity differs between the datasets.
+#[derive(Debug, serde::Serialize)]
+#[serde(tag = "change", rename_all = "camelCase")]
+pub enum Change {
+    /// The entity only exists in the new dataset.
+    Added { new: Side },
+    /// The entity only exists in the old dataset.
+    Removed { old: Side },
+    /// The entity exists in both datasets, but has a different name.
+    Renamed { old: Side, new: Side },
+    /// The entity exists in both datasets, with the same name. Only its children changed.
+    Matched { old: Side, new: Side },
+}
+
+impl Change {
+    /// Describe the change between a pair of entities, as returned by [`pair`].
+    fn new<T>(
+        old: Option<(Uuid, &T)>,
+        new: Option<(Uuid, &T)>,
+        name: impl Fn(&T) -> &String,
+    ) -> Self {
+        let side = |(uuid, x)| Side {
+            uuid,
+            name: name(x).clone(),
+        };
+
+        match (old.map(&side), new.map(&side)) {
+            (Some(old), Some(new)) if old.name == new.name => Self::Matched { old, new },
+            (Some(old), Some(new)) => Self::Renamed { old, new },
+            (Some(old), None) => Self::Removed { old },
+            (None, Some(new)) => Self::Added { new },
+            (None, None) => unreachable!("pair without entities"),
+        }
+    }
+
+    fn is_matched(&self) -> bool {
+        matches!(self, Self::Matched { .. })
+    }
+}
+
+/// Identifies an entity within one of the datasets.
+#[derive(Debug, serde::Serialize)]
+pub struct Side {
+    pub uuid: Uuid,
+    pub name: String,
+}
+
+/// A changed assignment of a component to a meal.
+#[derive(Debug, serde::Serialize)]
+pub struct AssignmentDiff {
+    /// The assignment in the old dataset, `None` if the component wasn't assigned.
+    pub old: Option<Assignment>,
+    /// The assignment in the new dataset, `None` if the component isn't assigned.
+    pub new: Option<Assignment>,
+}
+
+/// The assignment of a component to a meal within one of the datasets.
+#[derive(Debug, serde::Serialize)]
+pub struct Assignment {
+    pub component: Side,
+    /// The chosen variant, `None` if the meal leaves the choice open.
+    pub variant: Option<Side>,
+}
+
+impl Assignment {
+    fn new(data: &Data, component: Uuid, variant: Option<Uuid>) -> Self {
+        let cmp = data.components.get(&component);
+
+        Self {
+            component: Side {
+                uuid: component,
+                name: cmp.map(|c| c.name.clone()).unwrap_or_default(),
+            },
+            variant: variant.map(|uuid| Side {
+                uuid,
+                name: cmp
+                    .and_then(|c| c.variants.get(&uuid))
+                    .map(|v| v.name.clone())
+                    .unwrap_or_default(),
+            }),
+        }
+    }
+}
+
+/// Find the changed component assignments of a pair of matched meals.
+///
+/// `ids` maps the uuids of components and variants in `old` to their counterparts in `new`.
+fn assignment_diffs(
+    old: &Data,
+    old_meal: &Meal,
+    new: &Data,
+    new_meal: &Meal,
+    ids: &BTreeMap<Uuid, (Uuid, BTreeMap<Uuid, Uuid>)>,
+) -> Vec<AssignmentDiff> {
+    let mut new_assignments: BTreeMap<_, _> = new_meal
+        .components
+        .iter()
+        .filter(|(_, mc)| !mc.delete)
+        .map(|(k, mc)| (*k, mc.variant))
+        .collect();
+
+    let mut diffs = Vec::new();
+    for (component, mc) in old_meal.components.iter().filter(|(_, mc)| !mc.delete) {
+        let (new_component, new_variant) = match ids.get(component) {
+            Some((n_id, variants)) => (
+                *n_id,
+                mc.variant.map(|v| variants.get(&v).copied().unwrap_or(v)),
+            ),
+            // the component was removed, so it can't be assigned anymore
+            None => (*component, mc.variant),
+        };
+
+        let assigned = new_assignments.remove(&new_component);
+        if assigned == Some(new_variant) {
+            continue;
+        }
+
+        diffs.push(AssignmentDiff {
+            old: Some(Assignment::new(old, *component, mc.variant)),
+            new: assigned.map(|v| Assignment::new(new, new_component, v)),
+        });
+    }
+
+    diffs.extend(
+        new_assignments
+            .into_iter()
+            .map(|(component, variant)| AssignmentDiff {
+                old: None,
+                new: Some(Assignment::new(new, component, variant)),
+            }),
+    );
+
+    diffs
+}
+
+/// Match the entities of `old` with the entities of `new`.
+///
+/// Entities are matched by uuid first, and by name if that isn't possible. Entities for which
+/// `deleted` returns true are ignored.
+fn pair<'a, T>(
+    old: &'a BTreeMap<Uuid, T>,
+    new: &'a BTreeMap<Uuid, T>,
+    deleted: impl Fn(&T) -> bool,
+    name: impl Fn(&T) -> &String,
+) -> Vec<(Option<(Uuid, &'a T)>, Option<(Uuid, &'a T)>)> {
+    let mut pairs = Vec::new();
+    let mut old_left = Vec::new();
+    let mut new_left: BTreeMap<Uuid, &T> = new
+        .iter()
+        .filter(|(_, x)| !deleted(x))
+        .map(|(k, x)| (*k, x))
+        .collect();
+
+    for (k, x) in old.iter().filter(|(_, x)| !deleted(x)) {
+        match new_left.remove(k) {
+            Some(n) => pairs.push((Some((*k, x)), Some((*k, n)))),
+            None => old_left.push((*k, x)),
+        }
+    }
+
+    for (k, x) in old_left {
+        let found = new_left
+            .iter()
+            .find(|(_, n)| name(n) == name(x))
+            .map(|(n_k, _)| *n_k);
+
+        match found.and_then(|n_k| new_left.remove_entry(&n_k)) {
+            Some(n) => pairs.push((Some((k, x)), Some(n))),
+            None => pairs.push((Some((k, x)), None)),
+        }
+    }
+
+    pairs.extend(new_left.into_iter().map(|n| (None, Some(n))));
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{fixtures::data, meal::Meal};
+
+    #[test]
+    fn matches_by_uuid_then_name() {
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let old = data(&[(a, "soup"), (b, "salad")]);
+        let new = data(&[(a, "tomato soup"), (c, "salad")]);
+
+        let diff = Diff::new(&old, &new);
+        assert_eq!(diff.components.len(), 1);
+        assert!(matches!(
+            &diff.components[0].change,
+            Change::Renamed { old, new } if old.uuid == a && new.name == "tomato soup"
+        ));
+    }
+
+    #[test]
+    fn deleted_entities_are_removed() {
+        let a = Uuid::new_v4();
+        let old = data(&[(a, "soup")]);
+        let mut new = data(&[(a, "soup")]);
+        new.components.get_mut(&a).unwrap().delete = true;
+
+        let diff = Diff::new(&old, &new);
+        assert_eq!(diff.components.len(), 1);
+        assert!(matches!(
+            &diff.components[0].change,
+            Change::Removed { old } if old.uuid == a
+        ));
+    }
+
+    #[test]
+    fn finds_changed_assignments() {
+        let (a, b, meal) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let mut old = data(&[(a, "soup"), (b, "salad")]);
+        let mut new = data(&[(a, "soup"), (b, "salad")]);
+
+        let components = |uuid| vec![(uuid, None)].into_iter().collect();
+        old.meals.insert(
+            meal,
+            Meal::new("lunch".to_string(), "L".to_string(), components(a)),
+        );
+        new.meals.insert(
+            meal,
+            Meal::new("lunch".to_string(), "L".to_string(), components(b)),
+        );
+
+        let diff = Diff::new(&old, &new);
+        assert!(diff.components.is_empty());
+        assert_eq!(diff.meals.len(), 1);
+
+        let assignments = &diff.meals[0].components;
+        assert_eq!(assignments.len(), 2);
+        assert_eq!(assignments[0].old.as_ref().unwrap().component.uuid, a);
+        assert!(assignments[0].new.is_none());
+        assert!(assignments[1].old.is_none());
+        assert_eq!(assignments[1].new.as_ref().unwrap().component.uuid, b);
+    }
+}
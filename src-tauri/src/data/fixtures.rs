@@ -0,0 +1,17 @@
+//! Datasets used by tests.
+
+use super::{component::Component, Data, Metadata};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// Create a dataset for march 2021 that contains `components`, given by uuid and name.
+pub fn data(components: &[(Uuid, &str)]) -> Data {
+    let mut data = Data::new(2021, 3, Metadata::default());
+    for (uuid, name) in components {
+        data.components.insert(
+            *uuid,
+            Component::new(name.to_string(), None, BTreeMap::new(), BTreeMap::new()),
+        );
+    }
+    data
+}
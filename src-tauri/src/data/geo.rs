@@ -0,0 +1,107 @@
+//! Locations and the distances between them.
+
+use std::collections::BTreeMap;
+
+/// The mean radius of the earth in metres.
+const EARTH_RADIUS: f64 = 6_371_000.0;
+
+/// A position on the surface of the earth, in degrees.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Coordinates {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl Coordinates {
+    /// Check whether the coordinates are within the range of latitudes and longitudes.
+    pub fn is_valid(self) -> bool {
+        (-90.0..=90.0).contains(&self.latitude) && (-180.0..=180.0).contains(&self.longitude)
+    }
+
+    /// Get the straight-line distance to `other` in metres, along the surface of the earth.
+    pub fn distance(self, other: Self) -> f64 {
+        let (lat1, lat2) = (self.latitude.to_radians(), other.latitude.to_radians());
+        let dlat = lat2 - lat1;
+        let dlon = (other.longitude - self.longitude).to_radians();
+
+        let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS * a.sqrt().asin()
+    }
+}
+
+/// Road distances between customers, imported from a routing tool.
+///
+/// Customers are identified by their number. Number 0 stands for the depot routes start and end
+/// at.
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+pub struct DistanceTable {
+    /// The legs, by the number of their start and their end.
+    pub legs: BTreeMap<u32, BTreeMap<u32, Leg>>,
+}
+
+/// The way from one place to another.
+#[derive(Clone, Copy, Debug, serde::Deserialize, serde::Serialize)]
+pub struct Leg {
+    /// The distance in metres.
+    pub distance: f64,
+    /// The travel time in seconds.
+    pub duration: f64,
+}
+
+impl DistanceTable {
+    /// Parse a table with one leg per line.
+    ///
+    /// Each line holds the number of the start, the number of the end, the distance in metres and
+    /// the travel time in seconds, separated by commas, semicolons or tabs. Empty lines and lines
+    /// starting with `#` are skipped, as is a header in the first line.
+    ///
+    /// Returns the number of the first line that can't be parsed on failure.
+    pub fn parse(text: &str) -> Result<Self, usize> {
+        let mut table = Self::default();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            match parse_leg(line) {
+                Some((from, to, leg)) => {
+                    table.legs.entry(from).or_default().insert(to, leg);
+                }
+                None if i == 0 => {}
+                None => return Err(i + 1),
+            }
+        }
+
+        Ok(table)
+    }
+
+    /// Get the leg from `from` to `to`.
+    ///
+    /// If the table only contains the leg in the other direction, that one is used.
+    pub fn get(&self, from: u32, to: u32) -> Option<Leg> {
+        let leg = |a, b| self.legs.get(&a).and_then(|l: &BTreeMap<_, _>| l.get(&b));
+        leg(from, to).or_else(|| leg(to, from)).copied()
+    }
+}
+
+/// Parse a single line of a [`DistanceTable`].
+fn parse_leg(line: &str) -> Option<(u32, u32, Leg)> {
+    let fields: Vec<_> = line.split(&[',', ';', '\t'][..]).map(str::trim).collect();
+    let (from, to, distance, duration) = match fields[..] {
+        [from, to, distance, duration] => (from, to, distance, duration),
+        _ => return None,
+    };
+
+    let leg = Leg {
+        distance: distance.parse().ok()?,
+        duration: duration.parse().ok()?,
+    };
+    let valid = |x: f64| x.is_finite() && x >= 0.0;
+    if valid(leg.distance) && valid(leg.duration) {
+        Some((from.parse().ok()?, to.parse().ok()?, leg))
+    } else {
+        None
+    }
+}
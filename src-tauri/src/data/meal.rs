@@ -1,4 +1,8 @@
-use std::{collections::BTreeMap, iter::FromIterator};
+use super::{allergen::Declaration, component::Component};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    iter::FromIterator,
+};
 use uuid::Uuid;
 
 /// Struct to hold a meal
@@ -8,6 +12,11 @@ pub struct Meal {
     pub short: String,
     pub delete: bool,
     pub components: BTreeMap<Uuid, MealComponent>,
+    /// The base price in cents, if it is set.
+    ///
+    /// Prices are part of the monthly dataset, so changing them doesn't affect past months.
+    #[serde(default)]
+    pub price: Option<i64>,
 }
 
 impl Meal {
@@ -16,6 +25,7 @@ impl Meal {
             name,
             short,
             delete: false,
+            price: None,
             components: BTreeMap::from_iter(
                 components
                     .into_iter()
@@ -23,6 +33,93 @@ impl Meal {
             ),
         }
     }
+
+    /// Get the combined allergens and additives of the chosen variants.
+    ///
+    /// If the meal leaves the choice of a variant open, all variants of the component are
+    /// included, as any of them may be served.
+    pub fn declaration(&self, components: &BTreeMap<Uuid, Component>) -> Declaration {
+        let mut declaration = Declaration::default();
+
+        for (k, mc) in self.components.iter().filter(|(_, mc)| !mc.delete) {
+            let cmp = match components.get(k) {
+                Some(x) => x,
+                None => continue,
+            };
+
+            match mc.variant {
+                Some(v) => {
+                    if let Some(v) = cmp.variants.get(&v) {
+                        declaration.add(&v.allergens, &v.additives);
+                    }
+                }
+                None => {
+                    for v in cmp.variants.values().filter(|v| !v.delete) {
+                        declaration.add(&v.allergens, &v.additives);
+                    }
+                }
+            }
+        }
+
+        declaration
+    }
+
+    /// Get the diets the meal is suitable for.
+    ///
+    /// The meal is suitable for a diet if all its chosen variants are. If the meal leaves the
+    /// choice of a variant open, it is enough if one of the variants is suitable.
+    pub fn diets(&self, components: &BTreeMap<Uuid, Component>) -> BTreeSet<Uuid> {
+        let mut diets: Option<BTreeSet<Uuid>> = None;
+
+        for (k, mc) in self.components.iter().filter(|(_, mc)| !mc.delete) {
+            let cmp = components.get(k);
+            let fitting: BTreeSet<Uuid> = match mc.variant {
+                Some(v) => cmp
+                    .and_then(|c| c.variants.get(&v))
+                    .map(|v| v.diets.clone())
+                    .unwrap_or_default(),
+                None => cmp
+                    .iter()
+                    .flat_map(|c| c.variants.values())
+                    .filter(|v| !v.delete)
+                    .flat_map(|v| v.diets.iter().copied())
+                    .collect(),
+            };
+
+            diets = Some(match diets {
+                Some(d) => d.intersection(&fitting).copied().collect(),
+                None => fitting,
+            });
+        }
+
+        diets.unwrap_or_default()
+    }
+}
+
+/// Struct to hold a meal, together with information computed from its components.
+#[derive(Debug, serde::Serialize)]
+pub struct MealEntry {
+    #[serde(flatten)]
+    pub meal: Meal,
+    #[serde(flatten)]
+    pub declaration: Declaration,
+    /// The diets the meal is suitable for.
+    pub diets: BTreeSet<Uuid>,
+    /// The footnote declaring the allergens and additives on menus.
+    pub footnote: String,
+}
+
+impl MealEntry {
+    pub fn new(meal: &Meal, components: &BTreeMap<Uuid, Component>) -> Self {
+        let declaration = meal.declaration(components);
+
+        Self {
+            meal: meal.clone(),
+            footnote: declaration.footnote(),
+            declaration,
+            diets: meal.diets(components),
+        }
+    }
 }
 
 /// Struct to hold connection from meal to component
@@ -0,0 +1,400 @@
+use super::Data;
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+
+/// Struct to hold the result of merging two versions of a dataset that diverged from a common
+/// base.
+///
+/// The versions are compared value by value, so changes to different entities, or to different
+/// fields of the same entity, are merged without conflicts. A conflict is only reported if both
+/// versions changed the same value in different ways, or if one of them removed an entity the
+/// other one changed.
+///
+/// Lists of unique values, like display orders or the meals of a day, are merged value by value
+/// as well, see [`merge_set`].
+///
+/// When the merge is created, conflicting values are taken from "ours" until they are resolved.
+pub struct Merge {
+    value: Value,
+    pub conflicts: Vec<Conflict>,
+}
+
+impl Merge {
+    pub fn new(
+        mut base: Data,
+        mut ours: Data,
+        mut theirs: Data,
+    ) -> Result<Self, serde_json::Error> {
+        // these are replaced when the result is saved anyway
+        for data in &mut [&mut base, &mut ours, &mut theirs] {
+            data.meta.saved = None;
+            data.meta.saved_by = None;
+        }
+
+        let base = serde_json::to_value(base)?;
+        let ours = serde_json::to_value(ours)?;
+        let theirs = serde_json::to_value(theirs)?;
+
+        let mut conflicts = Vec::new();
+        let value = merge(
+            Some(&base),
+            Some(&ours),
+            Some(&theirs),
+            &mut Vec::new(),
+            &mut conflicts,
+        )
+        .unwrap_or(Value::Null);
+
+        Ok(Self { value, conflicts })
+    }
+
+    /// Resolve conflicts.
+    ///
+    /// Resolutions that don't match a conflict are ignored.
+    pub fn resolve(&mut self, resolutions: &[Resolution]) {
+        for resolution in resolutions {
+            let index = match self
+                .conflicts
+                .iter()
+                .position(|c| c.path == resolution.path)
+            {
+                Some(x) => x,
+                None => continue,
+            };
+            let conflict = self.conflicts.remove(index);
+
+            let mut parents = conflict.path.clone();
+            let key = match parents.pop() {
+                Some(x) => x,
+                None => continue,
+            };
+            let parent = parents
+                .iter()
+                .try_fold(&mut self.value, |value, key| value.get_mut(key));
+
+            if let Some(Value::Object(parent)) = parent {
+                match conflict.take(resolution.choice) {
+                    Some(value) => parent.insert(key, value),
+                    None => parent.remove(&key),
+                };
+            }
+        }
+    }
+
+    /// Get the merged dataset.
+    pub fn into_data(self) -> Result<Data, serde_json::Error> {
+        serde_json::from_value(self.value)
+    }
+}
+
+/// Struct to hold a value both versions changed in different ways.
+#[derive(Debug, serde::Serialize)]
+pub struct Conflict {
+    /// The keys leading to the value, e.g. `["components", "<uuid>", "name"]`.
+    pub path: Vec<String>,
+    /// The value in the base version, `None` if it didn't exist.
+    pub base: Option<Value>,
+    /// The value in "our" version, `None` if it was removed.
+    pub ours: Option<Value>,
+    /// The value in "their" version, `None` if it was removed.
+    pub theirs: Option<Value>,
+}
+
+impl Conflict {
+    fn take(self, choice: Choice) -> Option<Value> {
+        match choice {
+            Choice::Base => self.base,
+            Choice::Ours => self.ours,
+            Choice::Theirs => self.theirs,
+        }
+    }
+}
+
+/// Struct to hold the way a single conflict should be resolved.
+#[derive(Debug, serde::Deserialize)]
+pub struct Resolution {
+    /// The path of the conflict.
+    pub path: Vec<String>,
+    pub choice: Choice,
+}
+
+/// The version a conflicting value is taken from.
+#[derive(Clone, Copy, Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Choice {
+    Base,
+    Ours,
+    Theirs,
+}
+
+/// Merge a single value.
+///
+/// `None` means the value doesn't exist in a version, or that it is removed from the result.
+fn merge(
+    base: Option<&Value>,
+    ours: Option<&Value>,
+    theirs: Option<&Value>,
+    path: &mut Vec<String>,
+    conflicts: &mut Vec<Conflict>,
+) -> Option<Value> {
+    // only one side changed the value, or both made the same change
+    if ours == theirs || base == theirs {
+        return ours.cloned();
+    }
+    if base == ours {
+        return theirs.cloned();
+    }
+
+    // both sides changed a list, so merge it value by value
+    if let (Some(Value::Array(o)), Some(Value::Array(t))) = (ours, theirs) {
+        let b = match base {
+            Some(Value::Array(b)) => &b[..],
+            _ => &[],
+        };
+        if let Some(merged) = merge_set(b, o, t) {
+            return Some(Value::Array(merged));
+        }
+    }
+
+    // both sides changed the content of an object, so merge it key by key
+    if let (Some(Value::Object(o)), Some(Value::Object(t))) = (ours, theirs) {
+        let b = match base {
+            Some(Value::Object(b)) => Some(b),
+            _ => None,
+        };
+
+        let mut merged = Map::new();
+        for key in o.keys().chain(t.keys()).collect::<BTreeSet<_>>() {
+            path.push(key.clone());
+            let value = merge(
+                b.and_then(|b| b.get(key)),
+                o.get(key),
+                t.get(key),
+                path,
+                conflicts,
+            );
+            path.pop();
+
+            if let Some(value) = value {
+                merged.insert(key.clone(), value);
+            }
+        }
+
+        return Some(Value::Object(merged));
+    }
+
+    conflicts.push(Conflict {
+        path: path.clone(),
+        base: base.cloned(),
+        ours: ours.cloned(),
+        theirs: theirs.cloned(),
+    });
+    ours.cloned()
+}
+
+/// Merge lists of unique values, keeping their order.
+///
+/// Values either side removed are dropped, values either side added are appended, ours first.
+/// The remaining values keep their order from the base, unless one side changed it.
+///
+/// Returns `None` if the lists aren't lists of unique strings and numbers, or if both sides
+/// changed the order in different ways.
+fn merge_set(base: &[Value], ours: &[Value], theirs: &[Value]) -> Option<Vec<Value>> {
+    let is_set = |list: &[Value]| {
+        list.iter()
+            .enumerate()
+            .all(|(i, v)| (v.is_string() || v.is_number()) && !list[..i].contains(v))
+    };
+    if !(is_set(base) && is_set(ours) && is_set(theirs)) {
+        return None;
+    }
+
+    // the values kept by both sides, in the order of a single version
+    let kept = |list: &[Value]| -> Vec<Value> {
+        list.iter()
+            .filter(|v| base.contains(v) && ours.contains(v) && theirs.contains(v))
+            .cloned()
+            .collect()
+    };
+    let (b, o, t) = (kept(base), kept(ours), kept(theirs));
+    let mut merged = if o == b || o == t {
+        t
+    } else if t == b {
+        o
+    } else {
+        return None;
+    };
+
+    for value in ours.iter().chain(theirs) {
+        if !base.contains(value) && !merged.contains(value) {
+            merged.push(value.clone());
+        }
+    }
+
+    Some(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{
+        component::{Component, Variant},
+        fixtures::data,
+        meal::Meal,
+        order,
+    };
+    use std::collections::BTreeMap;
+    use uuid::Uuid;
+
+    #[test]
+    fn merges_independent_changes() {
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let base = data(&[(a, "soup")]);
+        let ours = data(&[(a, "tomato soup")]);
+        let theirs = data(&[(a, "soup"), (b, "salad")]);
+
+        let merge = Merge::new(base, ours, theirs).unwrap();
+        assert!(merge.conflicts.is_empty());
+
+        let merged = merge.into_data().unwrap();
+        assert_eq!(merged.components[&a].name, "tomato soup");
+        assert_eq!(merged.components[&b].name, "salad");
+    }
+
+    #[test]
+    fn resolves_conflicting_changes() {
+        let a = Uuid::new_v4();
+        let base = data(&[(a, "soup")]);
+        let ours = data(&[(a, "tomato soup")]);
+        let theirs = data(&[(a, "pea soup")]);
+
+        let mut merge = Merge::new(base, ours, theirs).unwrap();
+        let path = vec!["components".to_string(), a.to_string(), "name".to_string()];
+        assert_eq!(merge.conflicts.len(), 1);
+        assert_eq!(merge.conflicts[0].path, path);
+
+        merge.resolve(&[Resolution {
+            path,
+            choice: Choice::Theirs,
+        }]);
+        assert!(merge.conflicts.is_empty());
+        assert_eq!(merge.into_data().unwrap().components[&a].name, "pea soup");
+    }
+
+    #[test]
+    fn removal_conflicts_with_change() {
+        let a = Uuid::new_v4();
+        let base = data(&[(a, "soup")]);
+        let ours = data(&[]);
+        let theirs = data(&[(a, "pea soup")]);
+
+        let mut merge = Merge::new(base, ours, theirs).unwrap();
+        let path = vec!["components".to_string(), a.to_string()];
+        assert_eq!(merge.conflicts.len(), 1);
+        assert_eq!(merge.conflicts[0].path, path);
+        assert!(merge.conflicts[0].ours.is_none());
+
+        merge.resolve(&[Resolution {
+            path,
+            choice: Choice::Ours,
+        }]);
+        assert!(merge.into_data().unwrap().components.is_empty());
+    }
+
+    #[test]
+    fn merges_added_entities_into_order() {
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let add = |data: &mut Data, uuid, name: &str| {
+            data.components.insert(
+                uuid,
+                Component::new(name.to_string(), None, BTreeMap::new(), BTreeMap::new()),
+            );
+            order::push(&mut data.component_order, &data.components, uuid);
+        };
+
+        let mut base = data(&[]);
+        add(&mut base, a, "soup");
+        let mut ours = data(&[]);
+        add(&mut ours, a, "soup");
+        add(&mut ours, b, "salad");
+        let mut theirs = data(&[]);
+        add(&mut theirs, a, "soup");
+        add(&mut theirs, c, "dessert");
+
+        let merge = Merge::new(base, ours, theirs).unwrap();
+        assert!(merge.conflicts.is_empty());
+        assert_eq!(merge.into_data().unwrap().component_order, vec![a, b, c]);
+    }
+
+    #[test]
+    fn merges_lists_as_ordered_sets() {
+        let list = |values: &[&str]| values.iter().map(|v| Value::from(*v)).collect::<Vec<_>>();
+        let base = list(&["a", "b", "c"]);
+
+        // removals and additions of both sides
+        assert_eq!(
+            merge_set(&base, &list(&["a", "c", "d"]), &list(&["b", "c", "e"])),
+            Some(list(&["c", "d", "e"]))
+        );
+        // the order of the side that changed it
+        assert_eq!(
+            merge_set(&base, &list(&["a", "b", "c", "d"]), &list(&["c", "b", "a"])),
+            Some(list(&["c", "b", "a", "d"]))
+        );
+        // different orders on both sides
+        assert_eq!(
+            merge_set(&base, &list(&["b", "a", "c"]), &list(&["c", "b", "a"])),
+            None
+        );
+        // lists that aren't sets
+        assert_eq!(merge_set(&[], &list(&["a", "a"]), &list(&["b"])), None);
+    }
+
+    #[test]
+    fn merges_display_orders() {
+        let (a, v1, v2, v3) = (
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+        );
+        let (m1, m2, m3) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let variant = |data: &mut Data, uuid, name: &str| {
+            let cmp = data.components.get_mut(&a).unwrap();
+            cmp.variants.insert(uuid, Variant::new(name.to_string()));
+            order::push(&mut cmp.variant_order, &cmp.variants, uuid);
+        };
+        let meal = |data: &mut Data, uuid, name: &str| {
+            let meal = Meal::new(name.to_string(), String::new(), BTreeMap::new());
+            data.meals.insert(uuid, meal);
+            order::push(&mut data.meal_order, &data.meals, uuid);
+        };
+
+        let mut base = data(&[(a, "soup")]);
+        variant(&mut base, v1, "tomato");
+        meal(&mut base, m1, "lunch");
+        meal(&mut base, m2, "dinner");
+
+        // ours adds a variant and moves the first meal to the end
+        let mut ours = data(&[(a, "soup")]);
+        variant(&mut ours, v1, "tomato");
+        variant(&mut ours, v2, "pea");
+        meal(&mut ours, m2, "dinner");
+        meal(&mut ours, m1, "lunch");
+
+        // theirs adds a variant and a meal
+        let mut theirs = data(&[(a, "soup")]);
+        variant(&mut theirs, v1, "tomato");
+        variant(&mut theirs, v3, "lentil");
+        meal(&mut theirs, m1, "lunch");
+        meal(&mut theirs, m2, "dinner");
+        meal(&mut theirs, m3, "snack");
+
+        let merge = Merge::new(base, ours, theirs).unwrap();
+        assert!(merge.conflicts.is_empty());
+
+        let merged = merge.into_data().unwrap();
+        assert_eq!(merged.components[&a].variant_order, vec![v1, v2, v3]);
+        assert_eq!(merged.meal_order, vec![m2, m1, m3]);
+    }
+}
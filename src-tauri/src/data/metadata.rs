@@ -0,0 +1,48 @@
+use chrono::{DateTime, Local};
+use ron::de::from_reader;
+use std::{fs::File, io::BufReader, path::Path};
+
+/// Struct to hold information about a dataset.
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Metadata {
+    /// A short, descriptive title.
+    pub title: String,
+    /// Free form notes about the dataset.
+    pub notes: String,
+    /// The time the dataset was created.
+    pub created: Option<DateTime<Local>>,
+    /// The user that created the dataset.
+    pub created_by: Option<String>,
+    /// The time the dataset was last saved.
+    pub saved: Option<DateTime<Local>>,
+    /// The user that last saved the dataset.
+    pub saved_by: Option<String>,
+}
+
+impl Metadata {
+    pub fn new(now: DateTime<Local>, user: Option<String>) -> Self {
+        Self {
+            created: Some(now),
+            created_by: user,
+            ..Self::default()
+        }
+    }
+
+    /// Read only the metadata of the dataset in `file`.
+    ///
+    /// Returns `None` if the file can't be read.
+    pub fn read(file: &Path) -> Option<Self> {
+        /// Only the metadata part of a dataset.
+        #[derive(serde::Deserialize)]
+        struct Header {
+            #[serde(default)]
+            meta: Metadata,
+        }
+
+        let file = File::open(file).ok()?;
+        from_reader::<_, Header>(BufReader::new(file))
+            .ok()
+            .map(|h| h.meta)
+    }
+}
@@ -1,8 +1,32 @@
+pub mod allergen;
 mod available_datasets;
+pub mod calendar;
+pub mod category;
 pub mod component;
+pub mod conflict;
+pub mod customer;
+pub mod customer_order;
+pub mod diet;
+pub mod diff;
+#[cfg(test)]
+pub mod fixtures;
+pub mod geo;
 pub mod meal;
+pub mod merge;
+mod metadata;
+pub mod nutrition;
+pub mod order;
+pub mod production;
+pub mod reference;
+pub mod route;
+pub mod selection;
+pub mod standing_order;
+pub mod template;
+pub mod tour;
 
 pub use available_datasets::AvailableDatasets;
+use chrono::{Datelike, NaiveDate};
+pub use metadata::Metadata;
 use std::collections::BTreeMap;
 use uuid::Uuid;
 
@@ -13,19 +37,75 @@ pub struct Data {
     pub year: i32,
     /// The month the data applies to.
     pub month: u32,
+    /// Information about the dataset itself.
+    #[serde(default)]
+    pub meta: Metadata,
+    /// The categories components can belong to.
+    #[serde(default)]
+    pub categories: BTreeMap<Uuid, category::Category>,
     /// The available meal components.
     pub components: BTreeMap<Uuid, component::Component>,
+    /// The display order of the components, see [`order`].
+    #[serde(default)]
+    pub component_order: Vec<Uuid>,
     /// The available meals.
     pub meals: BTreeMap<Uuid, meal::Meal>,
+    /// The display order of the meals, see [`order`].
+    #[serde(default)]
+    pub meal_order: Vec<Uuid>,
+    /// The meals offered on each day, in the order they are offered in.
+    ///
+    /// Days without meals are left out.
+    #[serde(default)]
+    pub menu: BTreeMap<NaiveDate, Vec<Uuid>>,
+    /// The meals customers ordered.
+    #[serde(default)]
+    pub orders: BTreeMap<Uuid, customer_order::Order>,
 }
 
 impl Data {
-    pub fn new(year: i32, month: u32) -> Self {
+    pub fn new(year: i32, month: u32, meta: Metadata) -> Self {
         Self {
             year,
             month,
+            meta,
+            categories: BTreeMap::new(),
             components: BTreeMap::new(),
+            component_order: Vec::new(),
             meals: BTreeMap::new(),
+            meal_order: Vec::new(),
+            menu: BTreeMap::new(),
+            orders: BTreeMap::new(),
         }
     }
+
+    /// Check whether `date` lies within the month the data applies to.
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        date.year() == self.year && date.month() == self.month
+    }
+
+    /// Get all days of the month the data applies to.
+    ///
+    /// Returns no days if the year and month of a loaded file don't form a valid date.
+    pub fn days(&self) -> impl Iterator<Item = NaiveDate> {
+        let (year, month) = (self.year, self.month);
+        std::iter::successors(NaiveDate::from_ymd_opt(year, month, 1), |d| d.succ_opt())
+            .take_while(move |d| d.year() == year && d.month() == month)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn days_of_month() {
+        let days: Vec<_> = Data::new(2024, 2, Metadata::default()).days().collect();
+        assert_eq!(days.len(), 29);
+        assert_eq!(days[0], NaiveDate::from_ymd(2024, 2, 1));
+        assert_eq!(days[28], NaiveDate::from_ymd(2024, 2, 29));
+
+        assert_eq!(Data::new(2024, 13, Metadata::default()).days().count(), 0);
+        assert_eq!(Data::new(2024, 0, Metadata::default()).days().count(), 0);
+    }
 }
@@ -0,0 +1,150 @@
+use super::{component::Component, meal::Meal};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// The grams of carbohydrates in a single bread unit.
+const GRAMS_PER_BREAD_UNIT: f64 = 12.0;
+
+/// Struct to hold the nutrition values of a single portion.
+///
+/// Values that aren't known are `None`.
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Nutrition {
+    /// The energy in kcal.
+    pub energy: Option<f64>,
+    /// The carbohydrates in g.
+    pub carbohydrates: Option<f64>,
+    /// The protein in g.
+    pub protein: Option<f64>,
+    /// The fat in g.
+    pub fat: Option<f64>,
+    /// The salt in g.
+    pub salt: Option<f64>,
+}
+
+impl Nutrition {
+    /// The names of all values, in the order of [`Self::values`].
+    const NAMES: [&'static str; 5] = ["energy", "carbohydrates", "protein", "fat", "salt"];
+
+    fn values(&self) -> [Option<f64>; 5] {
+        [
+            self.energy,
+            self.carbohydrates,
+            self.protein,
+            self.fat,
+            self.salt,
+        ]
+    }
+}
+
+/// Struct to hold the nutrition values of a whole meal.
+///
+/// A value is `None` if it is missing for at least one part of the meal, instead of counting the
+/// missing part as zero. `missing` lists the parts responsible.
+#[derive(Debug, Default, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NutritionTotals {
+    #[serde(flatten)]
+    pub values: Nutrition,
+    /// The carbohydrates in bread units.
+    pub bread_units: Option<f64>,
+    pub missing: Vec<MissingNutrition>,
+}
+
+impl NutritionTotals {
+    /// Create totals of nothing, with all values being zero.
+    pub fn zero() -> Self {
+        Self {
+            values: Nutrition {
+                energy: Some(0.0),
+                carbohydrates: Some(0.0),
+                protein: Some(0.0),
+                fat: Some(0.0),
+                salt: Some(0.0),
+            },
+            bread_units: Some(0.0),
+            missing: Vec::new(),
+        }
+    }
+
+    /// Get the totals of the variants a meal consists of.
+    ///
+    /// If the meal leaves the choice of a variant open, all values are missing, as the variants
+    /// may differ. See [`Selection::nutrition`] for the totals of a concrete configuration.
+    ///
+    /// [`Selection::nutrition`]: super::selection::Selection::nutrition
+    pub fn of_meal(meal: &Meal, components: &BTreeMap<Uuid, Component>) -> Self {
+        let mut totals = Self::zero();
+
+        for (k, mc) in meal.components.iter().filter(|(_, mc)| !mc.delete) {
+            let nutrition = components
+                .get(k)
+                .zip(mc.variant)
+                .and_then(|(cmp, v)| cmp.variants.get(&v))
+                .map(|v| &v.nutrition);
+
+            let unknown = Nutrition::default();
+            totals.add(nutrition.unwrap_or(&unknown), *k, mc.variant, None);
+        }
+
+        totals
+    }
+
+    /// Add the values of a single part of the meal.
+    ///
+    /// `option` is set if the part is an option of the variant.
+    pub fn add(
+        &mut self,
+        nutrition: &Nutrition,
+        component: Uuid,
+        variant: Option<Uuid>,
+        option: Option<Uuid>,
+    ) {
+        let mut totals = [
+            &mut self.values.energy,
+            &mut self.values.carbohydrates,
+            &mut self.values.protein,
+            &mut self.values.fat,
+            &mut self.values.salt,
+        ];
+
+        let mut missing = Vec::new();
+        for ((total, value), name) in totals
+            .iter_mut()
+            .zip(nutrition.values().iter())
+            .zip(Nutrition::NAMES.iter())
+        {
+            match value {
+                Some(value) => **total = total.map(|t| t + value),
+                None => {
+                    **total = None;
+                    missing.push(*name);
+                }
+            }
+        }
+
+        if !missing.is_empty() {
+            self.missing.push(MissingNutrition {
+                component,
+                variant,
+                option,
+                values: missing,
+            });
+        }
+
+        self.bread_units = self.values.carbohydrates.map(|x| x / GRAMS_PER_BREAD_UNIT);
+    }
+}
+
+/// Struct to hold the nutrition values missing for a single part of a meal.
+#[derive(Debug, serde::Serialize)]
+pub struct MissingNutrition {
+    pub component: Uuid,
+    /// The variant missing the values. `None` if the meal leaves the choice of variant open.
+    pub variant: Option<Uuid>,
+    /// The option missing the values, `None` if the variant is missing them.
+    pub option: Option<Uuid>,
+    /// The names of the missing values.
+    pub values: Vec<&'static str>,
+}
@@ -0,0 +1,81 @@
+//! Display order of the entries of a collection.
+//!
+//! The entries themselves are kept in `BTreeMap`s, so their order is stored separately as a list
+//! of uuids. That list may be incomplete, e.g. for datasets created before it existed. Entries
+//! missing from it are placed after all others, ordered by uuid.
+
+use std::collections::{BTreeMap, BTreeSet};
+use uuid::Uuid;
+
+/// A change of the position of an entry.
+#[derive(Clone, Copy, Debug, serde::Deserialize)]
+#[serde(tag = "to", rename_all = "camelCase")]
+pub enum Movement {
+    /// Swap the entry with the one before it.
+    Up,
+    /// Swap the entry with the one after it.
+    Down,
+    /// Move the entry to a specific position. Positions past the end move it to the end.
+    Index { index: usize },
+}
+
+/// Get the keys of `entries` in the order specified by `order`.
+pub fn sorted<T>(order: &[Uuid], entries: &BTreeMap<Uuid, T>) -> Vec<Uuid> {
+    let mut seen = BTreeSet::new();
+    let mut keys: Vec<Uuid> = order
+        .iter()
+        .filter(|k| entries.contains_key(k) && seen.insert(**k))
+        .copied()
+        .collect();
+
+    keys.extend(entries.keys().filter(|k| !seen.contains(k)));
+    keys
+}
+
+/// Place `uuid` after all other entries.
+pub fn push<T>(order: &mut Vec<Uuid>, entries: &BTreeMap<Uuid, T>, uuid: Uuid) {
+    let mut keys = sorted(order, entries);
+    keys.retain(|k| *k != uuid);
+    keys.push(uuid);
+
+    *order = keys;
+}
+
+/// Change the position of `uuid`.
+///
+/// Returns `false` if `uuid` isn't a key of `entries`.
+pub fn shift<T>(
+    order: &mut Vec<Uuid>,
+    entries: &BTreeMap<Uuid, T>,
+    uuid: Uuid,
+    movement: Movement,
+) -> bool {
+    let mut keys = sorted(order, entries);
+    if !relocate(&mut keys, uuid, movement) {
+        return false;
+    }
+
+    *order = keys;
+    true
+}
+
+/// Change the position of `uuid` within a complete list of keys.
+///
+/// Returns `false` if `uuid` isn't part of `keys`.
+pub fn relocate(keys: &mut Vec<Uuid>, uuid: Uuid, movement: Movement) -> bool {
+    let from = match keys.iter().position(|k| *k == uuid) {
+        Some(x) => x,
+        None => return false,
+    };
+
+    let last = keys.len() - 1;
+    let to = match movement {
+        Movement::Up => from.saturating_sub(1),
+        Movement::Down => (from + 1).min(last),
+        Movement::Index { index } => index.min(last),
+    };
+
+    let k = keys.remove(from);
+    keys.insert(to, k);
+    true
+}
@@ -0,0 +1,216 @@
+use super::{customer::Customer, diet::Diet, Data};
+use chrono::NaiveDate;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+};
+use uuid::Uuid;
+
+/// Struct to hold the number of portions the kitchen has to prepare of every variant and option.
+#[derive(Clone, Debug, Default, serde::Serialize)]
+pub struct ProductionSheet {
+    /// The counts of the variants and options, by component.
+    pub components: BTreeMap<Uuid, ComponentCount>,
+    /// The orders that couldn't be counted, because their meal, variants or options don't exist
+    /// anymore or don't fit together.
+    pub invalid: Vec<Uuid>,
+}
+
+/// The portions of a single component.
+#[derive(Clone, Debug, Default, serde::Serialize)]
+pub struct ComponentCount {
+    pub variants: BTreeMap<Uuid, Count>,
+    pub options: BTreeMap<Uuid, Count>,
+}
+
+/// A number of portions, together with how many of them go to customers with specific diets.
+#[derive(Clone, Debug, Default, serde::Serialize)]
+pub struct Count {
+    pub total: u64,
+    /// The portions for customers with a diet, by diet.
+    ///
+    /// Portions for customers with several diets are counted for each of them.
+    pub diets: BTreeMap<Uuid, u64>,
+}
+
+/// The texts of a printed production sheet that aren't taken from the data.
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct SheetLabels {
+    pub title: String,
+    pub total: String,
+}
+
+impl Count {
+    fn add(&mut self, quantity: u32, diets: &BTreeSet<Uuid>) {
+        let quantity = u64::from(quantity);
+
+        self.total = self.total.saturating_add(quantity);
+        for diet in diets {
+            let count = self.diets.entry(*diet).or_default();
+            *count = count.saturating_add(quantity);
+        }
+    }
+}
+
+impl ProductionSheet {
+    /// Add up the orders that aren't cancelled from `from` to `to`, both included.
+    ///
+    /// Diets that were deleted from `diets` are ignored.
+    pub fn new(
+        data: &Data,
+        customers: &BTreeMap<Uuid, Customer>,
+        diets: &BTreeMap<Uuid, Diet>,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Self {
+        let mut sheet = Self::default();
+        let none = BTreeSet::new();
+
+        let orders = data
+            .orders
+            .iter()
+            .filter(|(_, o)| !o.cancelled && from <= o.date && o.date <= to);
+        for (uuid, order) in orders {
+            let variants = match order.selection.resolve(data) {
+                Ok(x) => x,
+                Err(_) => {
+                    sheet.invalid.push(*uuid);
+                    continue;
+                }
+            };
+
+            let customer_diets: BTreeSet<_> = customers
+                .get(&order.customer)
+                .map_or(&none, |c| &c.diets)
+                .iter()
+                .filter(|d| diets.contains_key(d))
+                .copied()
+                .collect();
+
+            for (component, variant) in variants {
+                let count = sheet.components.entry(component).or_default();
+                count
+                    .variants
+                    .entry(variant)
+                    .or_default()
+                    .add(order.quantity, &customer_diets);
+
+                for option in order
+                    .selection
+                    .options
+                    .get(&component)
+                    .into_iter()
+                    .flatten()
+                {
+                    count
+                        .options
+                        .entry(*option)
+                        .or_default()
+                        .add(order.quantity, &customer_diets);
+                }
+            }
+        }
+
+        sheet
+    }
+
+    /// Render the sheet as a printable HTML document.
+    ///
+    /// Components are sorted by name, their variants and options keep the order of the
+    /// component. There is a column for every diet a portion is counted for.
+    pub fn to_html(
+        &self,
+        data: &Data,
+        diets: &BTreeMap<Uuid, Diet>,
+        labels: &SheetLabels,
+    ) -> String {
+        let columns: BTreeSet<_> = self
+            .components
+            .values()
+            .flat_map(|c| c.variants.values().chain(c.options.values()))
+            .flat_map(|c| c.diets.keys())
+            .filter_map(|d| diets.get(d).map(|x| (x.name.as_str(), *d)))
+            .collect();
+
+        let mut components: Vec<_> = self
+            .components
+            .iter()
+            .filter_map(|(uuid, count)| data.components.get(uuid).map(|c| (c, count)))
+            .collect();
+        components.sort_by(|(a, _), (b, _)| a.name.cmp(&b.name));
+
+        let mut html = String::new();
+        let title = escape(&labels.title);
+        // writing to a string can't fail
+        let _ = write!(
+            html,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+             <style>\ntable {{ border-collapse: collapse; }}\n\
+             th, td {{ border: 1px solid black; padding: 2px 8px; }}\n\
+             td.count {{ text-align: right; }}\n</style>\n</head>\n<body>\n<h1>{}</h1>\n\
+             <table>\n<tr><th></th><th>{}</th>",
+            title,
+            title,
+            escape(&labels.total)
+        );
+        for (name, _) in &columns {
+            let _ = write!(html, "<th>{}</th>", escape(name));
+        }
+        html.push_str("</tr>\n");
+
+        for (component, count) in components {
+            let _ = writeln!(
+                html,
+                "<tr><th colspan=\"{}\">{}</th></tr>",
+                columns.len() + 2,
+                escape(&component.name)
+            );
+
+            let variants = ordered(&component.variant_order, &count.variants)
+                .map(|(uuid, c)| (component.variants.get(uuid).map(|v| &v.name), c, ""));
+            let options = ordered(&component.option_order, &count.options)
+                .map(|(uuid, c)| (component.options.get(uuid).map(|o| &o.name), c, "+ "));
+            for (name, c, prefix) in variants.chain(options) {
+                let name = name.map_or("", String::as_str);
+                let _ = write!(
+                    html,
+                    "<tr><td>{}{}</td><td class=\"count\">{}</td>",
+                    prefix,
+                    escape(name),
+                    c.total
+                );
+                for (_, diet) in &columns {
+                    match c.diets.get(diet) {
+                        Some(n) => {
+                            let _ = write!(html, "<td class=\"count\">{}</td>", n);
+                        }
+                        None => html.push_str("<td></td>"),
+                    }
+                }
+                html.push_str("</tr>\n");
+            }
+        }
+
+        html.push_str("</table>\n</body>\n</html>\n");
+        html
+    }
+}
+
+/// Iterate over `counts` in the order given by `order`, followed by the remaining entries.
+fn ordered<'a>(
+    order: &'a [Uuid],
+    counts: &'a BTreeMap<Uuid, Count>,
+) -> impl Iterator<Item = (&'a Uuid, &'a Count)> {
+    order
+        .iter()
+        .filter_map(move |uuid| counts.get_key_value(uuid))
+        .chain(counts.iter().filter(move |(uuid, _)| !order.contains(uuid)))
+}
+
+/// Escape the characters of `text` that have a special meaning in HTML.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
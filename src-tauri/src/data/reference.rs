@@ -0,0 +1,87 @@
+//! Lookup of entities that other entities refer to.
+//!
+//! Entities marked as deleted are treated as if they didn't exist, so nothing new can refer to
+//! them.
+
+use super::{
+    component::{Component, Option, Variant},
+    meal::Meal,
+    Data,
+};
+use crate::cmd::error::{CmdError, ErrorPayload};
+use uuid::Uuid;
+
+/// Error describing a reference to an entity that doesn't exist or is marked as deleted.
+#[derive(Debug, thiserror::Error)]
+pub enum ReferenceError {
+    #[error("error.references.component_not_found")]
+    ComponentNotFound { component: Uuid },
+    #[error("error.references.meal_not_found")]
+    MealNotFound { meal: Uuid },
+    #[error("error.references.option_not_found")]
+    OptionNotFound { component: Uuid, option: Uuid },
+    #[error("error.references.variant_not_found")]
+    VariantNotFound { component: Uuid, variant: Uuid },
+}
+
+impl CmdError for ReferenceError {
+    fn payload(&self) -> ErrorPayload {
+        match self {
+            Self::ComponentNotFound { component } => ErrorPayload::new(
+                "COMPONENT_NOT_FOUND",
+                "error.references.component_not_found",
+            )
+            .param("component", component),
+            Self::MealNotFound { meal } => {
+                ErrorPayload::new("MEAL_NOT_FOUND", "error.references.meal_not_found")
+                    .param("meal", meal)
+            }
+            Self::OptionNotFound { component, option } => {
+                ErrorPayload::new("OPTION_NOT_FOUND", "error.references.option_not_found")
+                    .param("component", component)
+                    .param("option", option)
+            }
+            Self::VariantNotFound { component, variant } => {
+                ErrorPayload::new("VARIANT_NOT_FOUND", "error.references.variant_not_found")
+                    .param("component", component)
+                    .param("variant", variant)
+            }
+        }
+    }
+}
+
+impl Data {
+    /// Get a component that isn't deleted.
+    pub fn component(&self, component: Uuid) -> Result<&Component, ReferenceError> {
+        self.components
+            .get(&component)
+            .filter(|c| !c.delete)
+            .ok_or(ReferenceError::ComponentNotFound { component })
+    }
+
+    /// Get a meal that isn't deleted.
+    pub fn meal(&self, meal: Uuid) -> Result<&Meal, ReferenceError> {
+        self.meals
+            .get(&meal)
+            .filter(|m| !m.delete)
+            .ok_or(ReferenceError::MealNotFound { meal })
+    }
+
+    /// Get an option that isn't deleted, of a component that isn't deleted.
+    pub fn option(&self, component: Uuid, option: Uuid) -> Result<&Option, ReferenceError> {
+        self.component(component)?
+            .options
+            .get(&option)
+            .filter(|o| !o.delete)
+            .ok_or(ReferenceError::OptionNotFound { component, option })
+    }
+
+    /// Get a variant that isn't deleted, of a component that isn't deleted.
+    pub fn variant(&self, component: Uuid, variant: Uuid) -> Result<&Variant, ReferenceError> {
+        self.component(component)?
+            .variants
+            .get(&variant)
+            .filter(|v| !v.delete)
+            .ok_or(ReferenceError::VariantNotFound { component, variant })
+    }
+}
@@ -0,0 +1,78 @@
+use super::{
+    customer::{Address, Customer},
+    customer_order::Order,
+    geo::Coordinates,
+    Data,
+};
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// Struct to hold a fixed tour a driver delivers meals on.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct Route {
+    pub name: String,
+    #[serde(default)]
+    pub driver: String,
+    #[serde(default)]
+    pub vehicle: String,
+    /// Where the route starts and ends, used to plan the order of the stops.
+    #[serde(default)]
+    pub depot: Option<Coordinates>,
+    /// The customers on the route, in the order they are visited.
+    ///
+    /// Every customer is part of at most one route.
+    #[serde(default)]
+    pub stops: Vec<Uuid>,
+}
+
+/// A stop of a route on a specific day.
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct Stop {
+    pub customer: Uuid,
+    pub name: String,
+    pub address: Address,
+    pub phone: String,
+    /// The delivery notes of the customer.
+    pub notes: String,
+    /// The orders delivered at the stop.
+    pub orders: BTreeMap<Uuid, Order>,
+}
+
+impl Route {
+    /// Get the stops of the route that meals are delivered to on `date`, in the order they are
+    /// visited.
+    ///
+    /// Customers without orders on `date` that aren't cancelled are left out.
+    pub fn sheet(
+        &self,
+        data: &Data,
+        customers: &BTreeMap<Uuid, Customer>,
+        date: NaiveDate,
+    ) -> Vec<Stop> {
+        self.stops
+            .iter()
+            .filter_map(|uuid| {
+                let customer = customers.get(uuid)?;
+                let orders: BTreeMap<_, _> = data
+                    .orders
+                    .iter()
+                    .filter(|(_, o)| o.customer == *uuid && o.date == date && !o.cancelled)
+                    .map(|(k, o)| (*k, o.clone()))
+                    .collect();
+                if orders.is_empty() {
+                    return None;
+                }
+
+                Some(Stop {
+                    customer: *uuid,
+                    name: customer.name.clone(),
+                    address: customer.address.clone(),
+                    phone: customer.phone.clone(),
+                    notes: customer.notes.clone(),
+                    orders,
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,309 @@
+use super::{
+    allergen::{Allergen, Declaration},
+    nutrition::NutritionTotals,
+    reference::ReferenceError,
+    Data,
+};
+use crate::cmd::error::{CmdError, ErrorPayload};
+use std::collections::{BTreeMap, BTreeSet};
+use uuid::Uuid;
+
+/// Struct to hold a concrete configuration of a meal, as it is served.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Selection {
+    pub meal: Uuid,
+    /// The chosen variants of the components the meal leaves open, by component.
+    #[serde(default)]
+    pub variants: BTreeMap<Uuid, Uuid>,
+    /// The chosen options, by component.
+    #[serde(default)]
+    pub options: BTreeMap<Uuid, BTreeSet<Uuid>>,
+}
+
+/// Error describing why a [`Selection`] doesn't fit the meal.
+#[derive(Debug, thiserror::Error)]
+pub enum SelectionError {
+    #[error("error.selection.component_not_in_meal")]
+    ComponentNotInMeal { component: Uuid },
+    #[error("error.selection.price_missing")]
+    PriceMissing { meal: Uuid },
+    #[error("error.selection.price_out_of_range")]
+    PriceOutOfRange { meal: Uuid },
+    #[error("error.selection.variant_fixed")]
+    VariantFixed { component: Uuid, variant: Uuid },
+    #[error(transparent)]
+    ReferenceError(#[from] ReferenceError),
+    #[error("error.selection.variant_missing")]
+    VariantMissing { component: Uuid },
+}
+
+impl CmdError for SelectionError {
+    fn payload(&self) -> ErrorPayload {
+        match self {
+            Self::ComponentNotInMeal { component } => ErrorPayload::new(
+                "COMPONENT_NOT_IN_MEAL",
+                "error.selection.component_not_in_meal",
+            )
+            .param("component", component),
+            Self::PriceMissing { meal } => {
+                ErrorPayload::new("PRICE_MISSING", "error.selection.price_missing")
+                    .param("meal", meal)
+            }
+            Self::PriceOutOfRange { meal } => {
+                ErrorPayload::new("PRICE_OUT_OF_RANGE", "error.selection.price_out_of_range")
+                    .param("meal", meal)
+            }
+            Self::VariantFixed { component, variant } => {
+                ErrorPayload::new("VARIANT_FIXED", "error.selection.variant_fixed")
+                    .param("component", component)
+                    .param("variant", variant)
+            }
+            Self::ReferenceError(e) => e.payload(),
+            Self::VariantMissing { component } => {
+                ErrorPayload::new("VARIANT_MISSING", "error.selection.variant_missing")
+                    .param("component", component)
+            }
+        }
+    }
+}
+
+impl Selection {
+    /// Check that the selection fits the meal, and get the variant of every component.
+    ///
+    /// Every component the meal leaves open needs a chosen variant. Components the meal fixes a
+    /// variant for may only be chosen with that same variant. The meal and all variants and
+    /// options have to exist and mustn't be deleted.
+    pub fn resolve(&self, data: &Data) -> Result<BTreeMap<Uuid, Uuid>, SelectionError> {
+        let meal = data.meal(self.meal)?;
+        let parts: BTreeMap<_, _> = meal
+            .components
+            .iter()
+            .filter(|(_, mc)| !mc.delete)
+            .collect();
+
+        for component in self.variants.keys().chain(self.options.keys()) {
+            if !parts.contains_key(component) {
+                return Err(SelectionError::ComponentNotInMeal {
+                    component: *component,
+                });
+            }
+        }
+
+        let mut variants = BTreeMap::new();
+        for (component, mc) in parts {
+            let variant = match (mc.variant, self.variants.get(component)) {
+                (Some(fixed), Some(chosen)) if fixed != *chosen => {
+                    return Err(SelectionError::VariantFixed {
+                        component: *component,
+                        variant: *chosen,
+                    })
+                }
+                (Some(fixed), _) => fixed,
+                (None, Some(chosen)) => *chosen,
+                (None, None) => {
+                    return Err(SelectionError::VariantMissing {
+                        component: *component,
+                    })
+                }
+            };
+            data.variant(*component, variant)?;
+            for option in self.options.get(component).into_iter().flatten() {
+                data.option(*component, *option)?;
+            }
+
+            variants.insert(*component, variant);
+        }
+
+        Ok(variants)
+    }
+
+    /// Get the price of the selection in cents.
+    ///
+    /// This is the base price of the meal plus the surcharges of all variants and options.
+    pub fn price(&self, data: &Data) -> Result<i64, SelectionError> {
+        let variants = self.resolve(data)?;
+        let mut price = data.meals[&self.meal]
+            .price
+            .ok_or(SelectionError::PriceMissing { meal: self.meal })?;
+        let mut add = |surcharge| {
+            price = price
+                .checked_add(surcharge)
+                .ok_or(SelectionError::PriceOutOfRange { meal: self.meal })?;
+            Ok::<_, SelectionError>(())
+        };
+
+        for (component, variant) in variants {
+            let cmp = &data.components[&component];
+            add(cmp.variants[&variant].surcharge)?;
+
+            for option in self.options.get(&component).into_iter().flatten() {
+                add(cmp.options[option].surcharge)?;
+            }
+        }
+
+        Ok(price)
+    }
+
+    /// Get the combined allergens and additives of the chosen variants and options.
+    pub fn declaration(&self, data: &Data) -> Result<Declaration, SelectionError> {
+        let mut declaration = Declaration::default();
+        for (allergens, additives, _) in self.parts(data)? {
+            declaration.add(allergens, additives);
+        }
+
+        Ok(declaration)
+    }
+
+    /// Get the combined nutrition values of the chosen variants and options.
+    pub fn nutrition(&self, data: &Data) -> Result<NutritionTotals, SelectionError> {
+        let mut totals = NutritionTotals::zero();
+        for (component, variant) in self.resolve(data)? {
+            let v = data.variant(component, variant)?;
+            totals.add(&v.nutrition, component, Some(variant), None);
+
+            for option in self.options.get(&component).into_iter().flatten() {
+                let o = data.option(component, *option)?;
+                totals.add(&o.nutrition, component, Some(variant), Some(*option));
+            }
+        }
+
+        Ok(totals)
+    }
+
+    /// Get the diets the selection is suitable for.
+    ///
+    /// The selection is suitable for a diet if all chosen variants and options are.
+    pub fn diets(&self, data: &Data) -> Result<BTreeSet<Uuid>, SelectionError> {
+        let mut diets: Option<BTreeSet<Uuid>> = None;
+        for (_, _, fitting) in self.parts(data)? {
+            diets = Some(match diets {
+                Some(d) => d.intersection(fitting).copied().collect(),
+                None => fitting.clone(),
+            });
+        }
+
+        Ok(diets.unwrap_or_default())
+    }
+
+    /// Get the allergens, additives and diets of all chosen variants and options.
+    fn parts<'a>(
+        &self,
+        data: &'a Data,
+    ) -> Result<
+        Vec<(
+            &'a BTreeSet<Allergen>,
+            &'a BTreeSet<u32>,
+            &'a BTreeSet<Uuid>,
+        )>,
+        SelectionError,
+    > {
+        let mut parts = Vec::new();
+        for (component, variant) in self.resolve(data)? {
+            let v = data.variant(component, variant)?;
+            parts.push((&v.allergens, &v.additives, &v.diets));
+
+            for option in self.options.get(&component).into_iter().flatten() {
+                let o = data.option(component, *option)?;
+                parts.push((&o.allergens, &o.additives, &o.diets));
+            }
+        }
+
+        Ok(parts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{
+        component::{Option, Variant},
+        fixtures::data,
+        meal::Meal,
+        nutrition::Nutrition,
+    };
+
+    #[test]
+    fn nutrition_includes_options() {
+        let (soup, tomato, cream, meal) = (
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+        );
+        let nutrition = |energy, carbohydrates| Nutrition {
+            energy: Some(energy),
+            carbohydrates: Some(carbohydrates),
+            protein: Some(1.0),
+            fat: Some(1.0),
+            salt: Some(1.0),
+        };
+
+        let mut data = data(&[(soup, "soup")]);
+        let cmp = data.components.get_mut(&soup).unwrap();
+        let mut variant = Variant::new("tomato".to_string());
+        variant.nutrition = nutrition(100.0, 12.0);
+        cmp.variants.insert(tomato, variant);
+        let mut option = Option::new("cream".to_string());
+        option.nutrition = nutrition(50.0, 12.0);
+        option.nutrition.salt = None;
+        cmp.options.insert(cream, option);
+
+        let components = vec![(soup, None)].into_iter().collect();
+        data.meals.insert(
+            meal,
+            Meal::new("lunch".to_string(), String::new(), components),
+        );
+
+        let mut selection = Selection {
+            meal,
+            variants: vec![(soup, tomato)].into_iter().collect(),
+            options: BTreeMap::new(),
+        };
+        let totals = selection.nutrition(&data).unwrap();
+        assert_eq!(totals.values.energy, Some(100.0));
+        assert_eq!(totals.bread_units, Some(1.0));
+        assert!(totals.missing.is_empty());
+
+        selection
+            .options
+            .insert(soup, vec![cream].into_iter().collect());
+        let totals = selection.nutrition(&data).unwrap();
+        assert_eq!(totals.values.energy, Some(150.0));
+        assert_eq!(totals.bread_units, Some(2.0));
+        assert_eq!(totals.values.salt, None);
+        assert_eq!(totals.missing.len(), 1);
+        assert_eq!(totals.missing[0].option, Some(cream));
+        assert_eq!(totals.missing[0].values, vec!["salt"]);
+    }
+
+    #[test]
+    fn price_out_of_range() {
+        let (soup, tomato, meal) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let mut data = data(&[(soup, "soup")]);
+        let mut variant = Variant::new("tomato".to_string());
+        variant.surcharge = 50;
+        data.components
+            .get_mut(&soup)
+            .unwrap()
+            .variants
+            .insert(tomato, variant);
+
+        let components = vec![(soup, Some(tomato))].into_iter().collect();
+        let mut m = Meal::new("lunch".to_string(), String::new(), components);
+        m.price = Some(500);
+        data.meals.insert(meal, m);
+
+        let selection = Selection {
+            meal,
+            variants: BTreeMap::new(),
+            options: BTreeMap::new(),
+        };
+        assert_eq!(selection.price(&data).unwrap(), 550);
+
+        data.meals.get_mut(&meal).unwrap().price = Some(i64::MAX);
+        assert!(matches!(
+            selection.price(&data),
+            Err(SelectionError::PriceOutOfRange { .. })
+        ));
+    }
+}
@@ -0,0 +1,85 @@
+use super::{selection::Selection, Data};
+use chrono::{Datelike, NaiveDate, Weekday};
+use std::collections::{BTreeMap, BTreeSet};
+use uuid::Uuid;
+
+/// Struct to hold what a customer gets every week, unless they order something else.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct StandingOrder {
+    pub customer: Uuid,
+    /// The weekdays the customer gets meals on.
+    pub weekdays: Vec<Weekday>,
+    pub choice: MealChoice,
+    /// The chosen variants of the components the meal leaves open, by component.
+    ///
+    /// Entries for components the meal doesn't leave open are ignored.
+    #[serde(default)]
+    pub variants: BTreeMap<Uuid, Uuid>,
+    /// The chosen options, by component.
+    ///
+    /// Entries for components the meal doesn't contain are ignored.
+    #[serde(default)]
+    pub options: BTreeMap<Uuid, BTreeSet<Uuid>>,
+    /// The number of portions.
+    pub quantity: u32,
+    /// The first day the standing order applies to.
+    #[serde(default)]
+    pub from: Option<NaiveDate>,
+    /// The last day the standing order applies to.
+    #[serde(default)]
+    pub to: Option<NaiveDate>,
+}
+
+/// The meal a standing order asks for.
+#[derive(Clone, Copy, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MealChoice {
+    /// Always the same meal.
+    Meal(Uuid),
+    /// The meal at a position of the daily menu, starting at 0.
+    Line(usize),
+}
+
+impl StandingOrder {
+    /// Check whether the standing order applies to `date`.
+    pub fn applies(&self, date: NaiveDate) -> bool {
+        self.weekdays.contains(&date.weekday())
+            && self.from.map_or(true, |f| f <= date)
+            && self.to.map_or(true, |t| date <= t)
+    }
+
+    /// Get the selection the standing order asks for on `date`.
+    ///
+    /// Returns `None` if the daily menu has no meal at the chosen line.
+    pub fn selection(&self, data: &Data, date: NaiveDate) -> Option<Selection> {
+        let meal = match self.choice {
+            MealChoice::Meal(meal) => meal,
+            MealChoice::Line(line) => *data
+                .menu
+                .get(&date)?
+                .iter()
+                .filter(|m| data.meal(**m).is_ok())
+                .nth(line)?,
+        };
+
+        let parts = data.meal(meal).ok().map(|m| &m.components);
+        let contains = |k: &Uuid| parts.map_or(false, |p| p.get(k).map_or(false, |mc| !mc.delete));
+        let open = |k: &Uuid| contains(k) && parts.map_or(false, |p| p[k].variant.is_none());
+
+        Some(Selection {
+            meal,
+            variants: self
+                .variants
+                .iter()
+                .filter(|(k, _)| open(k))
+                .map(|(k, v)| (*k, *v))
+                .collect(),
+            options: self
+                .options
+                .iter()
+                .filter(|(k, _)| contains(k))
+                .map(|(k, o)| (*k, o.clone()))
+                .collect(),
+        })
+    }
+}
@@ -0,0 +1,65 @@
+use super::{category::Category, component::Component, meal::Meal, Data};
+use chrono::{DateTime, Local};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// Struct to hold a reusable set of categories, components and meals.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct Template {
+    pub name: String,
+    /// The time the template was saved.
+    pub created: DateTime<Local>,
+    #[serde(default)]
+    pub categories: BTreeMap<Uuid, Category>,
+    pub components: BTreeMap<Uuid, Component>,
+    #[serde(default)]
+    pub component_order: Vec<Uuid>,
+    pub meals: BTreeMap<Uuid, Meal>,
+    #[serde(default)]
+    pub meal_order: Vec<Uuid>,
+}
+
+impl Template {
+    /// Create a template from the content of a dataset.
+    pub fn from_data(name: String, created: DateTime<Local>, data: &Data) -> Self {
+        Self {
+            name,
+            created,
+            categories: data.categories.clone(),
+            components: data.components.clone(),
+            component_order: data.component_order.clone(),
+            meals: data.meals.clone(),
+            meal_order: data.meal_order.clone(),
+        }
+    }
+
+    /// Copy the content of the template into a dataset.
+    pub fn apply(&self, data: &mut Data) {
+        data.categories = self.categories.clone();
+        data.components = self.components.clone();
+        data.component_order = self.component_order.clone();
+        data.meals = self.meals.clone();
+        data.meal_order = self.meal_order.clone();
+    }
+}
+
+/// Struct to hold an overview of a template.
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateSummary {
+    pub name: String,
+    pub created: DateTime<Local>,
+    pub component_count: usize,
+    pub meal_count: usize,
+}
+
+impl From<&Template> for TemplateSummary {
+    fn from(template: &Template) -> Self {
+        Self {
+            name: template.name.clone(),
+            created: template.created,
+            component_count: template.components.len(),
+            meal_count: template.meals.len(),
+        }
+    }
+}
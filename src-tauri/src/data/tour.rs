@@ -0,0 +1,458 @@
+//! Planning the order the stops of a route are visited in.
+//!
+//! Finding the best order is the travelling salesman problem, so the order is only approximated:
+//! starting from both the current order and the one given by always driving to the nearest
+//! remaining stop, segments of the tour are reversed as long as that makes it better (2-opt).
+//! Tours are compared by how late they are for the time windows of the customers first, and by
+//! their duration second.
+
+use super::{
+    customer::Customer,
+    geo::{Coordinates, DistanceTable},
+    route::Route,
+};
+use crate::cmd::error::{CmdError, ErrorPayload};
+use chrono::{Duration, NaiveTime, Timelike};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// Differences smaller than this are ignored when comparing tours, to avoid endless swapping.
+const EPSILON: f64 = 1e-6;
+
+/// Where the distances between the stops of a route come from.
+#[derive(Clone, Copy, Debug, serde::Deserialize)]
+#[serde(tag = "source", rename_all = "camelCase")]
+pub enum DistanceSource {
+    /// The straight-line distances between the coordinates of the depot and the customers.
+    Straight {
+        /// The average speed in km/h, used to estimate travel times.
+        speed: f64,
+    },
+    /// The imported road distances.
+    Table,
+}
+
+/// Error describing why the order of the stops of a route can't be planned.
+#[derive(Debug, thiserror::Error)]
+pub enum TourError {
+    #[error("error.tours.customer_not_found")]
+    CustomerNotFound { customer: Uuid },
+    #[error("error.tours.invalid_speed")]
+    InvalidSpeed,
+    #[error("error.tours.missing_coordinates")]
+    MissingCoordinates { customer: Uuid },
+    #[error("error.tours.missing_distance")]
+    MissingDistance { from: u32, to: u32 },
+    #[error("error.tours.no_depot")]
+    NoDepot,
+    #[error("error.tours.past_midnight")]
+    PastMidnight,
+    #[error("error.tours.too_long")]
+    TooLong,
+}
+
+impl CmdError for TourError {
+    fn payload(&self) -> ErrorPayload {
+        match self {
+            Self::CustomerNotFound { customer } => {
+                ErrorPayload::new("CUSTOMER_NOT_FOUND", "error.tours.customer_not_found")
+                    .param("customer", customer)
+            }
+            Self::InvalidSpeed => ErrorPayload::new("INVALID_SPEED", "error.tours.invalid_speed"),
+            Self::MissingCoordinates { customer } => {
+                ErrorPayload::new("MISSING_COORDINATES", "error.tours.missing_coordinates")
+                    .param("customer", customer)
+            }
+            Self::MissingDistance { from, to } => {
+                ErrorPayload::new("MISSING_DISTANCE", "error.tours.missing_distance")
+                    .param("from", *from)
+                    .param("to", *to)
+            }
+            Self::NoDepot => ErrorPayload::new("NO_DEPOT", "error.tours.no_depot"),
+            Self::PastMidnight => ErrorPayload::new("PAST_MIDNIGHT", "error.tours.past_midnight"),
+            Self::TooLong => ErrorPayload::new("TOUR_TOO_LONG", "error.tours.too_long"),
+        }
+    }
+}
+
+/// Struct to hold everything needed to plan the order of the stops of a route.
+///
+/// Stops are referred to by their index in the current order. In the matrices, index 0 is the
+/// depot and the stops follow it.
+pub struct Tour {
+    stops: Vec<Uuid>,
+    start: NaiveTime,
+    /// The distances in metres.
+    distance: Vec<Vec<f64>>,
+    /// The travel times in seconds.
+    duration: Vec<Vec<f64>>,
+    /// The time windows of the stops, in seconds after the start.
+    ///
+    /// Windows before the start are negative, so those stops are always late.
+    windows: Vec<Option<(f64, f64)>>,
+    /// The time spent at every stop in seconds.
+    service: f64,
+}
+
+/// What driving the stops of a route in a specific order is expected to be like.
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct Summary {
+    pub stops: Vec<Uuid>,
+    /// The total distance in metres, including the way back to the depot.
+    pub distance: f64,
+    /// The total time in seconds, including the way back to the depot.
+    pub duration: i64,
+    /// The times meals are delivered at, in the order of the stops.
+    pub arrivals: Vec<NaiveTime>,
+    /// The customers meals are delivered to after the end of their time window.
+    pub late: Vec<Uuid>,
+}
+
+struct Estimate {
+    distance: f64,
+    duration: f64,
+    /// The sum of the times meals are delivered after the end of time windows.
+    late: f64,
+    arrivals: Vec<f64>,
+}
+
+impl Estimate {
+    fn is_better(&self, other: &Self) -> bool {
+        if (self.late - other.late).abs() > EPSILON {
+            self.late < other.late
+        } else {
+            self.duration < other.duration - EPSILON
+        }
+    }
+}
+
+impl Tour {
+    /// Collect the distances between the depot and the stops of `route`.
+    ///
+    /// The route starts at `start` and `service` seconds are spent at every stop.
+    pub fn new(
+        route: &Route,
+        customers: &BTreeMap<Uuid, Customer>,
+        source: DistanceSource,
+        table: &DistanceTable,
+        start: NaiveTime,
+        service: f64,
+    ) -> Result<Self, TourError> {
+        let stops: Vec<_> = route
+            .stops
+            .iter()
+            .map(|uuid| {
+                customers
+                    .get(uuid)
+                    .map(|c| (*uuid, c))
+                    .ok_or(TourError::CustomerNotFound { customer: *uuid })
+            })
+            .collect::<Result<_, _>>()?;
+
+        let (distance, duration) = match source {
+            DistanceSource::Straight { speed } => {
+                if !speed.is_finite() || speed <= 0.0 {
+                    return Err(TourError::InvalidSpeed);
+                }
+
+                let mut places = vec![route.depot.ok_or(TourError::NoDepot)?];
+                for (uuid, c) in &stops {
+                    places.push(
+                        c.coordinates
+                            .ok_or(TourError::MissingCoordinates { customer: *uuid })?,
+                    );
+                }
+
+                let distance = matrix(&places, |a: &Coordinates, b| Ok(a.distance(*b)))?;
+                let duration: Vec<Vec<_>> = distance
+                    .iter()
+                    .map(|row| row.iter().map(|d| d / (speed / 3.6)).collect())
+                    .collect();
+                // speeds close to zero give travel times that can't be represented
+                if duration.iter().flatten().any(|d| !d.is_finite()) {
+                    return Err(TourError::InvalidSpeed);
+                }
+                (distance, duration)
+            }
+            DistanceSource::Table => {
+                let mut numbers = vec![0];
+                numbers.extend(stops.iter().map(|(_, c)| c.number));
+
+                let leg = |from: &u32, to: &u32| {
+                    table.get(*from, *to).ok_or(TourError::MissingDistance {
+                        from: *from,
+                        to: *to,
+                    })
+                };
+                (
+                    matrix(&numbers, |a, b| leg(a, b).map(|l| l.distance))?,
+                    matrix(&numbers, |a, b| leg(a, b).map(|l| l.duration))?,
+                )
+            }
+        };
+
+        let seconds = |t: NaiveTime| (t - start).num_seconds() as f64;
+        Ok(Self {
+            windows: stops
+                .iter()
+                .map(|(_, c)| c.window.map(|w| (seconds(w.from), seconds(w.to))))
+                .collect(),
+            stops: stops.into_iter().map(|(uuid, _)| uuid).collect(),
+            start,
+            distance,
+            duration,
+            service,
+        })
+    }
+
+    /// Get the best order of the stops that was found, as indices into the current order.
+    ///
+    /// Returns the current order if no better one was found.
+    pub fn optimize(&self) -> Vec<usize> {
+        let current: Vec<_> = (0..self.stops.len()).collect();
+        let mut best = current.clone();
+        let mut best_estimate = self.estimate(&best);
+
+        for order in vec![current, self.nearest_neighbour()] {
+            let order = self.two_opt(order);
+            let estimate = self.estimate(&order);
+            if estimate.is_better(&best_estimate) {
+                best = order;
+                best_estimate = estimate;
+            }
+        }
+
+        best
+    }
+
+    /// Describe visiting the stops in `order`, given as indices into the current order.
+    ///
+    /// Fails with [`TourError::TooLong`] if the times can't be represented, and with
+    /// [`TourError::PastMidnight`] if the tour doesn't end on the day it started.
+    pub fn summary(&self, order: &[usize]) -> Result<Summary, TourError> {
+        let estimate = self.estimate(order);
+        if !estimate.distance.is_finite() {
+            return Err(TourError::TooLong);
+        }
+
+        // the arrivals are times of day, so they have to stay before midnight
+        let duration = seconds(estimate.duration)?.num_seconds();
+        if i64::from(self.start.num_seconds_from_midnight()) + duration >= 24 * 60 * 60 {
+            return Err(TourError::PastMidnight);
+        }
+
+        Ok(Summary {
+            stops: order.iter().map(|i| self.stops[*i]).collect(),
+            distance: estimate.distance,
+            duration,
+            arrivals: estimate
+                .arrivals
+                .iter()
+                .map(|a| Ok(self.start + seconds(*a)?))
+                .collect::<Result<_, _>>()?,
+            late: order
+                .iter()
+                .zip(&estimate.arrivals)
+                .filter(|(i, a)| self.windows[**i].map_or(false, |(_, to)| **a > to + EPSILON))
+                .map(|(i, _)| self.stops[*i])
+                .collect(),
+        })
+    }
+
+    fn estimate(&self, order: &[usize]) -> Estimate {
+        let mut estimate = Estimate {
+            distance: 0.0,
+            duration: 0.0,
+            late: 0.0,
+            arrivals: Vec::with_capacity(order.len()),
+        };
+
+        let mut previous = 0;
+        for stop in order {
+            let node = stop + 1;
+            estimate.distance += self.distance[previous][node];
+            estimate.duration += self.duration[previous][node];
+
+            if let Some((from, to)) = self.windows[*stop] {
+                estimate.duration = estimate.duration.max(from);
+                estimate.late += (estimate.duration - to).max(0.0);
+            }
+            estimate.arrivals.push(estimate.duration);
+
+            estimate.duration += self.service;
+            previous = node;
+        }
+        estimate.distance += self.distance[previous][0];
+        estimate.duration += self.duration[previous][0];
+
+        estimate
+    }
+
+    /// Get the order given by always driving to the nearest stop that wasn't visited yet.
+    fn nearest_neighbour(&self) -> Vec<usize> {
+        let mut remaining: Vec<_> = (0..self.stops.len()).collect();
+        let mut order = Vec::with_capacity(remaining.len());
+
+        let mut previous = 0;
+        while !remaining.is_empty() {
+            let (index, _) = remaining
+                .iter()
+                .enumerate()
+                .min_by(|(_, a), (_, b)| {
+                    let time = &self.duration[previous];
+                    time[**a + 1]
+                        .partial_cmp(&time[**b + 1])
+                        .expect("travel time is not a number")
+                })
+                .expect("no remaining stop");
+
+            let stop = remaining.remove(index);
+            order.push(stop);
+            previous = stop + 1;
+        }
+
+        order
+    }
+
+    /// Reverse segments of `order` as long as that makes the tour better.
+    fn two_opt(&self, mut order: Vec<usize>) -> Vec<usize> {
+        let mut best = self.estimate(&order);
+
+        let mut improved = true;
+        while improved {
+            improved = false;
+            for i in 0..order.len() {
+                for j in i + 1..order.len() {
+                    order[i..=j].reverse();
+
+                    let estimate = self.estimate(&order);
+                    if estimate.is_better(&best) {
+                        best = estimate;
+                        improved = true;
+                    } else {
+                        order[i..=j].reverse();
+                    }
+                }
+            }
+        }
+
+        order
+    }
+}
+
+/// Convert `value` seconds to a [`Duration`], rounded to whole seconds.
+fn seconds(value: f64) -> Result<Duration, TourError> {
+    // the bound is rounded up when converted, so it has to be excluded
+    let max = Duration::max_value().num_seconds() as f64;
+    let value = value.round();
+    if value.is_finite() && value.abs() < max {
+        Ok(Duration::seconds(value as i64))
+    } else {
+        Err(TourError::TooLong)
+    }
+}
+
+/// Get the value of `f` for every pair of `places`.
+fn matrix<T>(
+    places: &[T],
+    f: impl Fn(&T, &T) -> Result<f64, TourError>,
+) -> Result<Vec<Vec<f64>>, TourError> {
+    places
+        .iter()
+        .enumerate()
+        .map(|(i, a)| {
+            places
+                .iter()
+                .enumerate()
+                .map(|(j, b)| if i == j { Ok(0.0) } else { f(a, b) })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Create a tour with stops along a line, at `positions` seconds away from the depot.
+    fn tour(positions: &[f64], windows: Vec<Option<(f64, f64)>>, service: f64) -> Tour {
+        let mut places = vec![0.0];
+        places.extend(positions);
+        let duration = matrix(&places, |a, b| Ok((a - b).abs())).unwrap();
+
+        Tour {
+            stops: positions.iter().map(|_| Uuid::new_v4()).collect(),
+            start: NaiveTime::from_hms(8, 0, 0),
+            distance: duration.clone(),
+            duration,
+            windows,
+            service,
+        }
+    }
+
+    #[test]
+    fn two_opt_removes_detours() {
+        let tour = tour(&[3.0, 1.0, 2.0], vec![None; 3], 0.0);
+        assert_eq!(tour.estimate(&[0, 1, 2]).duration, 8.0);
+        assert_eq!(tour.estimate(&tour.optimize()).duration, 6.0);
+    }
+
+    #[test]
+    fn two_opt_respects_windows() {
+        // without the window of the last stop, visiting the stops in order would be best
+        let tour = tour(&[1.0, 2.0, 3.0], vec![None, None, Some((0.0, 3.0))], 1.0);
+
+        let current = tour.summary(&[0, 1, 2]).unwrap();
+        assert_eq!(current.late, vec![tour.stops[2]]);
+
+        let order = tour.optimize();
+        assert_eq!(order, vec![2, 1, 0]);
+
+        let proposed = tour.summary(&order).unwrap();
+        assert!(proposed.late.is_empty());
+        assert_eq!(proposed.duration, 9);
+        assert_eq!(
+            proposed.arrivals,
+            vec![
+                NaiveTime::from_hms(8, 0, 3),
+                NaiveTime::from_hms(8, 0, 5),
+                NaiveTime::from_hms(8, 0, 7),
+            ]
+        );
+    }
+
+    #[test]
+    fn waits_for_windows() {
+        let tour = tour(&[1.0], vec![Some((60.0, 120.0))], 0.0);
+        let summary = tour.summary(&[0]).unwrap();
+
+        assert_eq!(summary.arrivals, vec![NaiveTime::from_hms(8, 1, 0)]);
+        assert_eq!(summary.duration, 61);
+    }
+
+    #[test]
+    fn rejects_tours_past_midnight() {
+        let mut tour = tour(&[1800.0], vec![None], 0.0);
+        tour.start = NaiveTime::from_hms(22, 30, 0);
+        assert!(tour.summary(&[0]).is_ok());
+
+        tour.start = NaiveTime::from_hms(23, 0, 0);
+        assert!(matches!(tour.summary(&[0]), Err(TourError::PastMidnight)));
+    }
+
+    #[test]
+    fn windows_before_start_are_late() {
+        let mut tour = tour(&[1.0], vec![None], 0.0);
+        tour.windows = vec![Some((-120.0, -60.0))];
+
+        let summary = tour.summary(&[0]).unwrap();
+        assert_eq!(summary.arrivals, vec![NaiveTime::from_hms(8, 0, 1)]);
+        assert_eq!(summary.late, vec![tour.stops[0]]);
+    }
+
+    #[test]
+    fn rejects_times_out_of_range() {
+        let tour = tour(&[1e300], vec![None], 0.0);
+        assert!(matches!(tour.summary(&[0]), Err(TourError::TooLong)));
+    }
+}
@@ -0,0 +1,87 @@
+use chrono::NaiveDate;
+use tauri::WebviewMut;
+use uuid::Uuid;
+
+/// The name of the tauri event all data change events are emitted as.
+pub const DATA_CHANGED: &str = "dataChanged";
+
+/// Describes a change of the application data.
+///
+/// Events are collected while a command is executed and emitted to the frontend once the command
+/// finished. This happens even if the command failed, as it may have changed data before that.
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(tag = "event", rename_all = "camelCase")]
+pub enum Event {
+    /// A new entity was added.
+    Added { entity: Entity },
+    /// An existing entity was changed.
+    Updated { entity: Entity },
+    /// An entity was deleted or marked as deleted.
+    Deleted { entity: Entity },
+    /// The active dataset was closed.
+    DatasetClosed,
+    /// A new dataset was created and is now active.
+    DatasetCreated { year: i32, month: u32 },
+    /// An existing dataset was opened and is now active.
+    DatasetOpened { year: i32, month: u32 },
+    /// The active dataset was written to disk.
+    DatasetSaved { year: i32, month: u32 },
+}
+
+impl Event {
+    /// Check whether the event describes a change of the active datasets content.
+    pub fn is_change(&self) -> bool {
+        match self {
+            Self::Added { entity } | Self::Updated { entity } | Self::Deleted { entity } => {
+                entity.in_dataset()
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Identifies a single entity.
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Entity {
+    Calendar,
+    Category { uuid: Uuid },
+    Component { uuid: Uuid },
+    Customer { uuid: Uuid },
+    Day { date: NaiveDate },
+    Diet { uuid: Uuid },
+    Meal { uuid: Uuid },
+    Metadata,
+    Option { component: Uuid, uuid: Uuid },
+    Order { uuid: Uuid },
+    Route { uuid: Uuid },
+    StandingOrder { uuid: Uuid },
+    Template { uuid: Uuid },
+    Variant { component: Uuid, uuid: Uuid },
+}
+
+impl Entity {
+    /// Check whether the entity is part of the active dataset, rather than stored on its own.
+    pub fn in_dataset(&self) -> bool {
+        !matches!(
+            self,
+            Self::Calendar
+                | Self::Customer { .. }
+                | Self::Diet { .. }
+                | Self::Route { .. }
+                | Self::StandingOrder { .. }
+                | Self::Template { .. }
+        )
+    }
+}
+
+/// Emit all `events` to the frontend.
+///
+/// Failing to emit an event is not considered an error, as the change itself already happened.
+pub fn emit(webview: &mut WebviewMut, events: Vec<Event>) {
+    for event in events {
+        if let Err(e) = tauri::event::emit(webview, DATA_CHANGED, Some(event)) {
+            log::warn!("failed to emit event: {}", e);
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use chrono::{DateTime, Local};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::{
+    fs::{create_dir_all, rename, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// The name of the current log file.
+const FILE_NAME: &str = "mow.log";
+/// The size in bytes after which the current log file is rotated.
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+/// The number of rotated log files that are kept.
+const MAX_FILES: usize = 5;
+
+/// A single log entry.
+///
+/// Entries are stored as one JSON object per line.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct Entry {
+    pub time: DateTime<Local>,
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+}
+
+/// Logger writing to a set of rotating log files.
+pub struct Logger {
+    dir: PathBuf,
+    /// The current log file. Only `None` while rotating or if rotating failed.
+    file: Mutex<Option<File>>,
+}
+
+impl Logger {
+    /// Install a new logger writing to `dir` as the global logger.
+    pub fn init(dir: PathBuf, level: LevelFilter) -> io::Result<()> {
+        create_dir_all(&dir)?;
+        let file = open(&dir)?;
+
+        log::set_boxed_logger(Box::new(Self {
+            dir,
+            file: Mutex::new(Some(file)),
+        }))
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        log::set_max_level(level);
+
+        Ok(())
+    }
+
+    /// Read the latest `limit` entries from the log files in `dir`, oldest first.
+    ///
+    /// Lines that can't be parsed are skipped.
+    pub fn read(dir: &Path, limit: usize) -> io::Result<Vec<Entry>> {
+        let mut entries = Vec::new();
+
+        // the current file holds the newest entries, rotated files get older with their index
+        for index in 0..(MAX_FILES + 1) {
+            let file = match File::open(file_name(dir, index)) {
+                Ok(file) => file,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+
+            let mut lines = Vec::new();
+            for line in BufReader::new(file).lines() {
+                if let Ok(entry) = serde_json::from_str::<Entry>(&line?) {
+                    lines.push(entry);
+                }
+            }
+
+            entries.extend(lines.into_iter().rev());
+            if entries.len() >= limit {
+                break;
+            }
+        }
+
+        entries.truncate(limit);
+        entries.reverse();
+
+        Ok(entries)
+    }
+
+    /// Rotate the log files if the current one is too big.
+    fn rotate(&self, file: &mut Option<File>) -> io::Result<()> {
+        if let Some(f) = file {
+            if f.metadata()?.len() < MAX_FILE_SIZE {
+                return Ok(());
+            }
+        }
+
+        // the current file has to be closed before it can be renamed on some platforms
+        *file = None;
+        for index in (0..MAX_FILES).rev() {
+            let from = file_name(&self.dir, index);
+            if from.is_file() {
+                rename(from, file_name(&self.dir, index + 1))?;
+            }
+        }
+
+        *file = Some(open(&self.dir)?);
+        Ok(())
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let entry = Entry {
+            time: Local::now(),
+            level: record.level(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+        };
+
+        #[cfg(debug_assertions)]
+        eprintln!("[{}] {}: {}", entry.level, entry.target, entry.message);
+
+        let mut file = match self.file.lock() {
+            Ok(file) => file,
+            Err(_) => return,
+        };
+        if let Err(e) = self.rotate(&mut file) {
+            eprintln!("failed to rotate log files: {}", e);
+        }
+        if let (Some(file), Ok(line)) = (&mut *file, serde_json::to_string(&entry)) {
+            if let Err(e) = writeln!(file, "{}", line) {
+                eprintln!("failed to write log entry: {}", e);
+            }
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            if let Some(file) = &mut *file {
+                let _ = file.flush();
+            }
+        }
+    }
+}
+
+/// Get the name of a log file. Index `0` is the current file.
+fn file_name(dir: &Path, index: usize) -> PathBuf {
+    let mut file = dir.to_path_buf();
+    if index == 0 {
+        file.push(FILE_NAME);
+    } else {
+        file.push(format!("{}.{}", FILE_NAME, index));
+    }
+
+    file
+}
+
+/// Open the current log file for appending.
+fn open(dir: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(file_name(dir, 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn write_entries(dir: &Path, index: usize, messages: &[&str]) {
+        let mut file = File::create(file_name(dir, index)).unwrap();
+        for message in messages {
+            let entry = Entry {
+                time: Local.ymd(2021, 3, 15).and_hms(12, 0, 0),
+                level: Level::Info,
+                target: "test".to_string(),
+                message: message.to_string(),
+            };
+            writeln!(file, "{}", serde_json::to_string(&entry).unwrap()).unwrap();
+        }
+    }
+
+    fn messages(entries: &[Entry]) -> Vec<&str> {
+        entries.iter().map(|e| e.message.as_str()).collect()
+    }
+
+    #[test]
+    fn reads_latest_entries_across_files() {
+        let dir = tempfile::tempdir().expect("failed to create temporary directory");
+        write_entries(dir.path(), 2, &["a", "b"]);
+        write_entries(dir.path(), 1, &["c", "d"]);
+        write_entries(dir.path(), 0, &["e"]);
+
+        let entries = Logger::read(dir.path(), 10).unwrap();
+        assert_eq!(messages(&entries), ["a", "b", "c", "d", "e"]);
+
+        let entries = Logger::read(dir.path(), 2).unwrap();
+        assert_eq!(messages(&entries), ["d", "e"]);
+    }
+
+    #[test]
+    fn rotates_full_files() {
+        let dir = tempfile::tempdir().expect("failed to create temporary directory");
+        write_entries(dir.path(), 1, &["a"]);
+        write_entries(dir.path(), 0, &["b"]);
+
+        let file = open(dir.path()).unwrap();
+        let logger = Logger {
+            dir: dir.path().to_path_buf(),
+            file: Mutex::new(Some(file)),
+        };
+
+        let mut file = logger.file.lock().unwrap();
+        logger.rotate(&mut file).unwrap();
+        assert_eq!(messages(&Logger::read(dir.path(), 10).unwrap()), ["a", "b"]);
+
+        file.as_ref().unwrap().set_len(MAX_FILE_SIZE).unwrap();
+        logger.rotate(&mut file).unwrap();
+        assert!(!file_name(dir.path(), 3).exists());
+        assert_eq!(file.as_ref().unwrap().metadata().unwrap().len(), 0);
+        assert_eq!(messages(&Logger::read(dir.path(), 10).unwrap()), ["a", "b"]);
+        assert!(file_name(dir.path(), 2).is_file());
+    }
+}
@@ -3,40 +3,61 @@
     windows_subsystem = "windows"
 )]
 
+mod clock;
 mod cmd;
+mod context;
 mod data;
+mod event;
+mod logger;
+mod recovery;
+mod settings;
+mod store;
 
-use data::Data;
+use context::{Context, Paths};
 use dotenv::dotenv;
-use std::{
-    env,
-    fs::File,
-    path::PathBuf,
-    sync::{Mutex, RwLock},
-};
-
-lazy_static::lazy_static! {
-    static ref PROJECT_DIRS: directories::ProjectDirs =
-        directories::ProjectDirs::from("dev", "tfld", "Meals on Wheels")
-        .expect("unable to find default directories");
-    static ref DATADIR: PathBuf = if let Ok(x) = env::var("MOW_DATADIR") {
-        PathBuf::from(x)
-    } else {
-        PROJECT_DIRS.data_dir().to_path_buf()
-    };
-    static ref DATA: RwLock<Option<(Data, Mutex<(File,File)>)>> = RwLock::new(None);
-}
+use logger::Logger;
+use settings::Settings;
+use std::{panic, sync::Arc};
 
 fn main() {
     #[cfg(debug_assertions)]
     dotenv().expect("dotenv loading failed");
 
+    let paths = Paths::from_env();
+    let settings = Settings::load(&paths.settings_file());
+    let level = match &settings {
+        Ok(settings) => settings.log_level,
+        Err(_) => Settings::default().log_level,
+    };
+    if let Err(e) = Logger::init(paths.log_dir.clone(), level) {
+        eprintln!("failed to initialize logging: {}", e);
+    }
+    let settings = settings.unwrap_or_else(|e| {
+        log::warn!("failed to load settings, using defaults: {}", e);
+        Settings::default()
+    });
+
+    // keep a record of panics in the log file
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        log::error!("{}", info);
+        default_hook(info);
+    }));
+    log::info!(
+        "starting {} {}",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION")
+    );
+
+    recovery::import_legacy(&paths);
+    let ctx = Arc::new(Context::new(paths, settings, clock::from_env()));
+
     tauri::AppBuilder::new()
-        .invoke_handler(|webview, arg| match serde_json::from_str(arg) {
+        .invoke_handler(move |webview, arg| match serde_json::from_str(arg) {
             Err(e) => Err(e.to_string()),
             Ok(command) => {
                 let cmd: cmd::Cmd = command;
-                cmd.execute(webview)
+                cmd.execute(webview, ctx.clone())
             }
         })
         .build()
@@ -0,0 +1,322 @@
+//! Crash recovery copies of datasets.
+//!
+//! Once the active dataset is changed for the first time, a new recovery copy is started at
+//! `<data dir>/recovery/<year>/<month>/<id>.ron`, where the id is the local time the copy was
+//! started at. The copy is updated after every change and on every save, so it is newer than the
+//! dataset file itself if the application stopped before the dataset was saved. Datasets that are
+//! only opened and closed don't get a copy.
+//!
+//! Older versions kept a single copy per dataset in the cache directory. Those are moved to the
+//! recovery directory on startup, see [`import_legacy`].
+
+use crate::{
+    context::{Context, Paths},
+    data::Data,
+};
+use chrono::{DateTime, Local};
+use fs2::FileExt;
+use ron::ser::to_writer;
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs::{copy, create_dir_all, read_dir, remove_file, rename, File, OpenOptions},
+    hash::{Hash, Hasher},
+    io::{self, BufWriter, Seek, SeekFrom},
+    path::PathBuf,
+};
+
+/// The format of recovery copy ids.
+const ID_FORMAT: &str = "%Y-%m-%dT%H-%M-%S";
+/// The number of recovery copies that are kept for every dataset.
+const MAX_COPIES: usize = 10;
+
+/// Information about a single recovery copy.
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveryCopy {
+    /// Identifies the copy within its dataset.
+    pub id: String,
+    /// The time the copy was last written.
+    pub modified: DateTime<Local>,
+    /// true, if the copy was written after the dataset file
+    pub is_newer: bool,
+}
+
+/// The files of the active dataset.
+pub struct Files {
+    year: i32,
+    month: u32,
+    /// The permanent file.
+    pub dataset: File,
+    /// The recovery copy and its path, once one was started.
+    copy: Option<(File, PathBuf)>,
+}
+
+impl Files {
+    /// Create the files of the dataset of `month` in `year`, without a recovery copy.
+    pub fn new(year: i32, month: u32, dataset: File) -> Self {
+        Self {
+            year,
+            month,
+            dataset,
+            copy: None,
+        }
+    }
+
+    /// Write `data` to the recovery copy, starting a new one at `now` if there is none yet.
+    ///
+    /// Returns `true` if a new copy was started.
+    pub fn write_copy(
+        &mut self,
+        paths: &Paths,
+        now: DateTime<Local>,
+        data: &Data,
+    ) -> Result<bool, ron::Error> {
+        let started = self.copy.is_none();
+        if started {
+            let path = paths.recovery_file(self.year, self.month, &new_id(now));
+            if let Some(x) = path.parent() {
+                create_dir_all(x)?;
+            }
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .open(&path)?;
+            file.try_lock_exclusive()?;
+
+            self.copy = Some((file, path));
+            if let Err(e) = prune(paths, self.year, self.month) {
+                log::warn!("failed to prune recovery copies: {}", e);
+            }
+        }
+
+        let (file, _) = self.copy.as_ref().expect("recovery copy vanished");
+        write(file, data)?;
+        Ok(started)
+    }
+
+    /// Remove the recovery copy.
+    ///
+    /// Failures are logged, as the copy only takes up space.
+    pub fn discard_copy(&mut self) {
+        if let Some((file, path)) = self.copy.take() {
+            drop(file);
+            if let Err(e) = remove_file(&path) {
+                log::warn!("failed to remove recovery copy {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+/// Get the id for a new recovery copy started at `now`.
+pub fn new_id(now: DateTime<Local>) -> String {
+    now.format(ID_FORMAT).to_string()
+}
+
+/// Check whether `id` is a valid recovery copy id.
+pub fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == '-' || c == 'T')
+}
+
+/// Get all recovery copies of a dataset, newest first.
+pub fn list(paths: &Paths, year: i32, month: u32) -> io::Result<Vec<RecoveryCopy>> {
+    let dataset_modified = paths
+        .dataset_file(year, month)
+        .metadata()
+        .and_then(|m| m.modified())
+        .ok();
+
+    let entries = match read_dir(paths.recovery_dir(year, month)) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut copies = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let id = match path.file_stem().and_then(|x| x.to_str()) {
+            Some(id) if is_valid_id(id) => id.to_string(),
+            _ => continue,
+        };
+
+        let modified = path.metadata()?.modified()?;
+        copies.push(RecoveryCopy {
+            id,
+            modified: modified.into(),
+            is_newer: match dataset_modified {
+                Some(x) => modified > x,
+                None => true,
+            },
+        });
+    }
+
+    copies.sort_by(|a, b| b.id.cmp(&a.id));
+    Ok(copies)
+}
+
+/// Remove the oldest recovery copies of a dataset, so only the newest ones are kept.
+///
+/// Copies that are newer than the dataset file hold changes that were never saved, so they are
+/// only removed once all other copies are gone. Copies that can't be removed, e.g. because
+/// another instance uses them, are skipped.
+pub fn prune(paths: &Paths, year: i32, month: u32) -> io::Result<()> {
+    let mut copies = list(paths, year, month)?;
+    // stable, so both groups stay sorted newest first
+    copies.sort_by_key(|c| !c.is_newer);
+
+    for copy in copies.into_iter().skip(MAX_COPIES) {
+        let path = paths.recovery_file(year, month, &copy.id);
+        if let Err(e) = remove_file(&path) {
+            log::warn!("failed to remove recovery copy {}: {}", path.display(), e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Move the recovery copies older versions kept in the cache directory to the recovery
+/// directory.
+///
+/// Older versions stored a single copy per dataset at `<cache dir>/<hash>/<year>/<month>.ron`,
+/// where the hash was derived from the data directory, see [`legacy_dir`]. Only the copies of the
+/// current data directory are moved, those of other data directories are left in place. Each
+/// becomes a copy with the id of the time it was last written. Failures are logged, so the
+/// remaining copies are still moved.
+pub fn import_legacy(paths: &Paths) {
+    let legacy = match legacy_copies(paths) {
+        Ok(x) => x,
+        Err(e) => {
+            log::warn!("failed to look for old recovery copies: {}", e);
+            return;
+        }
+    };
+
+    for (year, month, from) in legacy {
+        let result = from.metadata().and_then(|m| m.modified()).and_then(|time| {
+            let to = paths.recovery_file(year, month, &new_id(time.into()));
+            if to.exists() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} already exists", to.display()),
+                ));
+            }
+            if let Some(x) = to.parent() {
+                create_dir_all(x)?;
+            }
+
+            // the cache directory may be on another file system
+            if rename(&from, &to).is_err() {
+                copy(&from, &to)?;
+                remove_file(&from)?;
+            }
+            Ok(to)
+        });
+
+        match result {
+            Ok(to) => log::info!(
+                "moved old recovery copy {} to {}",
+                from.display(),
+                to.display()
+            ),
+            Err(e) => log::warn!("failed to move old recovery copy {}: {}", from.display(), e),
+        }
+    }
+}
+
+/// Get the directory older versions kept the recovery copies of the current data directory in.
+///
+/// The hash is computed the way those versions did. As the algorithm of `DefaultHasher` isn't
+/// guaranteed to stay the same, copies written by a build of another Rust version may be missed.
+fn legacy_dir(paths: &Paths) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    paths.data_dir.hash(&mut hasher);
+
+    paths.cache_dir.join(hasher.finish().to_string())
+}
+
+/// Find the recovery copies older versions kept for the current data directory.
+fn legacy_copies(paths: &Paths) -> io::Result<Vec<(i32, u32, PathBuf)>> {
+    let number = |path: &PathBuf| -> Option<i32> { path.file_stem()?.to_str()?.parse().ok() };
+    let dirs = |path: &PathBuf| -> io::Result<Vec<PathBuf>> {
+        match read_dir(path) {
+            Ok(entries) => entries.map(|e| e.map(|e| e.path())).collect(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    };
+
+    let mut copies = Vec::new();
+    for year_dir in dirs(&legacy_dir(paths))?.iter().filter(|p| p.is_dir()) {
+        let year = match number(year_dir) {
+            Some(x) => x,
+            None => continue,
+        };
+
+        for file in dirs(year_dir)? {
+            let is_ron = file.extension().map_or(false, |x| x == "ron");
+            match number(&file) {
+                Some(month) if is_ron && (1..=12).contains(&month) => {
+                    copies.push((year, month as u32, file))
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Ok(copies)
+}
+
+/// Replace the content of `file` with `data`.
+pub fn write(mut file: &File, data: &Data) -> Result<(), ron::Error> {
+    file.set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+
+    to_writer(BufWriter::new(file), data)
+}
+
+/// Write the active dataset to its recovery copy.
+///
+/// Failures are logged, as they shouldn't interrupt the users work.
+pub fn checkpoint(ctx: &Context) {
+    if let Some((data, files)) = &*ctx.data.read().expect("failed to get data read access") {
+        let mut files = files.lock().expect("failed to get file lock");
+        if let Err(e) = files.write_copy(&ctx.paths, ctx.clock.now(), data) {
+            log::error!("failed to write recovery copy: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::write;
+
+    #[test]
+    fn imports_copies_of_current_data_dir() {
+        let dir = tempfile::tempdir().expect("failed to create temporary directory");
+        let paths = Paths {
+            data_dir: dir.path().join("data"),
+            cache_dir: dir.path().join("cache"),
+            config_dir: dir.path().join("config"),
+            log_dir: dir.path().join("logs"),
+        };
+
+        let own = legacy_dir(&paths).join("2021").join("3.ron");
+        let other = paths.cache_dir.join("12345").join("2021").join("3.ron");
+        for file in [&own, &other].iter() {
+            create_dir_all(file.parent().unwrap()).unwrap();
+            write(file, "").unwrap();
+        }
+
+        import_legacy(&paths);
+
+        let imported: Vec<_> = read_dir(paths.recovery_dir(2021, 3)).unwrap().collect();
+        assert_eq!(imported.len(), 1);
+        assert!(!own.exists());
+        assert!(other.exists());
+    }
+}
@@ -0,0 +1,57 @@
+use log::LevelFilter;
+use ron::{de::from_reader, ser::to_writer};
+use std::{
+    env,
+    fs::{create_dir_all, File},
+    io::{BufReader, BufWriter, ErrorKind},
+    path::Path,
+};
+
+/// User settings, persisted in the configuration directory.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Settings {
+    /// The minimum level of log entries that are written to the log file.
+    pub log_level: LevelFilter,
+    /// The name changes to datasets are attributed to. Defaults to the name of the system user.
+    pub user_name: Option<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            log_level: LevelFilter::Info,
+            user_name: None,
+        }
+    }
+}
+
+impl Settings {
+    /// Load the settings from `file`.
+    ///
+    /// Returns the default settings if the file doesn't exist.
+    pub fn load(file: &Path) -> Result<Self, ron::Error> {
+        match File::open(file) {
+            Ok(f) => from_reader(BufReader::new(f)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Get the name changes to datasets are attributed to.
+    pub fn user_name(&self) -> Option<String> {
+        match &self.user_name {
+            Some(x) if !x.trim().is_empty() => Some(x.trim().to_string()),
+            _ => env::var("USERNAME").or_else(|_| env::var("USER")).ok(),
+        }
+    }
+
+    /// Write the settings to `file`.
+    pub fn save(&self, file: &Path) -> Result<(), ron::Error> {
+        if let Some(x) = file.parent() {
+            create_dir_all(x)?;
+        }
+
+        to_writer(BufWriter::new(File::create(file)?), self)
+    }
+}
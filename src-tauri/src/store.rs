@@ -0,0 +1,109 @@
+use crate::cmd::error::{CmdError, ErrorPayload};
+use ron::{de::from_reader, ser::to_string};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    fs::{create_dir_all, rename, write, File},
+    io::{self, BufReader, ErrorKind},
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// Error accessing a [`Store`].
+#[derive(Debug, thiserror::Error)]
+#[error("error.global.store: {source}")]
+pub struct StoreError {
+    /// The file of the store.
+    pub path: PathBuf,
+    pub source: ron::Error,
+}
+
+impl CmdError for StoreError {
+    fn payload(&self) -> ErrorPayload {
+        ErrorPayload::new("STORE", "error.global.store")
+            .param("path", &self.path)
+            .detail(&self.source)
+    }
+}
+
+/// Replace the content of the file at `path`.
+///
+/// The content is written to `<path>.tmp` first, which then replaces the file, so a failure can't
+/// leave a partially written file behind.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+
+    write(&tmp, contents)?;
+    rename(&tmp, path)
+}
+
+/// A value that is kept in its own file, independent of the datasets.
+///
+/// The file is read for every access, and replaced atomically when the value is changed.
+pub struct Store<T> {
+    path: PathBuf,
+    /// Serializes changes to the file.
+    lock: Mutex<()>,
+    value: PhantomData<fn() -> T>,
+}
+
+impl<T: Default + DeserializeOwned + Serialize> Store<T> {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            lock: Mutex::new(()),
+            value: PhantomData,
+        }
+    }
+
+    /// Read the current value. Returns the default value if the file doesn't exist yet.
+    pub fn read(&self) -> Result<T, StoreError> {
+        let _lock = self.lock.lock().expect("failed to get store lock");
+        self.load()
+    }
+
+    /// Change the value using `f`.
+    ///
+    /// The changed value is only written if `f` succeeds.
+    pub fn update<R, E: From<StoreError>>(
+        &self,
+        f: impl FnOnce(&mut T) -> Result<R, E>,
+    ) -> Result<R, E> {
+        let _lock = self.lock.lock().expect("failed to get store lock");
+
+        let mut value = self.load()?;
+        let ret = f(&mut value)?;
+        self.save(&value)?;
+
+        Ok(ret)
+    }
+
+    fn load(&self) -> Result<T, StoreError> {
+        match File::open(&self.path) {
+            Ok(file) => from_reader(BufReader::new(file)).map_err(|e| self.error(e)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(T::default()),
+            Err(e) => Err(self.error(e.into())),
+        }
+    }
+
+    fn save(&self, value: &T) -> Result<(), StoreError> {
+        let write = || -> Result<(), ron::Error> {
+            if let Some(x) = self.path.parent() {
+                create_dir_all(x)?;
+            }
+            write_atomic(&self.path, to_string(value)?.as_bytes())?;
+
+            Ok(())
+        };
+
+        write().map_err(|e| self.error(e))
+    }
+
+    fn error(&self, source: ron::Error) -> StoreError {
+        StoreError {
+            path: self.path.clone(),
+            source,
+        }
+    }
+}
@@ -1,5 +1,6 @@
 import React from "react";
 import { promisified } from "tauri/api/tauri";
+import { listen } from "tauri/api/event";
 import { setTitle } from "tauri/api/window";
 
 import "semantic-ui-css/semantic.min.css";
@@ -18,6 +19,7 @@ class App extends React.Component {
     state: "loading",
     year: null,
     month: null,
+    revision: 0,
   };
 
   createDataset(nextMonth) {
@@ -52,6 +54,9 @@ class App extends React.Component {
           if (r.isBackup) {
             alert(t("views.select_dataset.loaded_backup"));
           }
+          if (r.hasNewerRecoveryCopy) {
+            alert(t("views.select_dataset.newer_recovery_copy"));
+          }
         }
       })
       .catch((e) => handle_error(e, t));
@@ -87,6 +92,16 @@ class App extends React.Component {
 
   componentDidMount() {
     this.update();
+
+    listen("dataChanged", (e) => {
+      if (e.payload.event.startsWith("dataset")) {
+        this.update();
+      } else {
+        this.setState((s) => {
+          return { revision: s.revision + 1 };
+        });
+      }
+    });
   }
 
   render() {
@@ -110,10 +125,10 @@ class App extends React.Component {
                 Default route
               </Route>
               <Route path="/components">
-                <Components />
+                <Components revision={this.state.revision} />
               </Route>
               <Route path="/meals">
-                <Meals />
+                <Meals revision={this.state.revision} />
               </Route>
             </Switch>
           </Container>
@@ -5,6 +5,7 @@ import { Header, Input, Table } from "semantic-ui-react";
 export default function ListEditor(props) {
   const { t } = useTranslation();
   const [add, setAdd] = useState("");
+  const keys = "order" in props ? props.order : Object.keys(props.data);
 
   return (
     <>
@@ -29,8 +30,9 @@ export default function ListEditor(props) {
           </Table.Row>
         </Table.Header>
         <Table.Body>
-          {Object.keys(props.data).length > 0 ? (
-            Object.entries(props.data).map(([k, v]) => {
+          {keys.length > 0 ? (
+            keys.map((k) => {
+              const v = props.data[k];
               return (
                 <Table.Row>
                   <Table.Cell content={v.name.trim().length > 0 ? v.name : k} />
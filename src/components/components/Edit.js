@@ -21,6 +21,7 @@ export default function Edit(props) {
         <ListEditor
           header={{ as: "h3" }}
           data={props.data.variants}
+          order={props.data.variant_order}
           title={t("views.components.edit.variants_title", {
             name: props.data.name,
           })}
@@ -46,6 +47,7 @@ export default function Edit(props) {
         <ListEditor
           header={{ as: "h3" }}
           data={props.data.options}
+          order={props.data.option_order}
           title={t("views.components.edit.options_title", {
             name: props.data.name,
           })}
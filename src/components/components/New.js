@@ -179,7 +179,7 @@ export default function New(props) {
                 setNewComponent((nc) => {
                   return {
                     ...nc,
-                    error: e,
+                    error: e.key,
                   };
                 })
               );
@@ -1,16 +1,17 @@
 export function handle_error(error, t) {
   console.error(error);
 
-  let components = error.split(":");
-
-  let error_string;
-  if (components.length < 1) {
-    error_string = error;
-  } else {
-    error_string = components[0];
+  // backend errors are structured payloads, everything else is shown as is
+  if (typeof error !== "object" || error === null || !("key" in error)) {
+    alert(`${t("error.undefined")}\n\n${error}`);
+    return;
   }
 
-  alert(`${t([error_string, "error.undefined"])}\n\n${error}`);
+  let message = t([error.key, "error.undefined"], error.params);
+  let detail =
+    error.detail === null ? error.code : `${error.code}: ${error.detail}`;
+
+  alert(`${message}\n\n${detail}`);
 }
 
 export function handle_unexpected_variant(expected, received, t) {
@@ -20,8 +21,12 @@ export function handle_unexpected_variant(expected, received, t) {
     return true;
   } else {
     handle_error(
-      `error.unexpected_variant: expected variant "${expected}", but ` +
-        `received variant "${received}"`,
+      {
+        code: "UNEXPECTED_VARIANT",
+        key: "error.unexpected_variant",
+        params: { expected: expected, received: received },
+        detail: `expected variant "${expected}", but received "${received}"`,
+      },
       t
     );
     return false;
@@ -6,15 +6,19 @@ import EditComponent from "../components/components/Edit";
 import NewComponent from "../components/components/New";
 import { handle_unexpected_variant } from "../error";
 
-export default function Components() {
+export default function Components(props) {
   const { t } = useTranslation();
   const [newDialog, setNewDialog] = useState(false);
   const [editor, setEditor] = useState({ open: false, uuid: null });
-  const [components, setComponents] = useState({ loading: true, data: [] });
+  const [components, setComponents] = useState({
+    loading: true,
+    data: {},
+    order: [],
+  });
 
   useEffect(() => {
     update(setComponents, t);
-  }, [setComponents, t]);
+  }, [setComponents, t, props.revision]);
 
   return (
     <React.Fragment>
@@ -51,7 +55,8 @@ export default function Components() {
           </Table.Row>
         </Table.Header>
         <Table.Body>
-          {Object.entries(components.data).map(([k, v]) => {
+          {components.order.map((k) => {
+            const v = components.data[k];
             return (
               <Table.Row key={k}>
                 <Table.Cell>{v.name}</Table.Cell>
@@ -110,13 +115,13 @@ function update(setComponents, t) {
     .then((r) => {
       if (handle_unexpected_variant("gotComponents", r.variant, t)) {
         setComponents((c) => {
-          return { ...c, loading: false, data: r.data };
+          return { ...c, loading: false, data: r.data, order: r.order };
         });
       }
     })
     .catch((e) => {
       setComponents((c) => {
-        return { ...c, loading: false, data: [] };
+        return { ...c, loading: false, data: {}, order: [] };
       });
     });
 }
@@ -5,10 +5,10 @@ import New from "../components/meals/New";
 import { promisified } from "tauri/api/tauri";
 import { handle_unexpected_variant, handle_error } from "../error";
 
-export default function Meals() {
+export default function Meals(props) {
   const { t } = useTranslation();
   const [add, setAdd] = useState(false);
-  const [meals, setMeals] = useState({});
+  const [meals, setMeals] = useState({ data: {}, order: [] });
   const [components, setComponents] = useState({});
 
   useEffect(() => {
@@ -20,7 +20,7 @@ export default function Meals() {
         }
       })
       .catch((e) => handle_error(e, t));
-  }, [setComponents, setMeals, t]);
+  }, [setComponents, setMeals, t, props.revision]);
 
   return (
     <>
@@ -44,6 +44,9 @@ export default function Meals() {
           <Table.Row>
             <Table.HeaderCell content={t("views.meals.list.column_name")} />
             <Table.HeaderCell content={t("views.meals.list.column_short")} />
+            <Table.HeaderCell
+              content={t("views.meals.list.column_allergens")}
+            />
             <Table.HeaderCell
               collapsing
               content={t("views.meals.list.column_actions")}
@@ -51,12 +54,12 @@ export default function Meals() {
           </Table.Row>
         </Table.Header>
         <Table.Body>
-          {Object.keys(meals).length > 0 ? (
-            Object.entries(meals).map(([k, v]) => renderRow(k, v))
+          {meals.order.length > 0 ? (
+            meals.order.map((k) => renderRow(k, meals.data[k]))
           ) : (
             <Table.Row>
               <Table.Cell
-                colSpan={3}
+                colSpan={4}
                 content={t("views.meals.list.empty")}
                 textAlign="center"
               />
@@ -80,6 +83,7 @@ function renderRow(uuid, meal) {
     <Table.Row key={uuid}>
       <Table.Cell content={meal.name} />
       <Table.Cell content={meal.short} />
+      <Table.Cell content={meal.footnote} />
       <Table.Cell content={<i>Coming soon</i>} textAlign="center" />
     </Table.Row>
   );
@@ -89,7 +93,7 @@ function update(setMeals, t) {
   promisified({ cmd: "meal", sub: { cmd: "getMeals" } })
     .then((r) => {
       if (handle_unexpected_variant("gotMeals", r.variant, t)) {
-        setMeals(r.data);
+        setMeals({ data: r.data, order: r.order });
       }
     })
     .catch((e) => handle_error(e, t));